@@ -1,4 +1,7 @@
-use clap::{Parser, Subcommand};
+use clap::{ArgAction, Parser, Subcommand, ValueEnum};
+
+use crate::deps::GraphFormat;
+use crate::forge::MergeMethod;
 
 #[derive(Parser)]
 #[command(name = "aidd", about = "AI-Driven Development CLI")]
@@ -6,9 +9,29 @@ pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
 
-    /// Enable verbose output
-    #[arg(short, long, global = true)]
-    pub verbose: bool,
+    /// Enable verbose output (-v: external commands, -vv: their output too)
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Only print warnings and errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Print the commands and file writes that would run, without running them
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    /// How failures are reported on stderr
+    #[arg(long, global = true, value_enum, default_value_t = ErrorFormat::Text)]
+    pub error_format: ErrorFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ErrorFormat {
+    /// `ERROR: <message>` followed by a hint
+    Text,
+    /// A single `{"error": {"kind", "exit_code", "message", "hint"}}` line
+    Json,
 }
 
 #[derive(Subcommand)]
@@ -23,6 +46,11 @@ pub enum Commands {
         #[command(subcommand)]
         action: IssueAction,
     },
+    /// Task plan maintenance
+    Plan {
+        #[command(subcommand)]
+        action: PlanAction,
+    },
     /// Pull request operations
     Pr {
         #[command(subcommand)]
@@ -30,6 +58,58 @@ pub enum Commands {
     },
     /// Show status of all issues
     Status,
+    /// Stage all changes and commit with a validated Conventional Commits message
+    Commit {
+        /// Issue number
+        issue: u32,
+        /// Task number whose Commit Plan is used (default: task whose branchName matches)
+        #[arg(long)]
+        task: Option<u32>,
+        /// Commit message (overrides the proposed message)
+        #[arg(short, long)]
+        message: Option<String>,
+    },
+    /// Generate a changelog from merged Conventional Commits
+    Changelog {
+        /// Start after this tag (default: latest tag)
+        #[arg(long)]
+        since: Option<String>,
+        /// Section heading (default: Unreleased)
+        #[arg(long)]
+        version: Option<String>,
+        /// Prepend the section to this file (e.g. CHANGELOG.md) instead of printing it
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// Run the pre-PR review checklist against an issue's branch
+    ReviewCheck {
+        /// Issue number
+        issue: u32,
+        /// Task number (default: task whose branchName matches)
+        #[arg(long)]
+        task: Option<u32>,
+    },
+}
+
+impl Commands {
+    /// Short name of the subcommand, used to name its log file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Commands::Wt { action: WtAction::Ensure { .. } } => "wt-ensure",
+            Commands::Wt { action: WtAction::Remove { .. } } => "wt-remove",
+            Commands::Issue { action: IssueAction::Create { .. } } => "issue-create",
+            Commands::Issue { action: IssueAction::Plan { .. } } => "issue-plan",
+            Commands::Plan { action: PlanAction::Estimate { .. } } => "plan-estimate",
+            Commands::Plan { action: PlanAction::Graph { .. } } => "plan-graph",
+            Commands::Pr { action: PrAction::Create { .. } } => "pr-create",
+            Commands::Pr { action: PrAction::Merge { .. } } => "pr-merge",
+            Commands::Pr { action: PrAction::Restack { .. } } => "pr-restack",
+            Commands::Status => "status",
+            Commands::Commit { .. } => "commit",
+            Commands::Changelog { .. } => "changelog",
+            Commands::ReviewCheck { .. } => "review-check",
+        }
+    }
 }
 
 #[derive(Subcommand)]
@@ -42,6 +122,9 @@ pub enum WtAction {
         issue: u32,
         /// Short task summary used in branch/worktree name (e.g. add-library-registration)
         summary: String,
+        /// Task number; records the branch in TASK.md and stacks on its parentTask
+        #[arg(long)]
+        task: Option<u32>,
     },
     /// Remove worktree + clean up branch
     Remove {
@@ -52,18 +135,83 @@ pub enum WtAction {
 
 #[derive(Subcommand)]
 pub enum IssueAction {
+    /// Open an issue from a markdown draft and print its number
+    Create {
+        /// Draft file: frontmatter with title, labels and assignees, then the issue body
+        #[arg(long, value_name = "FILE")]
+        from: String,
+        /// Run `issue plan` on the new issue right away
+        #[arg(long)]
+        plan: bool,
+    },
     /// Generate PLAN.md from a GitHub issue
     Plan {
         /// Issue number
         issue: u32,
+        /// Update an existing PLAN.md instead, keeping manual edits
+        #[arg(long)]
+        sync: bool,
+        /// Also plan the issues that tasks link to (`- [ ] #42`, sub-issues), recursively
+        #[arg(long, conflicts_with_all = ["from_file", "stdin"])]
+        recursive: bool,
+        /// Read the issue from a file instead of the forge: `gh issue view --json`
+        /// output or markdown whose first line is the title
+        #[arg(long, value_name = "FILE", conflicts_with = "stdin")]
+        from_file: Option<String>,
+        /// Read the issue from stdin, in the same formats as --from-file
+        #[arg(long)]
+        stdin: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum PlanAction {
+    /// Set a task's size (XS, S, M, L, XL) and update the PLAN.md total
+    Estimate {
+        /// Issue number
+        issue: u32,
+        /// Task number
+        task: u32,
+        /// T-shirt size
+        size: String,
+    },
+    /// Print the graph of task dependencies (TASK.md `dependsOn`)
+    Graph {
+        /// Issue number
+        issue: u32,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = GraphFormat::Mermaid)]
+        format: GraphFormat,
     },
 }
 
 #[derive(Subcommand)]
 pub enum PrAction {
-    /// Push branch and create a pull request
+    /// Run review checks, push branch and create a pull request
     Create {
         /// Issue number
         issue: u32,
+        /// Task number (default: task whose branchName matches)
+        #[arg(long)]
+        task: Option<u32>,
+        /// Create the PR even if review checks fail
+        #[arg(long)]
+        skip_review: bool,
+    },
+    /// Merge the pull request, then clean up worktree, branches and statuses
+    Merge {
+        /// Issue number
+        issue: u32,
+        /// Task number to mark as done (default: task whose branchName matches)
+        #[arg(long)]
+        task: Option<u32>,
+        /// Merge method
+        #[arg(long, value_enum, default_value_t = MergeMethod::Squash)]
+        method: MergeMethod,
+    },
+    /// Rebase stacked tasks whose parent merged and retarget their PRs
+    Restack {
+        /// Issue number
+        issue: u32,
     },
 }
@@ -0,0 +1,110 @@
+use anyhow::{Context, Result};
+use jiff::tz::TimeZone;
+use jiff::Timestamp;
+
+use crate::error::{Error, ErrorKind};
+
+/// Source of the current time.
+///
+/// Commands read the time through their `Repo` so tests can pin it.
+pub trait Clock {
+    fn now(&self) -> Timestamp;
+}
+
+/// The system clock.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Timestamp {
+        Timestamp::now()
+    }
+}
+
+/// A clock stopped at one instant, for tests.
+#[cfg(test)]
+pub struct FixedClock(pub Timestamp);
+
+#[cfg(test)]
+impl Clock for FixedClock {
+    fn now(&self) -> Timestamp {
+        self.0
+    }
+}
+
+/// A timestamp as written to generated files: `2026-01-01T00:00:00Z`.
+pub fn format_utc(time: Timestamp) -> String {
+    time.strftime("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+/// The time zone for human-facing output: `name` (an IANA name such as
+/// `Asia/Tokyo`) or the system's time zone.
+pub fn time_zone(name: Option<&str>) -> Result<TimeZone> {
+    match name {
+        Some(name) => TimeZone::get(name).with_context(|| {
+            Error::new(ErrorKind::Validation, format!("Unknown time zone '{name}'"))
+                .with_hint("Use an IANA name such as `Asia/Tokyo` or `UTC` in [display] timezone")
+        }),
+        None => Ok(TimeZone::system()),
+    }
+}
+
+/// A timestamp for people: `2026-01-01 09:00 JST`.
+pub fn format_local(time: Timestamp, tz: &TimeZone) -> String {
+    time.to_zoned(tz.clone()).strftime("%Y-%m-%d %H:%M %Z").to_string()
+}
+
+/// How long ago `then` was, in the largest whole unit: `3 days ago`.
+pub fn age(then: Timestamp, now: Timestamp) -> String {
+    let seconds = now.duration_since(then).as_secs();
+    if seconds < 0 {
+        return "in the future".to_string();
+    }
+    const UNITS: [(i64, &str); 6] = [
+        (365 * 86_400, "year"),
+        (30 * 86_400, "month"),
+        (7 * 86_400, "week"),
+        (86_400, "day"),
+        (3_600, "hour"),
+        (60, "minute"),
+    ];
+    UNITS
+        .iter()
+        .find(|(length, _)| seconds >= *length)
+        .map(|(length, name)| match seconds / length {
+            1 => format!("1 {name} ago"),
+            n => format!("{n} {name}s ago"),
+        })
+        .unwrap_or_else(|| "just now".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> Timestamp {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_format_utc_drops_fractions() {
+        assert_eq!(format_utc(at("2026-01-01T00:00:00.75Z")), "2026-01-01T00:00:00Z");
+    }
+
+    #[test]
+    fn test_format_local() {
+        let tz = time_zone(Some("Asia/Tokyo")).unwrap();
+        assert_eq!(format_local(at("2026-01-01T00:00:00Z"), &tz), "2026-01-01 09:00 JST");
+        assert!(time_zone(Some("Mars/Olympus")).is_err());
+    }
+
+    #[test]
+    fn test_age() {
+        let now = at("2026-03-10T12:00:00Z");
+        assert_eq!(age(at("2026-03-10T11:59:30Z"), now), "just now");
+        assert_eq!(age(at("2026-03-10T11:00:00Z"), now), "1 hour ago");
+        assert_eq!(age(at("2026-03-07T09:00:00Z"), now), "3 days ago");
+        assert_eq!(age(at("2026-01-01T00:00:00Z"), now), "2 months ago");
+        assert_eq!(age(at("2024-03-01T00:00:00Z"), now), "2 years ago");
+        assert_eq!(age(at("2026-03-11T00:00:00Z"), now), "in the future");
+    }
+}
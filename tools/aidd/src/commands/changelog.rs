@@ -0,0 +1,342 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+
+use crate::conventional;
+use crate::frontmatter::parse_plan_frontmatter;
+use crate::helpers::{info, warn};
+use crate::repo::Repo;
+
+/// Field separator for `git log --format` output.
+const SEP: char = '\x1f';
+
+/// Section headings per commit type, in output order.
+const TYPE_HEADINGS: &[(&str, &str)] = &[
+    ("feat", "Features"),
+    ("fix", "Bug Fixes"),
+    ("refactor", "Refactoring"),
+    ("docs", "Documentation"),
+    ("test", "Tests"),
+    ("chore", "Chores"),
+];
+
+/// A conventional commit that reached the base branch.
+#[derive(Debug, PartialEq)]
+struct Entry {
+    kind: String,
+    scope: Option<String>,
+    description: String,
+    issue: Option<u32>,
+}
+
+/// Generate a changelog section from merged Conventional Commits.
+///
+/// Collects first-parent history since `since` (default: the latest tag),
+/// groups entries by type and scope, and adds a per-issue rollup with the
+/// PLAN.md titles. Prints to stdout, or prepends to `output` if given.
+pub fn generate(
+    repo: &Repo,
+    since: Option<String>,
+    version: Option<String>,
+    output: Option<String>,
+) -> Result<()> {
+    let git = |args: &[&str]| repo.git(args);
+
+    let since = since.or_else(|| git(&["describe", "--tags", "--abbrev=0"]).ok());
+    let range = match &since {
+        Some(tag) => format!("{tag}..HEAD"),
+        None => "HEAD".to_string(),
+    };
+    info(&format!("Collecting merged commits in {range}..."));
+
+    let first_parent = git(&[
+        "log",
+        "--first-parent",
+        &format!("--format=%H{SEP}%P{SEP}%s"),
+        &range,
+    ])
+    .context("Failed to read git history")?;
+
+    let mut entries = Vec::new();
+    let mut skipped = 0;
+    for line in first_parent.lines() {
+        let mut fields = line.split(SEP);
+        let (Some(_), Some(parents), Some(subject)) = (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+
+        let parents: Vec<&str> = parents.split_whitespace().collect();
+        let collected = if let [first, second] = parents[..] {
+            // Merge commit: every commit of the merged branch gets the
+            // issue encoded in the branch name.
+            let issue = merge_branch(subject).and_then(issue_from_branch);
+            let merged_range = format!("{first}..{second}");
+            let merged =
+                git(&["log", "--no-merges", "--format=%s", &merged_range]).unwrap_or_default();
+            merged.lines().map(|s| parse_entry(s, issue)).collect()
+        } else {
+            let (issue, subject) = strip_pr_decorations(subject);
+            vec![parse_entry(subject, issue)]
+        };
+
+        for entry in collected {
+            match entry {
+                Some(entry) => entries.push(entry),
+                None => skipped += 1,
+            }
+        }
+    }
+
+    if skipped > 0 {
+        warn(&format!("Skipped {skipped} commits that do not follow Conventional Commits"));
+    }
+
+    let heading = format!(
+        "## {} ({})",
+        version.as_deref().unwrap_or("Unreleased"),
+        git(&["log", "-1", "--format=%cs"]).unwrap_or_default()
+    );
+    let issue_url = git(&["remote", "get-url", "origin"])
+        .ok()
+        .and_then(|remote| repository_url(&remote))
+        .map(|url| format!("{url}/issues"));
+    let section = render(&heading, &entries, issue_url.as_deref(), |issue| plan_title(repo, issue));
+
+    match output {
+        Some(path) => {
+            let path = repo.root.join(path);
+            let existing = repo.runner.read_file(&path).unwrap_or_default();
+            repo.runner.write_file(&path, &insert_section(&existing, &section))?;
+            info(&format!("Updated {}", path.display()));
+        }
+        None => print!("{section}"),
+    }
+    Ok(())
+}
+
+/// Convert a commit subject into a changelog entry, if it is conventional.
+fn parse_entry(subject: &str, issue: Option<u32>) -> Option<Entry> {
+    let commit = conventional::parse(subject).ok()?;
+    Some(Entry {
+        kind: commit.kind.to_string(),
+        scope: commit.scope.map(str::to_string),
+        description: commit.description.trim().to_string(),
+        issue,
+    })
+}
+
+/// Extract the branch from a "Merge pull request #N from owner/branch" subject.
+fn merge_branch(subject: &str) -> Option<&str> {
+    let (_, from) = subject.split_once(" from ")?;
+    let from = from.split_whitespace().next()?;
+    // GitHub prefixes the branch with the fork owner.
+    match from.split_once('/') {
+        Some((_, branch)) if branch.contains('/') => Some(branch),
+        _ => Some(from),
+    }
+}
+
+/// Extract the issue number from a `{prefix}/{issue}-{summary}` branch
+/// (or the older `feat/issue-{issue}-task-{task}` form).
+fn issue_from_branch(branch: &str) -> Option<u32> {
+    let (_, rest) = branch.split_once('/')?;
+    let rest = rest.strip_prefix("issue-").unwrap_or(rest);
+    rest.split('-').next()?.parse().ok()
+}
+
+/// Strip the `[TASK-<issue>-<task>]`/`[ISSUE-<issue>]` tag and the
+/// trailing ` (#<pr>)` GitHub adds to squash commits.
+///
+/// Returns the issue from the tag and the remaining subject.
+fn strip_pr_decorations(subject: &str) -> (Option<u32>, &str) {
+    let mut subject = subject.trim();
+    if let Some(idx) = subject.rfind(" (#") {
+        if subject.ends_with(')') {
+            subject = &subject[..idx];
+        }
+    }
+
+    let Some((tag, rest)) = subject.strip_prefix('[').and_then(|s| s.split_once("] ")) else {
+        return (None, subject);
+    };
+    let issue = tag
+        .strip_prefix("TASK-")
+        .or_else(|| tag.strip_prefix("ISSUE-"))
+        .and_then(|n| n.split('-').next())
+        .and_then(|n| n.parse().ok());
+    (issue, rest)
+}
+
+/// Turn a git remote URL into the repository's web URL.
+fn repository_url(remote: &str) -> Option<String> {
+    let remote = remote.trim().trim_end_matches(".git");
+    if let Some(path) = remote.strip_prefix("git@") {
+        let (host, path) = path.split_once(':')?;
+        return Some(format!("https://{host}/{path}"));
+    }
+    if remote.starts_with("https://") || remote.starts_with("http://") {
+        return Some(remote.to_string());
+    }
+    None
+}
+
+/// Read the PLAN.md title of an issue.
+fn plan_title(repo: &Repo, issue: u32) -> Option<String> {
+    let content = repo.runner.read_file(&repo.plan_file(issue)).ok()?;
+    parse_plan_frontmatter(&content).ok().map(|fm| fm.title)
+}
+
+/// Render a changelog section.
+fn render(
+    heading: &str,
+    entries: &[Entry],
+    issue_url: Option<&str>,
+    plan_title: impl Fn(u32) -> Option<String>,
+) -> String {
+    let link = |issue: u32| match issue_url {
+        Some(url) => format!("[#{issue}]({url}/{issue})"),
+        None => format!("#{issue}"),
+    };
+
+    let mut out = format!("{heading}\n\n");
+    if entries.is_empty() {
+        out.push_str("No changes.\n\n");
+        return out;
+    }
+
+    for (kind, title) in TYPE_HEADINGS {
+        // BTreeMap keeps scopes sorted, with unscoped entries first.
+        let mut by_scope: BTreeMap<Option<&str>, Vec<&Entry>> = BTreeMap::new();
+        for entry in entries.iter().filter(|e| e.kind == *kind) {
+            by_scope.entry(entry.scope.as_deref()).or_default().push(entry);
+        }
+        if by_scope.is_empty() {
+            continue;
+        }
+
+        out.push_str(&format!("### {title}\n\n"));
+        for (scope, scoped) in by_scope {
+            for entry in scoped {
+                let scope = scope.map(|s| format!("**{s}:** ")).unwrap_or_default();
+                let issue = entry.issue.map(|i| format!(" ({})", link(i))).unwrap_or_default();
+                out.push_str(&format!("- {scope}{}{issue}\n", entry.description));
+            }
+        }
+        out.push('\n');
+    }
+
+    let mut by_issue: BTreeMap<u32, Vec<&Entry>> = BTreeMap::new();
+    for entry in entries {
+        if let Some(issue) = entry.issue {
+            by_issue.entry(issue).or_default().push(entry);
+        }
+    }
+    if !by_issue.is_empty() {
+        out.push_str("### Issues\n\n");
+        for (issue, issue_entries) in by_issue {
+            let title = plan_title(issue).map(|t| format!(" {t}")).unwrap_or_default();
+            let counts: Vec<String> = TYPE_HEADINGS
+                .iter()
+                .filter_map(|(kind, _)| {
+                    let n = issue_entries.iter().filter(|e| e.kind == *kind).count();
+                    (n > 0).then(|| format!("{kind} {n}"))
+                })
+                .collect();
+            out.push_str(&format!("- {}{title} ({})\n", link(issue), counts.join(", ")));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Insert a section at the top of an existing changelog, below its `# ` title.
+fn insert_section(existing: &str, section: &str) -> String {
+    if existing.trim().is_empty() {
+        return format!("# Changelog\n\n{section}");
+    }
+    match existing.find("\n## ") {
+        Some(idx) => format!("{}{section}{}", &existing[..idx + 1], &existing[idx + 1..]),
+        None => format!("{}\n\n{section}", existing.trim_end()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_branch() {
+        assert_eq!(
+            merge_branch("Merge pull request #12 from owner/feat/11-deploy"),
+            Some("feat/11-deploy")
+        );
+        assert_eq!(merge_branch("Merge branch 'feat/3-x' into main"), None);
+    }
+
+    #[test]
+    fn test_issue_from_branch() {
+        assert_eq!(issue_from_branch("feat/11-add-deploy"), Some(11));
+        assert_eq!(issue_from_branch("feat/issue-11-task-1"), Some(11));
+        assert_eq!(issue_from_branch("main"), None);
+    }
+
+    #[test]
+    fn test_strip_pr_decorations() {
+        assert_eq!(
+            strip_pr_decorations("[TASK-11-2] feat(web): add page (#45)"),
+            (Some(11), "feat(web): add page")
+        );
+        assert_eq!(
+            strip_pr_decorations("[ISSUE-3] fix: typo"),
+            (Some(3), "fix: typo")
+        );
+        assert_eq!(strip_pr_decorations("docs: readme"), (None, "docs: readme"));
+    }
+
+    #[test]
+    fn test_repository_url() {
+        assert_eq!(
+            repository_url("git@github.com:owner/repo.git").as_deref(),
+            Some("https://github.com/owner/repo")
+        );
+        assert_eq!(
+            repository_url("https://github.com/owner/repo.git\n").as_deref(),
+            Some("https://github.com/owner/repo")
+        );
+        assert_eq!(repository_url("/tmp/origin.git"), None);
+    }
+
+    #[test]
+    fn test_render_groups_by_type_and_scope() {
+        let entries = vec![
+            parse_entry("fix(web): handle empty list", Some(2)).unwrap(),
+            parse_entry("feat(web): add page", Some(2)).unwrap(),
+            parse_entry("feat(api): add endpoint", Some(3)).unwrap(),
+            parse_entry("feat: top level", None).unwrap(),
+        ];
+        let out = render("## Unreleased", &entries, Some("https://x/issues"), |i| {
+            (i == 2).then(|| "Library registration".to_string())
+        });
+
+        let features = out.find("### Features").unwrap();
+        let fixes = out.find("### Bug Fixes").unwrap();
+        assert!(features < fixes);
+        let top = out.find("- top level").unwrap();
+        let api = out.find("- **api:** add endpoint ([#3](https://x/issues/3))").unwrap();
+        let web = out.find("- **web:** add page").unwrap();
+        assert!(top < api && api < web);
+        assert!(out.contains("- [#2](https://x/issues/2) Library registration (feat 1, fix 1)"));
+        assert!(out.contains("- [#3](https://x/issues/3) (feat 1)"));
+    }
+
+    #[test]
+    fn test_insert_section() {
+        let section = "## 0.2.0\n\n- new\n\n";
+        assert_eq!(insert_section("", section), "# Changelog\n\n## 0.2.0\n\n- new\n\n");
+        assert_eq!(
+            insert_section("# Changelog\n\n## 0.1.0\n\n- old\n", section),
+            "# Changelog\n\n## 0.2.0\n\n- new\n\n## 0.1.0\n\n- old\n"
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use anyhow::{Context, Result};
+
+use crate::conventional::{self, COMMIT_TYPES};
+use crate::error::{Error, ErrorKind};
+use crate::frontmatter::split_frontmatter;
+use crate::helpers::{info, warn, BASE_BRANCH};
+use crate::repo::Repo;
+use crate::tasks::find_task_by_branch;
+
+/// Stage all changes and commit them with a validated Conventional Commits message.
+///
+/// Without `message`, proposes one from the TASK.md Commit Plan (or the
+/// branch name) and, on a terminal, lets the user edit it before committing.
+pub fn run(repo: &Repo, issue: u32, task: Option<u32>, message: Option<String>) -> Result<()> {
+    let work_dir = repo.work_dir(issue, task)?;
+    let git = |args: &[&str]| repo.runner.run("git", args, Some(&work_dir));
+
+    let branch =
+        git(&["rev-parse", "--abbrev-ref", "HEAD"]).context("Failed to get current branch")?;
+
+    info("Staging changes...");
+    git(&["add", "-A"]).context("Failed to stage changes")?;
+    // Read the status rather than `diff --quiet`'s exit code: a dry run
+    // skips the staging above and reports every command as successful.
+    let status = git(&["status", "--porcelain"]).context("Failed to read git status")?;
+    if status.trim().is_empty() {
+        info("No changes to commit");
+        return Ok(());
+    }
+
+    let message = match message {
+        Some(message) => message,
+        None => {
+            let task = task.or_else(|| find_task_by_branch(repo, issue, &branch));
+            let plan = task
+                .and_then(|t| repo.runner.read_file(&repo.task_file(issue, t)).ok())
+                .map(|content| commit_plan_items(&content))
+                .unwrap_or_default();
+            let used: Vec<String> = git(&["log", "--format=%s", &format!("{BASE_BRANCH}..HEAD")])
+                .unwrap_or_default()
+                .lines()
+                .map(str::to_string)
+                .collect();
+
+            let proposal = propose_message(&plan, &used, &branch);
+            confirm_message(proposal)?
+        }
+    };
+
+    let commit = conventional::validate(&message)
+        .with_context(|| {
+            Error::new(ErrorKind::Validation, format!("Invalid commit message '{message}'"))
+        })?;
+    if let Some(prefix) = branch_prefix(&branch) {
+        if prefix != commit.kind {
+            warn(&format!("Commit type '{}' differs from branch prefix '{prefix}'", commit.kind));
+        }
+    }
+
+    info("Committing...");
+    git(&["commit", "-m", &message]).context("Commit failed")?;
+    println!("{}", git(&["log", "-1", "--format=%h %s"]).unwrap_or(message));
+    Ok(())
+}
+
+/// Let the user accept or override the proposed message on a terminal.
+fn confirm_message(proposal: Option<String>) -> Result<String> {
+    let stdin = io::stdin();
+    if !stdin.is_terminal() {
+        return proposal.context(Error::new(
+            ErrorKind::Validation,
+            "No commit message could be proposed; pass --message",
+        ));
+    }
+
+    match &proposal {
+        Some(p) => eprint!("Commit message [{p}]: "),
+        None => eprint!("Commit message: "),
+    }
+    io::stderr().flush().ok();
+
+    let mut input = String::new();
+    stdin.lock().read_line(&mut input).context("Failed to read commit message")?;
+    let input = input.trim();
+    if input.is_empty() {
+        proposal.context(Error::new(ErrorKind::Validation, "Commit message is required"))
+    } else {
+        Ok(input.to_string())
+    }
+}
+
+/// Extract the entries of the `# Commit Plan` section of a TASK.md.
+fn commit_plan_items(content: &str) -> Vec<String> {
+    let body = split_frontmatter(content).map(|(_, body)| body).unwrap_or(content);
+    body.lines()
+        .skip_while(|l| l.trim() != "# Commit Plan")
+        .skip(1)
+        .take_while(|l| !l.starts_with("# "))
+        .filter_map(|l| l.trim().strip_prefix("- "))
+        .map(|item| {
+            let item = item.trim();
+            item.strip_prefix('`')
+                .and_then(|i| i.strip_suffix('`'))
+                .unwrap_or(item)
+                .to_string()
+        })
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// Propose the next commit message.
+///
+/// Uses the first Commit Plan entry not yet committed on the branch, and
+/// falls back to `<prefix>: <summary>` derived from a `{prefix}/{issue}-{summary}`
+/// branch name.
+fn propose_message(plan: &[String], used: &[String], branch: &str) -> Option<String> {
+    if let Some(item) = plan.iter().find(|item| !used.contains(item)) {
+        return Some(item.clone());
+    }
+
+    let prefix = branch_prefix(branch)?;
+    let (_, rest) = branch.split_once('/')?;
+    let (_, summary) = rest.split_once('-')?;
+    let summary = summary.replace('-', " ");
+    (!summary.trim().is_empty()).then(|| format!("{prefix}: {summary}"))
+}
+
+/// The Conventional Commits type encoded in a `{prefix}/...` branch name.
+fn branch_prefix(branch: &str) -> Option<&str> {
+    let (prefix, _) = branch.split_once('/')?;
+    COMMIT_TYPES.contains(&prefix).then_some(prefix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TASK: &str = "---\nissueNumber: 1\ntaskNumber: 1\nstatus: doing\n---\n\n\
+                        # Verification\n- [ ] `mise run lint` passes\n\n\
+                        # Commit Plan\n\n- `feat(aidd): add commit command`\n\
+                        - `test(aidd): cover commit proposals`\n";
+
+    #[test]
+    fn test_commit_plan_items() {
+        assert_eq!(
+            commit_plan_items(TASK),
+            vec!["feat(aidd): add commit command", "test(aidd): cover commit proposals"]
+        );
+    }
+
+    #[test]
+    fn test_propose_skips_used_plan_items() {
+        let plan = commit_plan_items(TASK);
+        let used = vec!["feat(aidd): add commit command".to_string()];
+        assert_eq!(
+            propose_message(&plan, &used, "feat/1-commit").as_deref(),
+            Some("test(aidd): cover commit proposals")
+        );
+    }
+
+    #[test]
+    fn test_propose_falls_back_to_branch() {
+        assert_eq!(
+            propose_message(&[], &[], "fix/42-null-pointer").as_deref(),
+            Some("fix: null pointer")
+        );
+        assert_eq!(propose_message(&[], &[], "feature/42-x"), None);
+        assert_eq!(propose_message(&[], &[], "main"), None);
+    }
+}
@@ -1,109 +1,407 @@
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::fs;
+use std::path::Path;
 
 use anyhow::{Context, Result};
-use serde::Deserialize;
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+use similar::TextDiff;
 
-use crate::helpers::{features_dir, info, plan_file, repo_root, run_command};
+use crate::clock;
+use crate::error::{self, Error, ErrorKind};
+use crate::estimate;
+use crate::forge::{Issue, NewIssue};
+use crate::frontmatter::{
+    parse_issue_draft, render_frontmatter, upsert_field, yaml_value, PlanFrontmatter,
+    TaskFrontmatter,
+};
+use crate::helpers::{info, warn};
+use crate::repo::Repo;
+use crate::tasks::{rollup_plan_status, rollup_status, update_task_fields};
 
-#[derive(Debug, Deserialize)]
-struct GhIssue {
-    #[allow(dead_code)]
-    number: u32,
-    title: String,
-    body: Option<String>,
+/// A checkbox item or sub-issue of an issue that becomes a task.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IssueTask {
+    pub title: String,
+    /// Task number written before the title, as in `- [ ] 2. Index books`.
+    pub number: Option<u32>,
+    /// Checked in the issue, i.e. already done.
+    pub done: bool,
+    /// Text of the nearest heading above the item.
+    pub group: Option<String>,
+    /// Checkboxes nested under the item, in document order.
+    pub steps: Vec<String>,
+    /// Size from a marker in the item, e.g. `(M)`, or the issue's labels.
+    pub estimate: Option<&'static str>,
+    /// Issue the item refers to (`- [ ] #42`), or the sub-issue it stands for.
+    pub linked_issue: Option<u32>,
 }
 
-/// Generate PLAN.md from a GitHub issue.
+/// Open an issue from a markdown draft and print its number.
 ///
-/// Fetches the issue via `gh issue view`, extracts task items from
+/// The draft's frontmatter gives the title, labels and assignees; the rest
+/// of the file is the issue body. With `plan`, the new issue is planned
+/// right away.
+pub fn create(repo: &Repo, draft: &str, plan: bool) -> Result<()> {
+    let path = Path::new(draft);
+    if !repo.runner.exists(path) {
+        anyhow::bail!(Error::new(ErrorKind::NotFound, format!("{draft} not found")));
+    }
+    let content = repo.runner.read_file(path)?;
+    let (fields, body) = parse_issue_draft(&content).with_context(|| {
+        Error::new(ErrorKind::Validation, format!("Invalid issue draft {draft}"))
+            .with_hint("Start the draft with frontmatter giving at least `title:`")
+    })?;
+    let title = fields.title.trim();
+    if title.is_empty() {
+        anyhow::bail!(Error::new(ErrorKind::Validation, format!("{draft} has an empty title")));
+    }
+
+    info(&format!("Creating issue \"{title}\"..."));
+    let issue = repo.forge.create_issue(&NewIssue {
+        title,
+        body: body.trim(),
+        labels: &fields.labels,
+        assignees: &fields.assignees,
+    })?;
+    info(&format!("Created issue #{}", issue.number));
+    println!("{}", issue.number);
+
+    if plan {
+        plan_issues(repo, issue.number, false, false)?;
+    }
+    Ok(())
+}
+
+/// Plan an issue and, with `recursive`, the issues its tasks link to.
+///
+/// Every issue is planned once, parents before children. With `sync`, an
+/// issue that already has a PLAN.md is synced instead of regenerated.
+pub fn plan_issues(repo: &Repo, issue: u32, sync: bool, recursive: bool) -> Result<()> {
+    let mut pending = VecDeque::from([issue]);
+    let mut seen = BTreeSet::new();
+    while let Some(number) = pending.pop_front() {
+        if !seen.insert(number) {
+            continue;
+        }
+        let tasks = if sync && (number == issue || repo.runner.exists(&repo.plan_file(number))) {
+            self::sync(repo, number)?
+        } else {
+            plan(repo, number)?
+        };
+        if recursive {
+            pending.extend(tasks.iter().filter_map(|t| t.linked_issue));
+        }
+    }
+    Ok(())
+}
+
+/// Generate PLAN.md and a TASK.md per task from an issue.
+///
+/// Fetches the issue from the forge, extracts task items from
 /// checkboxes in the body, and generates a PLAN.md from a template.
-pub fn plan(issue: u32) -> Result<()> {
+/// Existing TASK.md files are kept as they are. Returns the tasks.
+fn plan(repo: &Repo, issue: u32) -> Result<Vec<IssueTask>> {
+    let (plan_content, tasks) = build_plan(repo, issue)?;
+
+    // Create features directory
+    let feat_dir = repo.features_dir(issue);
+    repo.runner.create_dir_all(&feat_dir)?;
+
+    // Generate PLAN.md
+    let pf = repo.plan_file(issue);
+    repo.runner.write_file(&pf, &plan_content)?;
+    info(&format!("Generated: {}", pf.display()));
+
+    for (number, task) in (1..).zip(&tasks) {
+        write_task(repo, issue, number, task)?;
+    }
+    // Kept TASK.md files may be further along than the issue says.
+    rollup_plan_status(repo, issue)?;
+
+    Ok(tasks)
+}
+
+/// Bring an existing PLAN.md up to date with its issue, keeping human edits.
+/// Prints the PLAN.md diff before writing it and returns the issue's tasks.
+fn sync(repo: &Repo, issue: u32) -> Result<Vec<IssueTask>> {
+    let pf = repo.plan_file(issue);
+    if !repo.runner.exists(&pf) {
+        anyhow::bail!(Error::new(ErrorKind::NotFound, format!("{} not found", pf.display()))
+            .with_hint(format!("Run `aidd issue plan {issue}` first")));
+    }
+    let current = repo.runner.read_file(&pf)?;
+
     info(&format!("Fetching issue #{issue}..."));
+    let fetched = repo.forge.fetch_issue(issue)?;
+    let tasks = issue_tasks(repo, &fetched)?;
+    let synced = sync_plan(&current, &fetched.title, &tasks)
+        .with_context(|| format!("Failed to sync {}", pf.display()))?;
+
+    if synced.content == current {
+        info("PLAN.md is up to date");
+    } else {
+        let name = pf.display().to_string();
+        let diff = TextDiff::from_lines(&current, &synced.content);
+        print!("{}", diff.unified_diff().header(&name, &name));
+        repo.runner.write_file(&pf, &synced.content)?;
+        info(&format!("Updated: {}", pf.display()));
+    }
 
-    let json_str = run_command("gh", &[
-        "issue",
-        "view",
-        &issue.to_string(),
-        "--json",
-        "number,title,body",
-    ])
-    .context("Failed to fetch issue. Is `gh` authenticated?")?;
+    for (number, task) in &synced.added {
+        write_task(repo, issue, *number, task)?;
+    }
+    for number in &synced.dropped {
+        if repo.runner.exists(&repo.task_file(issue, *number)) {
+            update_task_fields(repo, issue, *number, &[("status", "dropped")])?;
+        }
+        info(&format!("Task {issue}/{number} is no longer in the issue, marked as dropped"));
+    }
+    for (number, status) in &synced.updated {
+        if repo.runner.exists(&repo.task_file(issue, *number)) {
+            update_task_fields(repo, issue, *number, &[("status", status)])?;
+        }
+        let reason = if *status == "done" { "checked" } else { "back" };
+        info(&format!("Task {issue}/{number} is {reason} in the issue, marked as {status}"));
+    }
+    rollup_plan_status(repo, issue)?;
 
-    let gh_issue: GhIssue =
-        serde_json::from_str(&json_str).context("Failed to parse issue JSON")?;
+    Ok(tasks)
+}
 
-    let body = gh_issue.body.unwrap_or_default();
-    let tasks = extract_tasks(&body);
+/// Write the TASK.md of a task unless it already exists.
+fn write_task(repo: &Repo, issue: u32, number: u32, task: &IssueTask) -> Result<()> {
+    let tf = repo.task_file(issue, number);
+    if repo.runner.exists(&tf) {
+        info(&format!("Keeping existing {}", tf.display()));
+        return Ok(());
+    }
+    if let Some(dir) = tf.parent() {
+        repo.runner.create_dir_all(dir)?;
+    }
+    repo.runner.write_file(&tf, &generate_task(issue, number, task)?)?;
+    info(&format!("Generated: {}", tf.display()));
+    Ok(())
+}
+
+/// Fetch an issue and render its PLAN.md content.
+fn build_plan(repo: &Repo, issue: u32) -> Result<(String, Vec<IssueTask>)> {
+    info(&format!("Fetching issue #{issue}..."));
+
+    let fetched = repo.forge.fetch_issue(issue)?;
+    let tasks = issue_tasks(repo, &fetched)?;
 
     info(&format!(
         "Found {} task items in issue #{issue}",
         tasks.len()
     ));
 
-    // Create features directory
-    let feat_dir = features_dir(issue);
-    fs::create_dir_all(&feat_dir)
-        .with_context(|| format!("Failed to create {}", feat_dir.display()))?;
+    let now = clock::format_utc(repo.clock.now());
+    let content = generate_plan(&repo.root, &fetched.title, issue, &tasks, &now)?;
+    Ok((content, tasks))
+}
 
-    // Generate PLAN.md
-    let plan_content = generate_plan(&gh_issue.title, issue, &tasks);
-    let pf = plan_file(issue);
-    fs::write(&pf, plan_content)
-        .with_context(|| format!("Failed to write {}", pf.display()))?;
-    info(&format!("Generated: {}", pf.display()));
+/// The tasks of an issue: its checkbox items, then the sub-issues no item
+/// links to.
+///
+/// Linked issues are fetched for their title, state and size label; one
+/// that cannot be found, or any when the forge is offline, is left as a
+/// plain task. Tasks still without a size get it from the issue's labels.
+fn issue_tasks(repo: &Repo, issue: &Issue) -> Result<Vec<IssueTask>> {
+    let mut tasks = extract_tasks(&issue.body);
+    let mut linked = BTreeMap::new();
+    let sub_issues = found_or(repo.forge.sub_issues(issue.number), Vec::new())?;
+    for sub_issue in sub_issues {
+        if !tasks.iter().any(|t| t.linked_issue == Some(sub_issue.number)) {
+            tasks.push(IssueTask { linked_issue: Some(sub_issue.number), ..Default::default() });
+        }
+        linked.insert(sub_issue.number, Some(sub_issue));
+    }
 
-    Ok(())
+    let label_size = estimate::from_labels(&issue.labels);
+    for task in &mut tasks {
+        if let Some(number) = task.linked_issue {
+            if let Entry::Vacant(entry) = linked.entry(number) {
+                entry.insert(if repo.forge.is_offline() {
+                    None
+                } else {
+                    found_or(repo.forge.fetch_issue(number).map(Some), None)?
+                });
+            }
+            match linked.get(&number).and_then(Option::as_ref) {
+                Some(linked) => link_task(task, linked),
+                None if task.title.is_empty() => task.title = format!("#{number}"),
+                None => {}
+            }
+        }
+        task.estimate = task.estimate.or(label_size);
+    }
+    Ok(tasks)
+}
+
+/// The value of `result`, or `default` with a warning if the forge did not
+/// find what was asked for.
+fn found_or<T>(result: Result<T>, default: T) -> Result<T> {
+    match result {
+        Err(err) if error::kind(&err) == ErrorKind::NotFound => {
+            warn(&format!("{err:#}"));
+            Ok(default)
+        }
+        result => result,
+    }
+}
+
+/// Fill a task from the issue it links to: the issue's title unless the
+/// item has text of its own, done once the issue is closed, and the
+/// issue's size label.
+fn link_task(task: &mut IssueTask, linked: &Issue) {
+    if task.title.is_empty() {
+        task.title = linked.title.clone();
+    }
+    task.done |= linked.closed;
+    task.estimate = task.estimate.or_else(|| estimate::from_labels(&linked.labels));
+}
+
+/// A list item being parsed and what its checkbox turned into.
+struct Item {
+    role: Role,
+    text: String,
 }
 
-/// Extract task descriptions from checkbox items in the issue body.
-fn extract_tasks(body: &str) -> Vec<String> {
-    body.lines()
-        .filter_map(|line| {
-            let trimmed = line.trim();
-            if trimmed.starts_with("- [ ] ") || trimmed.starts_with("- [x] ") {
-                Some(trimmed[6..].trim().to_string())
-            } else {
-                None
+enum Role {
+    /// No checkbox, or its text is not needed.
+    Plain,
+    /// Index into the extracted tasks.
+    Task(usize),
+    /// Index of the owning task and of the step within it.
+    Step(usize, usize),
+}
+
+/// Extract tasks from the GFM task list items of an issue body.
+///
+/// Checkboxes in any list (bulleted, numbered, inside blockquotes) count;
+/// code blocks do not. Checkboxes nested under a task become its steps.
+fn extract_tasks(body: &str) -> Vec<IssueTask> {
+    let mut tasks: Vec<IssueTask> = Vec::new();
+    let mut items: Vec<Item> = Vec::new();
+    let mut heading: Option<String> = None;
+    let mut group: Option<String> = None;
+
+    for event in Parser::new_ext(body, Options::ENABLE_TASKLISTS) {
+        match event {
+            Event::Start(Tag::Heading { .. }) => heading = Some(String::new()),
+            Event::End(TagEnd::Heading(_)) => {
+                group = heading.take().map(|h| h.trim().to_string()).filter(|h| !h.is_empty());
             }
-        })
-        .filter(|s| !s.is_empty())
-        .collect()
+            Event::Start(Tag::Item) => items.push(Item { role: Role::Plain, text: String::new() }),
+            Event::TaskListMarker(done) => {
+                let owner = items.iter().find_map(|item| match item.role {
+                    Role::Task(task) => Some(task),
+                    _ => None,
+                });
+                let role = match owner {
+                    Some(task) => {
+                        tasks[task].steps.push(String::new());
+                        Role::Step(task, tasks[task].steps.len() - 1)
+                    }
+                    None => {
+                        tasks.push(IssueTask { done, group: group.clone(), ..Default::default() });
+                        Role::Task(tasks.len() - 1)
+                    }
+                };
+                if let Some(item) = items.last_mut() {
+                    item.role = role;
+                }
+            }
+            Event::End(TagEnd::Item) => {
+                let Some(item) = items.pop() else { continue };
+                let text = item.text.trim().to_string();
+                match item.role {
+                    Role::Plain => {}
+                    Role::Task(task) => {
+                        let (title, size) = estimate::split_marker(&text);
+                        let (title, number) = split_number(&title);
+                        let (title, linked_issue) = split_link(&title);
+                        tasks[task].title = title;
+                        tasks[task].number = number;
+                        tasks[task].estimate = size;
+                        tasks[task].linked_issue = linked_issue;
+                    }
+                    Role::Step(task, step) => tasks[task].steps[step] = text,
+                }
+            }
+            Event::Text(text) => push_text(&mut heading, &mut items, &text),
+            Event::Code(code) => push_text(&mut heading, &mut items, &format!("`{code}`")),
+            Event::SoftBreak | Event::HardBreak => push_text(&mut heading, &mut items, " "),
+            _ => {}
+        }
+    }
+
+    tasks.retain(|task| !task.title.is_empty() || task.linked_issue.is_some());
+    for task in &mut tasks {
+        task.steps.retain(|step| !step.is_empty());
+    }
+    tasks
+}
+
+/// Split a leading issue reference, as in `#42` or `#42 Search API`, off
+/// a task title.
+fn split_link(title: &str) -> (String, Option<u32>) {
+    let (first, rest) = title.split_once(' ').unwrap_or((title, ""));
+    match first.strip_prefix('#').and_then(|number| number.parse().ok()) {
+        Some(number) => (rest.trim().to_string(), Some(number)),
+        None => (title.to_string(), None),
+    }
+}
+
+/// Split a leading task number, as in `2. Index books`, off a task title.
+fn split_number(title: &str) -> (String, Option<u32>) {
+    let number = title.split_once(". ").and_then(|(n, rest)| Some((n.parse().ok()?, rest)));
+    match number {
+        Some((number, rest)) => (rest.trim().to_string(), Some(number)),
+        None => (title.to_string(), None),
+    }
+}
+
+/// Append inline text to the open heading, or else to the innermost list item.
+fn push_text(heading: &mut Option<String>, items: &mut [Item], text: &str) {
+    if let Some(heading) = heading {
+        heading.push_str(text);
+    } else if let Some(item) = items.last_mut() {
+        item.text.push_str(text);
+    }
 }
 
 /// Generate PLAN.md content from template.
-fn generate_plan(title: &str, issue: u32, tasks: &[String]) -> String {
-    let root = repo_root();
+///
+/// Tasks get a group column when they come from more than one section.
+/// Tasks checked in the issue are listed as done and count towards the
+/// PLAN status.
+fn generate_plan(
+    root: &Path,
+    title: &str,
+    issue: u32,
+    tasks: &[IssueTask],
+    now: &str,
+) -> Result<String> {
     let template_path = root.join(".agent/templates/PLAN.md");
 
     // Try to read template; if unavailable, use embedded default
     let _template = fs::read_to_string(&template_path).ok();
 
-    let now = chrono_like_now();
-
-    let mut task_table = String::new();
-    for (i, desc) in tasks.iter().enumerate() {
-        let num = i + 1;
-        let estimate = if desc.len() > 50 { "M" } else { "S" };
-        task_table.push_str(&format!("| {num} | {desc} | {estimate} |\n"));
-    }
-
-    let task_section = if task_table.is_empty() {
-        String::new()
-    } else {
-        format!(
-            "# Task Breakdown\n| # | タスク概要 | 見積 |\n|---|-----------|------|\n{task_table}\n"
-        )
-    };
-
-    format!(
-        r#"---
-issueNumber: {issue}
-title: "{title}"
-status: draft
-ownerAgent: claude
-createdAt: {now}
----
+    let task_section = task_section(tasks);
+    let statuses: Vec<String> = tasks.iter().map(|t| task_status(t).to_string()).collect();
+    let status = rollup_status(&statuses).unwrap_or("draft");
+    let frontmatter = render_frontmatter(&PlanFrontmatter {
+        issue_number: issue,
+        title: title.to_string(),
+        status: status.to_string(),
+        owner_agent: "claude".to_string(),
+        created_at: now.to_string(),
+    })?;
 
+    Ok(format!(
+        r#"{frontmatter}
 # Goal
 {title}
 
@@ -117,18 +415,316 @@ Issue #{issue} の実装スコープ。
 - [ ] lint/test パス
 - [ ] PR レビュー済み
 "#
-    )
+    ))
+}
+
+/// The `# Task Breakdown` section for tasks numbered from 1, or nothing.
+fn task_section(tasks: &[IssueTask]) -> String {
+    if tasks.is_empty() {
+        return String::new();
+    }
+    let grouped = tasks.iter().any(|t| t.group != tasks[0].group);
+    let columns: &[&str] = if grouped { &GROUPED_COLUMNS } else { &COLUMNS };
+
+    let mut section = format!("# Task Breakdown\n| {} |\n|", columns.join(" | "));
+    for column in columns {
+        // Wide enough for the full-width header text.
+        let width = (column.chars().count() * 2 + 1).max(3);
+        section.push_str(&format!("{}|", "-".repeat(width)));
+    }
+    section.push('\n');
+    for (number, task) in (1..).zip(tasks) {
+        section.push_str(&format!("| {} |\n", task_row(columns, number, task).join(" | ")));
+    }
+    let estimates = tasks.iter().map(|t| t.estimate.unwrap_or(estimate::UNKNOWN));
+    section.push_str(&format!("\n{}\n\n", estimate::total_line(estimates)));
+    section
+}
+
+/// Task Breakdown columns.
+const COLUMNS: [&str; 4] = ["#", "タスク概要", "状態", "見積"];
+/// Task Breakdown columns when tasks come from several sections.
+const GROUPED_COLUMNS: [&str; 5] = ["#", "タスク概要", "グループ", "状態", "見積"];
+
+/// The cells of a task's Task Breakdown row, for the given header.
+fn task_row(columns: &[&str], number: u32, task: &IssueTask) -> Vec<String> {
+    columns
+        .iter()
+        .map(|column| match column.trim() {
+            "#" => number.to_string(),
+            "タスク概要" => task.title.clone(),
+            "グループ" => task.group.clone().unwrap_or_default(),
+            "状態" => task_status(task).to_string(),
+            "見積" => task.estimate.unwrap_or(estimate::UNKNOWN).to_string(),
+            _ => String::new(),
+        })
+        .collect()
+}
+
+/// The result of syncing PLAN.md with its issue.
+struct Synced {
+    content: String,
+    /// New tasks and their numbers.
+    added: Vec<(u32, IssueTask)>,
+    /// Numbers of tasks no longer in the issue.
+    dropped: Vec<u32>,
+    /// Numbers of tasks whose status changed, with the new status.
+    updated: Vec<(u32, &'static str)>,
+}
+
+/// Sync PLAN.md content with the issue's title and tasks.
+/// Rows match tasks by number, else by title; unmatched rows become `dropped`.
+fn sync_plan(content: &str, title: &str, tasks: &[IssueTask]) -> Result<Synced> {
+    let content = upsert_field(content, "title", &yaml_value(title)?)?;
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+
+    let Some(table) = TaskTable::find(&lines)? else {
+        // No table yet: every task is new.
+        let section = task_section(tasks);
+        let at = lines.iter().position(|l| l.trim() == "# Risks").unwrap_or(lines.len());
+        lines.splice(at..at, section.lines().map(str::to_string));
+        let added = (1..).zip(tasks.iter().cloned()).collect();
+        return Ok(Synced {
+            content: join_lines(&lines, &content),
+            added,
+            dropped: Vec::new(),
+            updated: Vec::new(),
+        });
+    };
+    let end = table.end;
+    let title_col =
+        table.column("タスク概要").context("Task Breakdown has no タスク概要 column")?;
+    let (number_col, status_col) = (table.column("#"), table.column("状態"));
+
+    let rows: Vec<(usize, Vec<String>)> =
+        table.rows().map(|i| (i, table_cells(&lines[i]))).collect();
+    let number_of = |cells: &[String]| number_col.and_then(|c| cells.get(c)?.parse::<u32>().ok());
+    let last_number = rows.iter().filter_map(|(_, cells)| number_of(cells)).max().unwrap_or(0);
+
+    // The row of each task: by number first, then by title among the rest.
+    let mut matched: Vec<Option<usize>> = tasks
+        .iter()
+        .map(|task| {
+            let number = task.number?;
+            rows.iter().position(|(_, cells)| number_of(cells) == Some(number))
+        })
+        .collect();
+    for (t, task) in tasks.iter().enumerate() {
+        if matched[t].is_none() {
+            matched[t] = rows.iter().enumerate().position(|(r, (_, cells))| {
+                !matched.contains(&Some(r)) && cells.get(title_col) == Some(&task.title)
+            });
+        }
+    }
+
+    let mut dropped = Vec::new();
+    let mut updated = Vec::new();
+    for (r, (i, cells)) in rows.iter().enumerate() {
+        if cells.get(title_col).is_none() {
+            continue;
+        }
+        let mut cells = cells.clone();
+        let status = status_col.and_then(|c| cells.get(c)).cloned();
+        let status = status.as_deref();
+        let new_status = match matched.iter().position(|m| *m == Some(r)) {
+            Some(t) => {
+                cells[title_col] = tasks[t].title.clone();
+                match status {
+                    _ if tasks[t].done && status != Some("done") => Some("done"),
+                    Some("dropped") => Some("todo"),
+                    _ => None,
+                }
+            }
+            None if status != Some("dropped") => Some("dropped"),
+            None => None,
+        };
+        if let Some(new_status) = new_status {
+            if let Some(c) = status_col.filter(|&c| c < cells.len()) {
+                cells[c] = new_status.to_string();
+            }
+            match new_status {
+                "dropped" => dropped.extend(number_of(&cells)),
+                _ => updated.extend(number_of(&cells).map(|n| (n, new_status))),
+            }
+        }
+        if cells != table_cells(&lines[*i]) {
+            lines[*i] = format!("| {} |", cells.join(" | "));
+        }
+    }
+
+    let header_names: Vec<&str> = table.columns.iter().map(String::as_str).collect();
+    let mut added = Vec::new();
+    let mut new_rows = Vec::new();
+    let mut number = last_number;
+    for (task, _) in tasks.iter().zip(&matched).filter(|(_, m)| m.is_none()) {
+        number += 1;
+        new_rows.push(format!("| {} |", task_row(&header_names, number, task).join(" | ")));
+        added.push((number, task.clone()));
+    }
+    lines.splice(end..end, new_rows);
+    set_total(&mut lines)?;
+
+    Ok(Synced { content: join_lines(&lines, &content), added, dropped, updated })
 }
 
-/// Simple ISO-8601 timestamp without external crates.
-fn chrono_like_now() -> String {
-    run_command("date", &["-u", "+%Y-%m-%dT%H:%M:%SZ"])
-        .unwrap_or_else(|_| "1970-01-01T00:00:00Z".to_string())
+/// The Task Breakdown table of a PLAN.md, as line indices.
+pub struct TaskTable {
+    /// The header row; the separator follows it.
+    pub header: usize,
+    /// One past the last row.
+    pub end: usize,
+    pub columns: Vec<String>,
+}
+
+impl TaskTable {
+    /// Locate the table; `None` if PLAN.md has no Task Breakdown section.
+    pub fn find(lines: &[String]) -> Result<Option<Self>> {
+        let Some(heading) = lines.iter().position(|l| l.trim() == "# Task Breakdown") else {
+            return Ok(None);
+        };
+        let header = lines[heading + 1..]
+            .iter()
+            .position(|l| l.trim_start().starts_with('|'))
+            .map(|i| heading + 1 + i)
+            .context("Task Breakdown has no table")?;
+        let end = lines[header..]
+            .iter()
+            .position(|l| !l.trim_start().starts_with('|'))
+            .map_or(lines.len(), |i| header + i);
+        Ok(Some(Self { header, end, columns: table_cells(&lines[header]) }))
+    }
+
+    pub fn column(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|c| c == name)
+    }
+
+    /// Indices of the data rows.
+    pub fn rows(&self) -> std::ops::Range<usize> {
+        (self.header + 2).min(self.end)..self.end
+    }
+}
+
+/// Recompute the estimate total below the Task Breakdown table.
+///
+/// Dropped tasks do not count. The total line is added if missing.
+pub fn set_total(lines: &mut Vec<String>) -> Result<()> {
+    let Some(table) = TaskTable::find(lines)? else {
+        return Ok(());
+    };
+    let Some(estimate_col) = table.column("見積") else {
+        return Ok(());
+    };
+    let status_col = table.column("状態");
+    let estimates: Vec<String> = lines[table.rows()]
+        .iter()
+        .map(|line| table_cells(line))
+        .filter(|cells| {
+            status_col.and_then(|c| cells.get(c)).map(String::as_str) != Some("dropped")
+        })
+        .map(|cells| cells.get(estimate_col).cloned().unwrap_or_default())
+        .collect();
+    let total = estimate::total_line(estimates.iter().map(String::as_str));
+
+    let end = table.end;
+    let existing = lines.get(end + 1).filter(|l| l.starts_with(estimate::TOTAL_PREFIX));
+    if existing.is_some() && lines[end].trim().is_empty() {
+        lines[end + 1] = total;
+    } else {
+        lines.splice(end..end, [String::new(), total]);
+    }
+    Ok(())
+}
+
+/// The trimmed cells of a markdown table row.
+pub fn table_cells(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = line.strip_suffix('|').unwrap_or(line);
+    line.split('|').map(|cell| cell.trim().to_string()).collect()
+}
+
+/// Join lines, keeping the trailing newline of `original`.
+pub fn join_lines(lines: &[String], original: &str) -> String {
+    let mut joined = lines.join("\n");
+    if original.ends_with('\n') {
+        joined.push('\n');
+    }
+    joined
+}
+
+/// Initial TASK.md status of a task.
+fn task_status(task: &IssueTask) -> &'static str {
+    if task.done {
+        "done"
+    } else {
+        "todo"
+    }
+}
+
+/// Generate TASK.md content for one task of an issue.
+fn generate_task(issue: u32, number: u32, task: &IssueTask) -> Result<String> {
+    let frontmatter = render_frontmatter(&TaskFrontmatter {
+        issue_number: issue,
+        task_number: number,
+        status: task_status(task).to_string(),
+        branch_name: None,
+        worktree_path: None,
+        parent_task: None,
+        estimate: Some(task.estimate.unwrap_or(estimate::UNKNOWN).to_string()),
+        linked_issue: task.linked_issue,
+        depends_on: Vec::new(),
+    })?;
+    let group = task.group.as_deref().map(|g| format!(" ({g})")).unwrap_or_default();
+    let link = task.linked_issue.map(|n| format!("\nLinked issue: #{n}\n")).unwrap_or_default();
+    let steps = if task.steps.is_empty() {
+        "<!-- 実装手順 -->\n".to_string()
+    } else {
+        (1..).zip(&task.steps).map(|(i, step)| format!("{i}. {step}\n")).collect()
+    };
+
+    Ok(format!(
+        r#"{frontmatter}
+# Context
+Issue #{issue}{group}: {title}
+{link}
+# Implementation Steps
+{steps}
+# Files to Change
+<!-- 変更対象のファイル -->
+
+# Verification
+- [ ] `mise run lint` パス
+- [ ] `bun test` パス（該当テストがある場合）
+
+# Commit Plan
+<!-- 例: `feat(xxx): <description>` -->
+"#,
+        title = task.title
+    ))
 }
 
 #[cfg(test)]
 mod tests {
+    use std::rc::Rc;
+
     use super::*;
+    use crate::clock::FixedClock;
+    use crate::forge::dry_run::DryRunForge;
+    use crate::forge::fake::FakeForge;
+    use crate::forge::offline::{self, OfflineForge};
+    use crate::forge::Forge;
+    use crate::runner::dry_run::Plan;
+    use crate::runner::fake::FakeRunner;
+
+    const NOW: &str = "2026-01-01T00:00:00Z";
+
+    fn task(title: &str) -> IssueTask {
+        IssueTask { title: title.to_string(), ..Default::default() }
+    }
+
+    fn titles(tasks: &[IssueTask]) -> Vec<&str> {
+        tasks.iter().map(|t| t.title.as_str()).collect()
+    }
 
     #[test]
     fn test_extract_tasks_checkboxes() {
@@ -141,11 +737,58 @@ mod tests {
 - [ ] Add tests
 "#;
         let tasks = extract_tasks(body);
-        assert_eq!(tasks.len(), 4);
-        assert_eq!(tasks[0], "Set up project structure");
-        assert_eq!(tasks[1], "Implement core logic");
-        assert_eq!(tasks[2], "Write documentation");
-        assert_eq!(tasks[3], "Add tests");
+        assert_eq!(
+            titles(&tasks),
+            vec![
+                "Set up project structure",
+                "Implement core logic",
+                "Write documentation",
+                "Add tests"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_tasks_list_variants() {
+        let body = r#"
+* [ ] Starred item
+- [X] Upper-case check with `code`
+1. [ ] Numbered item
+
+> - [ ] Quoted item
+
+```md
+- [ ] Inside a code block
+```
+
+    - [ ] Indented code block
+"#;
+        assert_eq!(
+            titles(&extract_tasks(body)),
+            vec!["Starred item", "Upper-case check with `code`", "Numbered item", "Quoted item"]
+        );
+    }
+
+    #[test]
+    fn test_extract_tasks_steps_and_groups() {
+        let body = r#"
+## Backend
+- [ ] Index books
+  - [ ] Add tokenizer
+  - [x] Build index
+    - [ ] Persist it
+  - plain note
+- [ ] Add API
+
+## Frontend
+- [ ] Add search page
+"#;
+        let tasks = extract_tasks(body);
+        assert_eq!(titles(&tasks), vec!["Index books", "Add API", "Add search page"]);
+        assert_eq!(tasks[0].steps, vec!["Add tokenizer", "Build index", "Persist it"]);
+        assert!(tasks[1].steps.is_empty());
+        assert_eq!(tasks[0].group.as_deref(), Some("Backend"));
+        assert_eq!(tasks[2].group.as_deref(), Some("Frontend"));
     }
 
     #[test]
@@ -157,16 +800,264 @@ mod tests {
 
     #[test]
     fn test_generate_plan_with_tasks() {
-        let content = generate_plan("My Feature", 1, &["Task A".to_string(), "Task B".to_string()]);
+        let content = generate_plan(
+            Path::new("/nonexistent"),
+            "My Feature",
+            1,
+            &[task("Task A"), task("Task B")],
+            NOW,
+        ).unwrap();
         assert!(content.contains("issueNumber: 1"));
         assert!(content.contains("My Feature"));
         assert!(content.contains("Task A"));
         assert!(content.contains("Task B"));
+        assert!(content.contains("| # | タスク概要 | 状態 | 見積 |"));
+        assert!(content.contains("| 1 | Task A | todo | ? |"));
+        assert!(content.contains("\n見積合計: 0 pt（未見積 2 件）\n\n# Risks"));
+        assert!(content.contains("status: draft"));
+    }
+
+    #[test]
+    fn test_generate_plan_with_groups() {
+        let mut api = task("Add API");
+        api.group = Some("Backend".to_string());
+        let tasks = [api, task("Docs")];
+        let content = generate_plan(Path::new("/nonexistent"), "Search", 1, &tasks, NOW).unwrap();
+        assert!(content.contains("| # | タスク概要 | グループ | 状態 | 見積 |"));
+        assert!(content.contains("| 1 | Add API | Backend | todo | ? |"));
+        assert!(content.contains("| 2 | Docs |  | todo | ? |"));
+    }
+
+    #[test]
+    fn test_checked_tasks_are_done() {
+        let tasks = extract_tasks("- [x] Write docs\n- [ ] Add tests\n  - [x] Unit tests\n");
+        assert_eq!(tasks.iter().map(|t| t.done).collect::<Vec<_>>(), vec![true, false]);
+
+        let content = generate_plan(Path::new("/nonexistent"), "Docs", 3, &tasks, NOW).unwrap();
+        assert!(content.contains("| 1 | Write docs | done | ? |"));
+        assert!(content.contains("| 2 | Add tests | todo | ? |"));
+        assert!(content.contains("status: doing"));
+        assert!(generate_task(3, 1, &tasks[0]).unwrap().contains("status: done"));
+        assert!(generate_task(3, 2, &tasks[1]).unwrap().contains("status: todo"));
+
+        let done = [tasks[0].clone()];
+        let content = generate_plan(Path::new("/nonexistent"), "Docs", 3, &done, NOW).unwrap();
+        assert!(content.contains("status: done"));
+    }
+
+    const EDITED_PLAN: &str = "---
+issueNumber: 4
+title: \"Old title\"
+status: doing
+ownerAgent: claude
+createdAt: 2026-01-01T00:00:00Z
+reviewer: alice
+---
+
+# Goal
+Old title
+
+# Scope
+Only the search API, not the UI.
+
+# Task Breakdown
+| # | タスク概要 | 状態 | 見積 |
+|---|-----------|------|------|
+| 1 | Index books | done | L |
+| 2 | Add ranking | todo | S |
+
+見積合計: 7 pt
+
+# Risks
+Index size.
+";
+
+    #[test]
+    fn test_sync_plan_keeps_edits() {
+        let tasks = [task("Index books"), task("Add search API")];
+        let synced = sync_plan(EDITED_PLAN, "New title", &tasks).unwrap();
+
+        assert_eq!(synced.added, vec![(3, task("Add search API"))]);
+        assert_eq!(synced.dropped, vec![2]);
+        let expected = EDITED_PLAN
+            .replace("title: \"Old title\"", "title: New title")
+            .replace(
+                "| 2 | Add ranking | todo | S |",
+                "| 2 | Add ranking | dropped | S |\n| 3 | Add search API | todo | ? |",
+            )
+            .replace("見積合計: 7 pt", "見積合計: 5 pt（未見積 1 件）");
+        assert_eq!(synced.content, expected);
+
+        let again = sync_plan(&synced.content, "New title", &tasks).unwrap();
+        assert_eq!(again.content, synced.content);
+        assert!(again.added.is_empty() && again.dropped.is_empty() && again.updated.is_empty());
+    }
+
+    #[test]
+    fn test_sync_plan_matches_numbered_items() {
+        let tasks = extract_tasks("- [ ] 1. Index all books\n- [ ] 2. Add ranking\n");
+        assert_eq!(tasks[0].number, Some(1));
+        let synced = sync_plan(EDITED_PLAN, "Old title", &tasks).unwrap();
+
+        assert!(synced.added.is_empty() && synced.dropped.is_empty());
+        assert!(synced.content.contains("| 1 | Index all books | done | L |\n"));
+        assert!(synced.content.contains("| 2 | Add ranking | todo | S |\n"));
+    }
+
+    #[test]
+    fn test_sync_plan_revives_dropped_tasks() {
+        let plan = EDITED_PLAN.replace("| Add ranking | todo |", "| Add ranking | dropped |");
+        let tasks = [task("Index books"), task("Add ranking")];
+        let synced = sync_plan(&plan, "Old title", &tasks).unwrap();
+
+        assert!(synced.added.is_empty() && synced.dropped.is_empty());
+        assert_eq!(synced.updated, vec![(2, "todo")]);
+        assert!(synced.content.contains("| 2 | Add ranking | todo | S |\n"));
+        assert!(synced.content.contains("見積合計: 7 pt"));
+    }
+
+    #[test]
+    fn test_sync_plan_marks_checked_tasks_done() {
+        let ranking = IssueTask { done: true, ..task("Add ranking") };
+        let synced = sync_plan(EDITED_PLAN, "Old title", &[task("Index books"), ranking]).unwrap();
+
+        assert_eq!(synced.updated, vec![(2, "done")]);
+        assert!(synced.content.contains("| 1 | Index books | done | L |\n"));
+        assert!(synced.content.contains("| 2 | Add ranking | done | S |\n"));
+    }
+
+    #[test]
+    fn test_hostile_titles_round_trip() {
+        let title = "fix: \"quoted\" \\ back-slashed\n- 検索 🔍";
+        let tasks = [task("#x: y")];
+        let plan = generate_plan(Path::new("/nonexistent"), title, 4, &tasks, NOW).unwrap();
+        let frontmatter = crate::frontmatter::parse_plan_frontmatter(&plan).unwrap();
+        assert_eq!(frontmatter.title, title);
+
+        let synced = sync_plan(&plan, "- leading dash", &tasks).unwrap();
+        let frontmatter = crate::frontmatter::parse_plan_frontmatter(&synced.content).unwrap();
+        assert_eq!(frontmatter.title, "- leading dash");
+        assert_eq!(frontmatter.created_at, NOW);
+    }
+
+    #[test]
+    fn test_sync_plan_adds_missing_table() {
+        let plan = generate_plan(Path::new("/nonexistent"), "Search", 4, &[], NOW).unwrap();
+        let synced = sync_plan(&plan, "Search", &[task("Index books")]).unwrap();
+
+        assert_eq!(synced.added.len(), 1);
+        let table = "# Task Breakdown\n| # | タスク概要 | 状態 | 見積 |\n|---|-----------|-----|-----|\n\
+                     | 1 | Index books | todo | ? |\n\n\
+                     見積合計: 0 pt（未見積 1 件）\n\n# Risks";
+        assert!(synced.content.contains(table), "{}", synced.content);
+    }
+
+    #[test]
+    fn test_task_estimates() {
+        let tasks = extract_tasks("- [ ] Index books (L)\n- [ ] size:xs Fix typo\n- [ ] Add UI\n");
+        assert_eq!(titles(&tasks), vec!["Index books", "Fix typo", "Add UI"]);
+        let sizes: Vec<_> = tasks.iter().map(|t| t.estimate).collect();
+        assert_eq!(sizes, vec![Some("L"), Some("XS"), None]);
+
+        let issue = Issue {
+            number: 5,
+            title: "Search".to_string(),
+            body: "- [ ] Index books (L)\n- [ ] Add UI\n".to_string(),
+            labels: vec!["size:S".to_string()],
+            closed: false,
+        };
+        let (runner, forge) = (FakeRunner::new(), FakeForge::new());
+        let repo = Repo::for_test("/nonexistent", &runner, &forge);
+        let tasks = issue_tasks(&repo, &issue).unwrap();
+        let sizes: Vec<_> = tasks.iter().map(|t| t.estimate).collect();
+        assert_eq!(sizes, vec![Some("L"), Some("S")]);
+
+        let content = generate_plan(Path::new("/nonexistent"), "Search", 5, &tasks, NOW).unwrap();
+        assert!(content.contains("| 1 | Index books | todo | L |"));
+        assert!(content.contains("\n見積合計: 7 pt\n"));
+    }
+
+    #[test]
+    fn test_generate_task() {
+        let indexing = IssueTask {
+            title: "Index books".to_string(),
+            done: false,
+            group: Some("Backend".to_string()),
+            steps: vec!["Add tokenizer".to_string(), "Build index".to_string()],
+            estimate: Some("M"),
+            linked_issue: Some(42),
+            ..Default::default()
+        };
+        let content = generate_task(5, 2, &indexing).unwrap();
+        let frontmatter = crate::frontmatter::parse_task_frontmatter(&content).unwrap();
+        assert_eq!((frontmatter.issue_number, frontmatter.task_number), (5, 2));
+        assert_eq!(frontmatter.status, "todo");
+        assert_eq!(frontmatter.estimate.as_deref(), Some("M"));
+        assert_eq!(frontmatter.linked_issue, Some(42));
+        assert!(content.contains("Issue #5 (Backend): Index books\n\nLinked issue: #42\n"));
+        assert!(content.contains("# Implementation Steps\n1. Add tokenizer\n2. Build index\n"));
+        let docs = generate_task(5, 1, &task("Docs")).unwrap();
+        assert!(docs.contains("<!-- 実装手順 -->"));
+        let frontmatter = crate::frontmatter::parse_task_frontmatter(&docs).unwrap();
+        assert_eq!(frontmatter.estimate.as_deref(), Some("?"));
+        assert_eq!(frontmatter.linked_issue, None);
+    }
+
+    #[test]
+    fn test_linked_issue_tasks() {
+        let body = "- [ ] #42\n- [ ] #43 Tune ranking (S)\n- [ ] #99\n- [ ] Docs\n";
+        let forge = FakeForge::new()
+            .with_issue(5, "Search", body)
+            .with_issue(42, "Search API", "")
+            .with_closed_issue(43, "Ranking")
+            .with_issue(44, "Admin UI", "")
+            .with_sub_issue(5, 42)
+            .with_sub_issue(5, 44);
+        let runner = FakeRunner::new();
+        let repo = Repo::for_test("/nonexistent", &runner, &forge);
+        let issue = forge.fetch_issue(5).unwrap();
+        let tasks = issue_tasks(&repo, &issue).unwrap();
+
+        assert_eq!(titles(&tasks), vec!["Search API", "Tune ranking", "#99", "Docs", "Admin UI"]);
+        let links: Vec<_> = tasks.iter().map(|t| t.linked_issue).collect();
+        assert_eq!(links, vec![Some(42), Some(43), Some(99), None, Some(44)]);
+        let done: Vec<_> = tasks.iter().map(|t| t.done).collect();
+        assert_eq!(done, vec![false, true, false, false, false]);
+        assert_eq!(tasks[1].estimate, Some("S"));
+    }
+
+    #[test]
+    fn test_linked_issues_are_not_fetched_offline() {
+        let issue = offline::parse_issue(5, "# Search\n- [ ] #42\n- [ ] #43 Tune ranking\n");
+        let issue = issue.unwrap();
+        let plan = Rc::new(Plan::default());
+        let forge = DryRunForge::new(Box::new(OfflineForge::new(issue.clone())), plan);
+        let runner = FakeRunner::new();
+        let repo = Repo::for_test("/nonexistent", &runner, &forge);
+        assert!(forge.is_offline());
+        let tasks = issue_tasks(&repo, &issue).unwrap();
+        assert_eq!(titles(&tasks), vec!["#42", "Tune ranking"]);
+    }
+
+    #[test]
+    fn test_build_plan_from_forge() {
+        let body = "- [ ] Index books\n- [ ] Add UI\n";
+        let forge = FakeForge::new().with_issue(5, "Add search", body);
+        let (runner, clock) = (FakeRunner::new(), FixedClock(NOW.parse().unwrap()));
+        let repo = Repo { clock: &clock, ..Repo::for_test("/nonexistent", &runner, &forge) };
+        let (content, tasks) = build_plan(&repo, 5).unwrap();
+        assert_eq!(tasks.len(), 2);
+        assert!(content.contains("issueNumber: 5"));
+        assert!(content.contains(&format!("createdAt: {NOW}")));
+        assert!(content.contains("Add search"));
+        assert!(content.contains("| 1 | Index books |"));
+        assert!(content.contains("| 2 | Add UI |"));
+        assert!(build_plan(&repo, 6).is_err());
     }
 
     #[test]
     fn test_generate_plan_without_tasks() {
-        let content = generate_plan("My Feature", 2, &[]);
+        let content = generate_plan(Path::new("/nonexistent"), "My Feature", 2, &[], NOW).unwrap();
         assert!(content.contains("issueNumber: 2"));
         assert!(!content.contains("Task Breakdown"));
     }
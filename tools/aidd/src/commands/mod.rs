@@ -1,4 +1,8 @@
+pub mod changelog;
+pub mod commit;
 pub mod issue;
+pub mod plan;
 pub mod pr;
+pub mod review;
 pub mod status;
 pub mod wt;
@@ -0,0 +1,135 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+
+use crate::commands::issue::{join_lines, set_total, table_cells, TaskTable};
+use crate::deps::{self, GraphFormat};
+use crate::error::{Error, ErrorKind};
+use crate::estimate::{parse_size, SIZES};
+use crate::helpers::{info, warn};
+use crate::repo::Repo;
+use crate::tasks::{list_tasks, read_task, update_task_fields};
+
+/// Set the estimate of a task in its TASK.md and in the PLAN.md table,
+/// then recompute the PLAN total.
+pub fn estimate(repo: &Repo, issue: u32, task: u32, size: &str) -> Result<()> {
+    let size = parse_size(size).with_context(|| {
+        Error::new(ErrorKind::Validation, format!("Unknown size '{size}'"))
+            .with_hint(format!("Use one of {}", SIZES.join(", ")))
+    })?;
+    read_task(repo, issue, task)?;
+    update_task_fields(repo, issue, task, &[("estimate", size)])?;
+
+    let pf = repo.plan_file(issue);
+    if repo.runner.exists(&pf) {
+        let content = repo.runner.read_file(&pf)?;
+        let (updated, found) = set_row_estimate(&content, task, size)
+            .with_context(|| format!("Failed to update {}", pf.display()))?;
+        if !found {
+            warn(&format!("Task {task} is not in the PLAN.md table; only the total was updated"));
+        }
+        if updated != content {
+            repo.runner.write_file(&pf, &updated)?;
+        }
+    }
+
+    info(&format!("Estimated issue #{issue} task {task} as {size}"));
+    Ok(())
+}
+
+/// Print the dependency graph of an issue's tasks, titled from the PLAN.md
+/// table.
+pub fn graph(repo: &Repo, issue: u32, format: GraphFormat) -> Result<()> {
+    let tasks = list_tasks(repo, issue)?;
+    if tasks.is_empty() {
+        anyhow::bail!(Error::new(ErrorKind::NotFound, format!("Issue #{issue} has no tasks"))
+            .with_hint(format!("Run `aidd issue plan {issue}` to create the tasks")));
+    }
+    deps::check(issue, &tasks)?;
+
+    let pf = repo.plan_file(issue);
+    let titles = if repo.runner.exists(&pf) {
+        task_titles(&repo.runner.read_file(&pf)?)?
+    } else {
+        BTreeMap::new()
+    };
+    print!("{}", deps::render(format, issue, &tasks, &titles));
+    Ok(())
+}
+
+/// Task titles from the タスク概要 column of the Task Breakdown table, by
+/// task number.
+fn task_titles(content: &str) -> Result<BTreeMap<u32, String>> {
+    let lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let Some(table) = TaskTable::find(&lines)? else {
+        return Ok(BTreeMap::new());
+    };
+    let (Some(number_col), Some(title_col)) = (table.column("#"), table.column("タスク概要"))
+    else {
+        return Ok(BTreeMap::new());
+    };
+    Ok(lines[table.rows()]
+        .iter()
+        .filter_map(|line| {
+            let cells = table_cells(line);
+            let number = cells.get(number_col)?.parse().ok()?;
+            Some((number, cells.get(title_col)?.to_string()))
+        })
+        .collect())
+}
+
+/// Set the 見積 cell of the row numbered `task` and recompute the total.
+///
+/// Returns the new content and whether the row was found.
+fn set_row_estimate(content: &str, task: u32, size: &str) -> Result<(String, bool)> {
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let mut found = false;
+    if let Some(table) = TaskTable::find(&lines)? {
+        let number_col = table.column("#").context("Task Breakdown has no # column")?;
+        let estimate_col = table.column("見積").context("Task Breakdown has no 見積 column")?;
+        for line in &mut lines[table.rows()] {
+            let mut cells = table_cells(line);
+            if cells.get(number_col).and_then(|n| n.parse::<u32>().ok()) != Some(task) {
+                continue;
+            }
+            if let Some(cell) = cells.get_mut(estimate_col) {
+                *cell = size.to_string();
+                *line = format!("| {} |", cells.join(" | "));
+                found = true;
+            }
+        }
+    }
+    set_total(&mut lines)?;
+    Ok((join_lines(&lines, content), found))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAN: &str = "\
+# Task Breakdown
+
+| # | タスク概要 | 状態 | 見積 |
+|---|-----------|------|------|
+| 1 | Index books | todo | S |
+| 2 | Add search page | todo | ? |
+| 3 | Old idea | dropped | L |
+
+見積合計: 2 pt（未見積 1 件）
+
+# Risks
+";
+
+    #[test]
+    fn test_set_row_estimate() {
+        let (updated, found) = set_row_estimate(PLAN, 2, "M").unwrap();
+        assert!(found);
+        assert!(updated.contains("| 2 | Add search page | todo | M |"));
+        assert!(updated.contains("\n見積合計: 5 pt\n\n# Risks"));
+        assert_eq!(updated.matches("見積合計").count(), 1);
+
+        let (_, found) = set_row_estimate(PLAN, 9, "M").unwrap();
+        assert!(!found);
+    }
+}
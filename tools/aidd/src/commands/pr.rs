@@ -1,40 +1,408 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
 use anyhow::{Context, Result};
 
-use crate::helpers::{find_worktree_for_issue, info, run_command_in};
+use crate::commands::{review, wt};
+use crate::error::{Error, ErrorKind};
+use crate::forge::{CheckState, Forge, MergeMethod, NewPullRequest, PrState, PrUpdate};
+use crate::frontmatter::TaskFrontmatter;
+use crate::helpers::{info, warn, BASE_BRANCH};
+use crate::interrupt;
+use crate::repo::Repo;
+use crate::tasks::{
+    child_tasks, find_task_by_branch, list_tasks, read_task, rollup_plan_status, set_task_status,
+};
+
+/// How long `merge` waits for the forge to report the PR as merged.
+const MERGE_WAIT_TIMEOUT: Duration = Duration::from_secs(300);
+/// Interval between PR state polls while waiting for the merge.
+const MERGE_POLL_INTERVAL: Duration = Duration::from_secs(5);
 
 /// Push branch and create a pull request.
-pub fn create(issue: u32) -> Result<()> {
-    let work_dir = find_worktree_for_issue(issue)
-        .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| ".".into()));
+///
+/// Runs `review-check` first and refuses to open the PR if any check
+/// fails, unless `skip_review` is set.
+pub fn create(repo: &Repo, issue: u32, task: Option<u32>, skip_review: bool) -> Result<()> {
+    let work_dir = repo.work_dir(issue, task)?;
+    let git = |args: &[&str]| repo.runner.run("git", args, Some(&work_dir));
 
-    let branch = run_command_in("git", &["rev-parse", "--abbrev-ref", "HEAD"], Some(&work_dir))
+    let branch = git(&["rev-parse", "--abbrev-ref", "HEAD"])
         .context("Failed to get current branch")?;
+    let task = task.or_else(|| find_task_by_branch(repo, issue, &branch));
+
+    if skip_review {
+        warn("Skipping review checks");
+    } else {
+        review::run(repo, issue, task)
+            .context("Fix the review check failures before creating a PR")?;
+    }
 
     // Push
     info("Pushing branch...");
-    run_command_in("git", &["push", "-u", "origin", &branch], Some(&work_dir))
+    repo.runner
+        .run_streamed("git push", "git", &["push", "-u", "origin", &branch], Some(&work_dir))
         .context("Failed to push branch")?;
 
     // Create PR
     info("Creating PR...");
-    let commit_summary = run_command_in("git", &["log", "-1", "--format=%s"], Some(&work_dir))
-        .unwrap_or_else(|_| "Implementation".to_string());
-    let commit_body = run_command_in("git", &["log", "-1", "--format=%b"], Some(&work_dir))
-        .unwrap_or_default();
+    let commit_summary =
+        git(&["log", "-1", "--format=%s"]).unwrap_or_else(|_| "Implementation".to_string());
+    let commit_body = git(&["log", "-1", "--format=%b"]).unwrap_or_default();
 
-    let pr_title = format!("[ISSUE-{issue}] {commit_summary}");
+    let pr_title = pr_title(issue, task, &commit_summary);
     let pr_body = format!(
         "## Summary\n{commit_body}\n\n## Related Issue\nCloses #{issue}"
     );
 
-    let output = run_command_in(
-        "gh",
-        &["pr", "create", "--title", &pr_title, "--body", &pr_body],
-        Some(&work_dir),
-    )
-    .context("Failed to create PR")?;
+    let base = match task.and_then(|task| stacked_base(repo, issue, task)) {
+        Some(base) => {
+            info(&format!("Stacked PR: targeting {base}"));
+            base
+        }
+        None => BASE_BRANCH.to_string(),
+    };
+
+    let pr = repo.forge.create_pr(&NewPullRequest {
+        title: &pr_title,
+        body: &pr_body,
+        head: &branch,
+        base: &base,
+    })?;
 
-    println!("{output}");
+    println!("{}", pr.url);
     info("PR created!");
     Ok(())
 }
+
+/// Build the PR title: `[TASK-<issue>-<task>] <summary>`, or
+/// `[ISSUE-<issue>] <summary>` when the task is unknown.
+pub fn pr_title(issue: u32, task: Option<u32>, summary: &str) -> String {
+    match task {
+        Some(task) => format!("[TASK-{issue}-{task}] {summary}"),
+        None => format!("[ISSUE-{issue}] {summary}"),
+    }
+}
+
+/// Merge the pull request for an issue and run the post-merge cleanup.
+///
+/// After the forge reports the PR as merged, marks the task done, restacks
+/// tasks stacked on it, removes the worktree and local branch, deletes the
+/// remote branch and rolls up the PLAN.md status.
+pub fn merge(repo: &Repo, issue: u32, task: Option<u32>, method: MergeMethod) -> Result<()> {
+    let forge = repo.forge;
+    let branch = match task.and_then(|t| read_task(repo, issue, t).ok()?.branch_name) {
+        Some(branch) => branch,
+        None => issue_branch(repo, issue)?,
+    };
+
+    let pr = forge
+        .find_pr(&branch)?
+        .with_context(|| {
+            Error::new(ErrorKind::NotFound, format!("No PR found for branch {branch}"))
+        })?;
+    if pr.state == PrState::Merged {
+        info(&format!("PR #{} is already merged", pr.number));
+    } else {
+        ensure_checks_pass(forge, pr.number)?;
+        info(&format!("Merging PR #{} ({branch})...", pr.number));
+        forge.merge_pr(pr.number, method)?;
+        wait_for_merge(forge, &branch, MERGE_POLL_INTERVAL)?;
+        info("PR merged");
+    }
+
+    let task = task.or_else(|| find_task_by_branch(repo, issue, &branch));
+    match task {
+        Some(task) => {
+            set_task_status(repo, issue, task, "done")?;
+            // Restack children while the parent branch still exists, and
+            // before deleting the remote branch closes PRs targeting it.
+            let children = child_tasks(repo, issue, task)?;
+            if !children.is_empty() {
+                fetch_base(repo)?;
+            }
+            for child in children {
+                if let Err(e) = restack_task(repo, &child.frontmatter, Some(&branch)) {
+                    warn(&format!("Failed to restack task {}: {e:#}", child.number));
+                }
+            }
+        }
+        None => warn("No TASK.md matches this branch; pass --task to mark it done"),
+    }
+    retarget_prs(forge, &branch, BASE_BRANCH)?;
+
+    wt::remove_merged_branch(repo, &branch)?;
+
+    info(&format!("Deleting remote branch: {branch}"));
+    if let Err(e) = repo.git(&["push", "origin", "--delete", &branch]) {
+        warn(&format!("Failed to delete remote branch (already deleted?): {e}"));
+    }
+
+    rollup_plan_status(repo, issue)?;
+
+    info("Merge complete!");
+    Ok(())
+}
+
+/// Rebase tasks whose parent has merged onto the base branch and retarget their PRs.
+pub fn restack(repo: &Repo, issue: u32) -> Result<()> {
+    let tasks = list_tasks(repo, issue)?;
+    let stale: Vec<_> = tasks
+        .iter()
+        .filter(|t| t.frontmatter.status != "done")
+        .filter_map(|t| {
+            let parent = tasks.iter().find(|p| Some(p.number) == t.frontmatter.parent_task)?;
+            (parent.frontmatter.status == "done").then_some((t, parent))
+        })
+        .collect();
+
+    if stale.is_empty() {
+        info(&format!("No stacked tasks with merged parents in issue #{issue}"));
+        return Ok(());
+    }
+
+    fetch_base(repo)?;
+    for (task, parent) in stale {
+        info(&format!("Restacking task {} (parent task {} merged)", task.number, parent.number));
+        restack_task(repo, &task.frontmatter, parent.frontmatter.branch_name.as_deref())?;
+    }
+    info("Restack complete!");
+    Ok(())
+}
+
+/// Rebase a stacked task's branch onto the base branch, push it and
+/// retarget its PR.
+///
+/// `parent_branch` is used as the rebase upstream while it still exists
+/// locally, so only the task's own commits are replayed.
+fn restack_task(repo: &Repo, task: &TaskFrontmatter, parent_branch: Option<&str>) -> Result<()> {
+    let forge = repo.forge;
+    let branch = task
+        .branch_name
+        .as_deref()
+        .with_context(|| {
+            let message = format!("Task {} has no branchName", task.task_number);
+            Error::new(ErrorKind::Validation, message)
+        })?;
+    let work_dir = repo.find_worktree_for_branch(branch).with_context(|| {
+        Error::new(ErrorKind::NotFound, format!("No worktree for {branch}"))
+            .with_hint(format!("Run 'aidd wt ensure ... --task {}' first", task.task_number))
+    })?;
+    let git = |args: &[&str]| repo.runner.run("git", args, Some(&work_dir));
+    let onto = format!("origin/{BASE_BRANCH}");
+    let pr = forge.find_pr(branch)?.filter(|pr| pr.state == PrState::Open);
+
+    let up_to_date = git(&["merge-base", "--is-ancestor", &onto, "HEAD"]).is_ok();
+    if up_to_date {
+        info(&format!("{branch} already contains {onto}"));
+    } else {
+        info(&format!("Rebasing {branch} onto {onto}..."));
+        let rebase = match parent_branch.filter(|b| repo.local_branch_exists(b)) {
+            Some(parent) => git(&["rebase", "--onto", &onto, parent]),
+            None => git(&["rebase", &onto]),
+        };
+        if let Err(e) = rebase {
+            let _ = git(&["rebase", "--abort"]);
+            if let Some(pr) = &pr {
+                notify_restack_failure(forge, pr.number, &onto)?;
+            }
+            return Err(e.context(
+                Error::new(ErrorKind::GitConflict, format!("Rebase of {branch} failed"))
+                    .with_hint(format!(
+                        "Rebase manually in {}, then run `aidd pr restack`",
+                        work_dir.display()
+                    )),
+            ));
+        }
+
+        info(&format!("Pushing {branch}..."));
+        repo.runner
+            .run_streamed(
+                "git push",
+                "git",
+                &["push", "--force-with-lease", "origin", branch],
+                Some(&work_dir),
+            )
+            .context("Failed to push restacked branch")?;
+    }
+
+    match pr {
+        Some(pr) if pr.base != BASE_BRANCH => {
+            info(&format!("Retargeting PR #{} to {BASE_BRANCH}...", pr.number));
+            let update = PrUpdate { base: Some(BASE_BRANCH), ..Default::default() };
+            forge.update_pr(pr.number, &update)?;
+        }
+        Some(_) => {}
+        None => info(&format!("No open PR for {branch} to retarget")),
+    }
+    Ok(())
+}
+
+/// Tell the PR author that the automatic restack needs manual help,
+/// without repeating the notice on every attempt.
+fn notify_restack_failure(forge: &dyn Forge, number: u32, onto: &str) -> Result<()> {
+    let body = format!(
+        "aidd could not rebase this stacked PR onto `{onto}` after its parent merged. \
+         Please rebase manually and run `aidd pr restack`."
+    );
+    if forge.comments(number)?.iter().any(|c| c.body == body) {
+        return Ok(());
+    }
+    forge.comment(number, &body)
+}
+
+/// Point every open PR based on `from` at `to`.
+///
+/// Deleting a branch closes the PRs that target it, so this must run before
+/// the merged branch is deleted on the remote.
+fn retarget_prs(forge: &dyn Forge, from: &str, to: &str) -> Result<()> {
+    for pr in forge.list_prs(Some(from))? {
+        info(&format!("Retargeting PR #{} ({}) to {to}...", pr.number, pr.head));
+        forge.update_pr(pr.number, &PrUpdate { base: Some(to), ..Default::default() })?;
+    }
+    Ok(())
+}
+
+/// Refuse to merge while any CI check is failing.
+fn ensure_checks_pass(forge: &dyn Forge, number: u32) -> Result<()> {
+    let checks = forge.checks(number)?;
+    let failing: Vec<&str> = checks
+        .iter()
+        .filter(|c| c.state == CheckState::Fail)
+        .map(|c| c.name.as_str())
+        .collect();
+    if !failing.is_empty() {
+        anyhow::bail!(Error::new(
+            ErrorKind::Validation,
+            format!("PR #{number} has failing checks: {}", failing.join(", ")),
+        )
+        .with_hint("Fix the failing checks and push, then rerun `aidd pr merge`"));
+    }
+    let pending = checks.iter().filter(|c| c.state == CheckState::Pending).count();
+    if pending > 0 {
+        info(&format!("{pending} checks still pending"));
+    }
+    Ok(())
+}
+
+/// The branch a stacked task's PR targets, or `None` for the default base.
+fn stacked_base(repo: &Repo, issue: u32, task: u32) -> Option<String> {
+    let parent = read_task(repo, issue, task).ok()?.parent_task?;
+    let parent_fm = read_task(repo, issue, parent).ok()?;
+    if parent_fm.status == "done" {
+        return None;
+    }
+    parent_fm.branch_name
+}
+
+/// Fetch the base branch so `origin/{BASE_BRANCH}` includes the merge.
+fn fetch_base(repo: &Repo) -> Result<()> {
+    repo.git(&["fetch", "origin", BASE_BRANCH]).context("Failed to fetch base branch")?;
+    Ok(())
+}
+
+/// Determine the branch of an issue from its local branches, or from its
+/// worktree; fails with the candidates when stacked tasks gave it several.
+fn issue_branch(repo: &Repo, issue: u32) -> Result<String> {
+    let mut branches = repo.find_branches_for_issue(issue);
+    if branches.len() > 1 {
+        anyhow::bail!(Error::new(
+            ErrorKind::Validation,
+            format!("Issue {issue} has several branches: {}", branches.join(", "))
+        )
+        .with_hint("Pass --task to choose the task to merge"));
+    }
+    if let Some(branch) = branches.pop() {
+        return Ok(branch);
+    }
+    let work_dir = repo.find_worktree_for_issue(issue).with_context(|| {
+        Error::new(ErrorKind::NotFound, format!("No worktree or branch found for issue {issue}"))
+    })?;
+    repo.runner
+        .run("git", &["rev-parse", "--abbrev-ref", "HEAD"], Some(&work_dir))
+        .context("Failed to get worktree branch")
+}
+
+/// Poll the PR state until it is merged.
+fn wait_for_merge(forge: &dyn Forge, branch: &str, interval: Duration) -> Result<()> {
+    let started = Instant::now();
+    loop {
+        let state = forge
+            .find_pr(branch)?
+            .with_context(|| {
+                Error::new(ErrorKind::NotFound, format!("PR for {branch} disappeared"))
+            })?
+            .state;
+
+        match state {
+            PrState::Merged => return Ok(()),
+            PrState::Closed => anyhow::bail!("PR for {branch} was closed without merging"),
+            PrState::Open => {}
+        }
+
+        if started.elapsed() >= MERGE_WAIT_TIMEOUT {
+            anyhow::bail!(
+                "Timed out after {}s waiting for PR for {branch} to merge",
+                MERGE_WAIT_TIMEOUT.as_secs()
+            );
+        }
+        info("Waiting for merge...");
+        interrupt::check()?;
+        thread::sleep(interval);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forge::fake::FakeForge;
+    use crate::forge::Check;
+
+    #[test]
+    fn test_pr_title() {
+        assert_eq!(pr_title(11, Some(2), "feat(web): add"), "[TASK-11-2] feat(web): add");
+        assert_eq!(pr_title(11, None, "feat(web): add"), "[ISSUE-11] feat(web): add");
+    }
+
+    #[test]
+    fn test_wait_for_merge_after_merge() {
+        let forge = FakeForge::new().with_pr("feat/1-x", "main");
+        forge.merge_pr(1, MergeMethod::Squash).unwrap();
+        wait_for_merge(&forge, "feat/1-x", Duration::ZERO).unwrap();
+        assert_eq!(forge.merges(), vec![(1, MergeMethod::Squash)]);
+    }
+
+    #[test]
+    fn test_ensure_checks_pass() {
+        let check = |name: &str, state| Check { name: name.to_string(), state };
+        let forge = FakeForge::new()
+            .with_pr("feat/1-x", "main")
+            .with_checks(1, vec![
+                check("lint", CheckState::Pass),
+                check("test", CheckState::Pending),
+            ])
+            .with_pr("feat/1-y", "main")
+            .with_checks(2, vec![check("lint", CheckState::Fail)]);
+
+        assert!(ensure_checks_pass(&forge, 1).is_ok());
+        let err = ensure_checks_pass(&forge, 2).unwrap_err();
+        assert!(err.to_string().contains("lint"));
+    }
+
+    #[test]
+    fn test_retarget_prs() {
+        let forge = FakeForge::new()
+            .with_pr("feat/1-child", "feat/1-parent")
+            .with_pr("feat/1-other", "main");
+        retarget_prs(&forge, "feat/1-parent", "main").unwrap();
+        assert!(forge.prs().iter().all(|pr| pr.base == "main"));
+    }
+
+    #[test]
+    fn test_notify_restack_failure_once() {
+        let forge = FakeForge::new().with_pr("feat/1-child", "feat/1-parent");
+        notify_restack_failure(&forge, 1, "origin/main").unwrap();
+        notify_restack_failure(&forge, 1, "origin/main").unwrap();
+        assert_eq!(forge.comments(1).unwrap().len(), 1);
+    }
+}
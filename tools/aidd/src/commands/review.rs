@@ -0,0 +1,432 @@
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::commands::pr::pr_title;
+use crate::conventional;
+use crate::error::{Error, ErrorKind};
+use crate::frontmatter::split_frontmatter;
+use crate::helpers::{info, BASE_BRANCH};
+use crate::repo::Repo;
+use crate::tasks::{find_task_by_branch, read_task};
+
+/// File extensions scanned for leftover `console.log` calls and imports.
+const SCRIPT_EXTENSIONS: &[&str] = &["ts", "tsx", "js", "jsx", "mjs", "cjs"];
+
+/// `.env` variants that are meant to be committed.
+const ENV_TEMPLATES: &[&str] = &[".env.sample", ".env.example"];
+
+/// Outcome of a single checklist item.
+pub struct CheckResult {
+    pub name: &'static str,
+    pub problems: Vec<String>,
+}
+
+impl CheckResult {
+    fn new(name: &'static str, problems: Vec<String>) -> Self {
+        Self { name, problems }
+    }
+
+    pub fn passed(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Run the `.agent/rules/review-checklist.md` checks against the branch of an issue.
+///
+/// Prints one line per check and fails if any check fails.
+pub fn run(repo: &Repo, issue: u32, task: Option<u32>) -> Result<()> {
+    let work_dir = repo.work_dir(issue, task)?;
+    let results = check_all(repo, issue, task, &work_dir)?;
+
+    println!();
+    for result in &results {
+        if result.passed() {
+            println!("[PASS] {}", result.name);
+        } else {
+            println!("[FAIL] {}", result.name);
+            for problem in &result.problems {
+                println!("       {problem}");
+            }
+        }
+    }
+    println!();
+
+    let failed = results.iter().filter(|r| !r.passed()).count();
+    if failed > 0 {
+        anyhow::bail!(Error::new(
+            ErrorKind::Validation,
+            format!("Review check failed: {failed} of {} checks failed", results.len()),
+        )
+        .with_hint("Fix the [FAIL] items listed above and rerun `aidd review-check`"));
+    }
+    info(&format!("All {} review checks passed", results.len()));
+    Ok(())
+}
+
+/// Run every check in `work_dir`, comparing the branch against the base branch.
+fn check_all(
+    repo: &Repo,
+    issue: u32,
+    task: Option<u32>,
+    work_dir: &Path,
+) -> Result<Vec<CheckResult>> {
+    let git = |args: &[&str]| repo.runner.run("git", args, Some(work_dir));
+
+    let branch =
+        git(&["rev-parse", "--abbrev-ref", "HEAD"]).context("Failed to get current branch")?;
+    let task = task.or_else(|| find_task_by_branch(repo, issue, &branch));
+
+    // Prefer the remote base so a stale local main does not widen the diff.
+    let remote_base = format!("origin/{BASE_BRANCH}");
+    let base = if git(&["rev-parse", "--verify", "--quiet", &remote_base]).is_ok() {
+        remote_base
+    } else {
+        BASE_BRANCH.to_string()
+    };
+    let range = format!("{base}...HEAD");
+
+    let diff = git(&["diff", "--unified=0", &range]).context("Failed to diff branch")?;
+    let files: Vec<String> = git(&["diff", "--name-only", &range])
+        .context("Failed to list changed files")?
+        .lines()
+        .map(str::to_string)
+        .collect();
+    let subjects: Vec<String> = git(&["log", "--format=%s", &format!("{base}..HEAD")])
+        .context("Failed to list branch commits")?
+        .lines()
+        .map(str::to_string)
+        .collect();
+    let added = added_lines(&diff);
+
+    // A missing or unreachable forge just means there is no PR yet.
+    let title = match repo.forge.find_pr(&branch).ok().flatten() {
+        Some(pr) => pr.title,
+        None => {
+            let summary = git(&["log", "-1", "--format=%s"]).unwrap_or_default();
+            pr_title(issue, task, &summary)
+        }
+    };
+
+    info("Running review checks...");
+    Ok(vec![
+        CheckResult::new("lint", run_step(repo, work_dir, "mise", &["run", "lint"])),
+        CheckResult::new("test", run_tests(repo, work_dir)),
+        CheckResult::new("task-status", check_task_status(repo, issue, task)),
+        CheckResult::new("task-verification", check_task_verification(repo, issue, task)),
+        CheckResult::new("console-log", check_console_log(&added)),
+        CheckResult::new("env-files", check_env_files(&files)),
+        CheckResult::new("pr-title", check_pr_title(&title, issue)),
+        CheckResult::new("conventional-commits", check_commit_subjects(&subjects)),
+        CheckResult::new("module-boundaries", check_module_boundaries(&added)),
+    ])
+}
+
+/// Run a verification step, reporting its failure output as the problem.
+fn run_step(repo: &Repo, work_dir: &Path, program: &str, args: &[&str]) -> Vec<String> {
+    match repo.runner.run(program, args, Some(work_dir)) {
+        Ok(_) => Vec::new(),
+        Err(e) => vec![format!("{e:#}")],
+    }
+}
+
+/// Run `bun test`, treating "no tests found" as a pass.
+fn run_tests(repo: &Repo, work_dir: &Path) -> Vec<String> {
+    run_step(repo, work_dir, "bun", &["test"])
+        .into_iter()
+        .filter(|p| !p.contains("No tests found"))
+        .collect()
+}
+
+fn check_task_status(repo: &Repo, issue: u32, task: Option<u32>) -> Vec<String> {
+    let Some(task) = task else {
+        return vec!["No TASK.md matches this branch (pass --task)".to_string()];
+    };
+    match read_task(repo, issue, task) {
+        Ok(fm) if fm.status == "done" => Vec::new(),
+        Ok(fm) => vec![format!("TASK {issue}/{task} status is '{}', expected 'done'", fm.status)],
+        Err(e) => vec![format!("{e:#}")],
+    }
+}
+
+fn check_task_verification(repo: &Repo, issue: u32, task: Option<u32>) -> Vec<String> {
+    let Some(task) = task else {
+        return vec!["No TASK.md matches this branch (pass --task)".to_string()];
+    };
+    match repo.runner.read_file(&repo.task_file(issue, task)) {
+        Ok(content) => unchecked_verification_items(&content)
+            .into_iter()
+            .map(|item| format!("Unchecked verification item: {item}"))
+            .collect(),
+        Err(e) => vec![format!("{e:#}")],
+    }
+}
+
+/// Collect unchecked `- [ ]` items from the `# Verification` section of a TASK.md.
+fn unchecked_verification_items(content: &str) -> Vec<String> {
+    let body = split_frontmatter(content).map(|(_, body)| body).unwrap_or(content);
+    body.lines()
+        .skip_while(|l| l.trim() != "# Verification")
+        .skip(1)
+        .take_while(|l| !l.starts_with("# "))
+        .filter_map(|l| l.trim().strip_prefix("- [ ]"))
+        .map(|item| item.trim().to_string())
+        .collect()
+}
+
+/// A line added by the branch diff.
+#[derive(Debug, PartialEq)]
+struct AddedLine {
+    file: String,
+    line: usize,
+    text: String,
+}
+
+/// Extract added lines (with their new line numbers) from a unified diff.
+fn added_lines(diff: &str) -> Vec<AddedLine> {
+    let mut result = Vec::new();
+    let mut file = String::new();
+    let mut line = 0;
+
+    for raw in diff.lines() {
+        if let Some(path) = raw.strip_prefix("+++ ") {
+            file = path.strip_prefix("b/").unwrap_or(path).to_string();
+        } else if raw.starts_with("--- ") {
+            continue;
+        } else if let Some(hunk) = raw.strip_prefix("@@ ") {
+            // @@ -a,b +c,d @@
+            line = hunk
+                .split_whitespace()
+                .find_map(|part| part.strip_prefix('+'))
+                .and_then(|range| range.split(',').next())
+                .and_then(|start| start.parse().ok())
+                .unwrap_or(0);
+        } else if let Some(text) = raw.strip_prefix('+') {
+            result.push(AddedLine { file: file.clone(), line, text: text.to_string() });
+            line += 1;
+        } else if !raw.starts_with('-') && !raw.starts_with('\\') {
+            line += 1;
+        }
+    }
+    result
+}
+
+fn is_script(file: &str) -> bool {
+    Path::new(file)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|ext| SCRIPT_EXTENSIONS.contains(&ext))
+}
+
+fn check_console_log(added: &[AddedLine]) -> Vec<String> {
+    added
+        .iter()
+        .filter(|l| is_script(&l.file) && l.text.contains("console.log"))
+        .map(|l| format!("{}:{}: {}", l.file, l.line, l.text.trim()))
+        .collect()
+}
+
+fn check_env_files(files: &[String]) -> Vec<String> {
+    files
+        .iter()
+        .filter(|f| {
+            let name = Path::new(f).file_name().and_then(|n| n.to_str()).unwrap_or_default();
+            (name == ".env" || name.starts_with(".env.")) && !ENV_TEMPLATES.contains(&name)
+        })
+        .map(|f| format!("{f} must not be committed"))
+        .collect()
+}
+
+/// PR titles follow `[TASK-<issue>-<task>] <summary>`.
+///
+/// `pr create` falls back to `[ISSUE-<issue>]` when no task is known; such
+/// a title fails, as the checklist requires every PR to name its task.
+fn check_pr_title(title: &str, issue: u32) -> Vec<String> {
+    let Some((tag, summary)) = title.strip_prefix('[').and_then(|rest| rest.split_once("] "))
+    else {
+        return vec![format!("'{title}' does not match '[TASK-{issue}-<task>] <summary>'")];
+    };
+    let task_ok = tag
+        .strip_prefix(&format!("TASK-{issue}-"))
+        .is_some_and(|task| task.parse::<u32>().is_ok());
+
+    if task_ok && !summary.trim().is_empty() {
+        Vec::new()
+    } else if tag == format!("ISSUE-{issue}") {
+        vec![format!(
+            "'{title}' names no task; pass --task or record the branch with \
+             `aidd wt ensure ... --task <n>`"
+        )]
+    } else {
+        vec![format!("'{title}' does not match '[TASK-{issue}-<task>] <summary>'")]
+    }
+}
+
+fn check_commit_subjects(subjects: &[String]) -> Vec<String> {
+    subjects
+        .iter()
+        .filter_map(|s| conventional::validate(s).err().map(|e| format!("'{s}': {e}")))
+        .collect()
+}
+
+/// Modules may only reach other contexts through `@contracts/*`.
+///
+/// Flags imports in `packages/modules/{ctx}/` of another context's
+/// `@modules/*` package or relative paths leaving the context, and any
+/// `@modules/*` import from `packages/contracts/`.
+fn check_module_boundaries(added: &[AddedLine]) -> Vec<String> {
+    added
+        .iter()
+        .filter(|l| is_script(&l.file))
+        .filter_map(|l| {
+            let spec = import_specifier(&l.text)?;
+            boundary_violation(&l.file, spec)
+                .map(|reason| format!("{}:{}: {reason}", l.file, l.line))
+        })
+        .collect()
+}
+
+fn boundary_violation(file: &str, spec: &str) -> Option<String> {
+    if file.starts_with("packages/contracts/") && spec.starts_with("@modules/") {
+        return Some(format!("contracts must not import '{spec}'"));
+    }
+
+    let context = file.strip_prefix("packages/modules/")?.split('/').next()?;
+    if let Some(package) = spec.strip_prefix("@modules/") {
+        if !package.starts_with(&format!("{context}-")) {
+            return Some(format!("'{spec}' crosses the '{context}' module boundary"));
+        }
+    } else if spec.starts_with('.') {
+        let dir = Path::new(file).parent()?;
+        let target = normalize(&dir.join(spec));
+        if !target.starts_with(format!("packages/modules/{context}")) {
+            return Some(format!("'{spec}' crosses the '{context}' module boundary"));
+        }
+    }
+    None
+}
+
+/// Return the module specifier of an `import`/`export ... from` line.
+fn import_specifier(line: &str) -> Option<&str> {
+    let trimmed = line.trim_start();
+    let rest = match trimmed.find(" from ") {
+        Some(idx) => &trimmed[idx + " from ".len()..],
+        None => trimmed.strip_prefix("import ")?,
+    };
+    let rest = rest.trim_start();
+    let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let inner = &rest[1..];
+    inner.find(quote).map(|end| &inner[..end])
+}
+
+/// Resolve `.` and `..` components without touching the filesystem.
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "\
+diff --git a/packages/modules/library/write/src/a.ts b/packages/modules/library/write/src/a.ts
+--- a/packages/modules/library/write/src/a.ts
++++ b/packages/modules/library/write/src/a.ts
+@@ -10,0 +11,3 @@ export function x() {
++import { Book } from \"@modules/catalog-write\";
++import { Lib } from \"@modules/library-read\";
++  console.log(\"debug\");
+@@ -40 +43 @@
+-const a = 1;
++import { y } from \"../../../../catalog/write/src/y.ts\";
+";
+
+    #[test]
+    fn test_added_lines_tracks_files_and_line_numbers() {
+        let lines = added_lines(DIFF);
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0].file, "packages/modules/library/write/src/a.ts");
+        assert_eq!(lines[0].line, 11);
+        assert_eq!(lines[2].line, 13);
+        assert_eq!(lines[3].line, 43);
+    }
+
+    #[test]
+    fn test_check_console_log() {
+        let problems = check_console_log(&added_lines(DIFF));
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("a.ts:13"));
+    }
+
+    #[test]
+    fn test_check_module_boundaries() {
+        let problems = check_module_boundaries(&added_lines(DIFF));
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].contains("@modules/catalog-write"));
+        assert!(problems[1].contains("catalog/write"));
+    }
+
+    #[test]
+    fn test_boundary_allows_contracts_and_own_context() {
+        let file = "packages/modules/library/write/src/a.ts";
+        assert!(boundary_violation(file, "@contracts/catalog-public").is_none());
+        assert!(boundary_violation(file, "../models/library.ts").is_none());
+        assert!(boundary_violation("apps/web/src/a.ts", "@modules/library-write").is_none());
+        assert!(boundary_violation("packages/contracts/x/src/a.ts", "@modules/x-write").is_some());
+    }
+
+    #[test]
+    fn test_import_specifier() {
+        assert_eq!(import_specifier("import { a } from \"x\";"), Some("x"));
+        assert_eq!(import_specifier("export * from './y';"), Some("./y"));
+        assert_eq!(import_specifier("import './side-effect';"), Some("./side-effect"));
+        assert_eq!(import_specifier("const a = 1;"), None);
+    }
+
+    #[test]
+    fn test_check_env_files() {
+        let files = vec![
+            ".env".to_string(),
+            "apps/web/.env.local".to_string(),
+            ".env.sample".to_string(),
+            "src/env.ts".to_string(),
+        ];
+        let problems = check_env_files(&files);
+        assert_eq!(problems.len(), 2);
+    }
+
+    #[test]
+    fn test_check_pr_title() {
+        assert!(check_pr_title("[TASK-11-2] Add deploy", 11).is_empty());
+        assert!(check_pr_title("[ISSUE-11] Add deploy", 11)[0].contains("names no task"));
+        assert!(!check_pr_title("[TASK-12-2] Add deploy", 11).is_empty());
+        assert!(!check_pr_title("[TASK-11-x] Add deploy", 11).is_empty());
+        assert!(!check_pr_title("Add deploy", 11).is_empty());
+        assert!(!check_pr_title("[TASK-11-2] ", 11).is_empty());
+    }
+
+    #[test]
+    fn test_check_commit_subjects() {
+        let subjects = vec!["feat(web): add page".to_string(), "wip".to_string()];
+        let problems = check_commit_subjects(&subjects);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("'wip'"));
+    }
+
+    #[test]
+    fn test_unchecked_verification_items() {
+        let content = "---\nstatus: done\n---\n\n# Verification\n- [x] lint\n- [ ] manual check\n\n\
+                       # Commit Plan\n- [ ] not verification\n";
+        assert_eq!(unchecked_verification_items(content), vec!["manual check"]);
+    }
+}
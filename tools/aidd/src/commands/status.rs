@@ -1,64 +1,140 @@
-use std::fs;
+use anyhow::Result;
+use jiff::tz::TimeZone;
+use jiff::Timestamp;
 
-use anyhow::{Context, Result};
-
-use crate::frontmatter::parse_plan_frontmatter;
-use crate::helpers::{info, repo_root};
+use crate::clock;
+use crate::deps;
+use crate::frontmatter::{parse_plan_frontmatter, PlanFrontmatter};
+use crate::helpers::{info, warn};
+use crate::repo::Repo;
+use crate::tasks::{list_tasks, TaskEntry};
 
 /// Display the status of all issues.
 ///
 /// Traverses the `features/` directory, reads frontmatter from
-/// PLAN.md files, and prints a formatted status overview.
-pub fn show() -> Result<()> {
+/// PLAN.md files, and prints a formatted status overview with the age of
+/// each plan, its creation time shown in `tz`, and the tasks blocked by
+/// unfinished dependencies.
+pub fn show(repo: &Repo, tz: &TimeZone) -> Result<()> {
     info("=== AI-Driven Development Status ===");
     println!();
 
-    let root = repo_root();
-    let features_dir = root.join("features");
+    let now = repo.clock.now();
+    let features_dir = repo.root.join("features");
 
-    if !features_dir.exists() {
+    if !repo.runner.exists(&features_dir) {
         println!("No features found.");
         return Ok(());
     }
 
-    let mut issue_dirs: Vec<_> = fs::read_dir(&features_dir)
-        .with_context(|| format!("Failed to read {}", features_dir.display()))?
-        .filter_map(|e| e.ok())
-        .filter(|e| e.path().is_dir())
+    let mut issue_dirs: Vec<String> = repo
+        .runner
+        .list_dir(&features_dir)?
+        .into_iter()
+        .filter(|name| repo.runner.is_dir(&features_dir.join(name)))
         .collect();
-
-    issue_dirs.sort_by_key(|e| {
-        e.file_name()
-            .to_string_lossy()
-            .parse::<u32>()
-            .unwrap_or(u32::MAX)
-    });
+    issue_dirs.sort_by_key(|name| name.parse::<u32>().unwrap_or(u32::MAX));
 
     if issue_dirs.is_empty() {
         println!("No features found.");
         return Ok(());
     }
 
-    for entry in &issue_dirs {
-        let issue_num = entry.file_name().to_string_lossy().to_string();
-        let issue_path = entry.path();
-
+    for issue_num in &issue_dirs {
         // Read PLAN.md
-        let plan_path = issue_path.join("PLAN.md");
-        if plan_path.exists() {
-            let content = fs::read_to_string(&plan_path).unwrap_or_default();
-            let fm = parse_plan_frontmatter(&content);
-            let status = fm.map(|f| f.status).unwrap_or_else(|_| "unknown".to_string());
-            let title = {
-                let c = fs::read_to_string(&plan_path).unwrap_or_default();
-                parse_plan_frontmatter(&c).map(|f| f.title).unwrap_or_default()
-            };
-            println!("Issue #{issue_num}: [{status}] {title}");
+        let plan_path = features_dir.join(issue_num).join("PLAN.md");
+        if repo.runner.exists(&plan_path) {
+            let content = repo.runner.read_file(&plan_path).unwrap_or_default();
+            match parse_plan_frontmatter(&content) {
+                Ok(fm) => println!("Issue #{issue_num}: {}", plan_summary(&fm, now, tz)),
+                Err(_) => println!("Issue #{issue_num}: [unknown]"),
+            }
         } else {
             println!("Issue #{issue_num}: (no PLAN.md)");
         }
+
+        let Ok(number) = issue_num.parse::<u32>() else {
+            continue;
+        };
+        let tasks = list_tasks(repo, number)?;
+        if let Err(err) = deps::check(number, &tasks) {
+            warn(&format!("Issue #{issue_num}: {err:#}"));
+        }
+        for line in blocked_tasks(&tasks) {
+            println!("  {line}");
+        }
     }
 
     println!();
     Ok(())
 }
+
+/// `[status] title`, then when the plan was created if `createdAt` parses.
+fn plan_summary(fm: &PlanFrontmatter, now: Timestamp, tz: &TimeZone) -> String {
+    let summary = format!("[{}] {}", fm.status, fm.title);
+    match fm.created_at.parse::<Timestamp>() {
+        Ok(created) => format!(
+            "{summary} — created {} ({})",
+            clock::age(created, now),
+            clock::format_local(created, tz)
+        ),
+        Err(_) => summary,
+    }
+}
+
+/// `Task 3 [blocked] waits for 2` for each task held up by its dependencies.
+fn blocked_tasks(tasks: &[TaskEntry]) -> Vec<String> {
+    tasks
+        .iter()
+        .filter(|task| deps::state(task, tasks) == "blocked")
+        .map(|task| {
+            let waits: Vec<String> =
+                deps::blockers(task, tasks).iter().map(u32::to_string).collect();
+            format!("Task {} [blocked] waits for {}", task.number, waits.join(", "))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontmatter::TaskFrontmatter;
+
+    #[test]
+    fn test_plan_summary() {
+        let mut fm = PlanFrontmatter {
+            issue_number: 12,
+            title: "Add search".to_string(),
+            status: "doing".to_string(),
+            owner_agent: "claude".to_string(),
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+        };
+        let now = "2026-01-04T03:00:00Z".parse().unwrap();
+        let tz = clock::time_zone(Some("Asia/Tokyo")).unwrap();
+        assert_eq!(
+            plan_summary(&fm, now, &tz),
+            "[doing] Add search — created 3 days ago (2026-01-01 09:00 JST)"
+        );
+
+        fm.created_at = "someday".to_string();
+        assert_eq!(plan_summary(&fm, now, &tz), "[doing] Add search");
+    }
+
+    #[test]
+    fn test_blocked_tasks() {
+        let task = |number: u32, status: &str, depends_on: Vec<u32>| TaskEntry {
+            number,
+            frontmatter: TaskFrontmatter { depends_on, ..TaskFrontmatter::test(number, status) },
+        };
+        let tasks = [
+            task(1, "done", vec![]),
+            task(2, "doing", vec![1]),
+            task(3, "todo", vec![1, 2]),
+            task(4, "todo", vec![2, 3]),
+        ];
+        assert_eq!(
+            blocked_tasks(&tasks),
+            ["Task 3 [blocked] waits for 2", "Task 4 [blocked] waits for 2, 3"]
+        );
+    }
+}
@@ -1,29 +1,47 @@
-use std::fs;
+use std::path::Path;
 
 use anyhow::{Context, Result};
 
-use crate::helpers::{
-    branch_name, find_branches_for_issue, find_worktree_for_issue, info, repo_root, run_command,
-    run_command_in, run_command_inherit, supabase_ports, supabase_project_id, warn, worktree_path,
-};
+use crate::error::{Error, ErrorKind};
+use crate::helpers::{branch_name, info, supabase_ports, supabase_project_id, warn, BASE_BRANCH};
+use crate::interrupt;
+use crate::repo::Repo;
+use crate::tasks::{read_task, update_task_fields};
 
 /// Create a worktree for the given issue (idempotent).
 ///
 /// If the worktree already exists, prints its path and returns.
 /// Otherwise, creates the branch and worktree, installs dependencies,
 /// and copies `.env` if present.
-pub fn ensure(prefix: &str, issue: u32, summary: &str) -> Result<()> {
+///
+/// With `task`, the branch and worktree are recorded in the task's TASK.md,
+/// and a task stacked on a `parentTask` branches from the parent's branch.
+pub fn ensure(
+    repo: &Repo,
+    prefix: &str,
+    issue: u32,
+    summary: &str,
+    task: Option<u32>,
+) -> Result<()> {
     let branch = branch_name(prefix, issue, summary);
-    let wt_path = worktree_path(issue, summary);
-    let root = repo_root();
+    let wt_path = repo.worktree_path(issue, summary);
+    let root = &repo.root;
 
     // Already exists?
-    if wt_path.exists() {
+    if repo.runner.exists(&wt_path) {
         info(&format!("Worktree already exists: {}", wt_path.display()));
+        if let Some(task) = task {
+            record_task_branch(repo, issue, task, &branch, summary)?;
+        }
         println!("{}", wt_path.display());
         return Ok(());
     }
 
+    let start_point = match task {
+        Some(task) => start_point_for_task(repo, issue, task)?,
+        None => BASE_BRANCH.to_string(),
+    };
+
     info(&format!(
         "Creating worktree: {} (branch: {branch})",
         wt_path.display()
@@ -31,58 +49,61 @@ pub fn ensure(prefix: &str, issue: u32, summary: &str) -> Result<()> {
 
     // Ensure parent directory exists
     if let Some(parent) = wt_path.parent() {
-        fs::create_dir_all(parent)
-            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        repo.runner.create_dir_all(parent)?;
     }
 
-    // Check if branch already exists
-    let branch_exists = run_command("git", &["-C", &root.to_string_lossy(), "branch", "--list", &branch])
-        .map(|out| !out.is_empty())
-        .unwrap_or(false);
-
     let wt_str = wt_path.to_string_lossy().to_string();
 
-    if branch_exists {
-        run_command("git", &["-C", &root.to_string_lossy(), "worktree", "add", &wt_str, &branch])
-            .context("Failed to create worktree with existing branch")?;
+    let new_branch = !repo.local_branch_exists(&branch);
+    if new_branch {
+        repo.git(&["worktree", "add", "-b", &branch, &wt_str, &start_point])
+            .context("Failed to create worktree with new branch")?;
     } else {
-        run_command("git", &[
-            "-C",
-            &root.to_string_lossy(),
-            "worktree",
-            "add",
-            "-b",
-            &branch,
-            &wt_str,
-            "main",
-        ])
-        .context("Failed to create worktree with new branch")?;
+        repo.git(&["worktree", "add", &wt_str, &branch])
+            .context("Failed to create worktree with existing branch")?;
+    }
+
+    // An interrupted setup leaves a worktree that looks ready but is not.
+    let (abort_path, abort_branch) = (wt_path.clone(), branch.clone());
+    interrupt::on_abort(&format!("remove half-built worktree {wt_str}"), move |repo| {
+        remove_worktree(repo, &abort_path)?;
+        if new_branch {
+            delete_branch(repo, &abort_branch, true);
+        }
+        Ok(())
+    });
+
+    if let Some(task) = task {
+        record_task_branch(repo, issue, task, &branch, summary)?;
     }
 
     // Install dependencies
     info("Installing dependencies...");
-    if run_command_in("mise", &["install"], Some(&wt_path)).is_err() {
+    if repo.runner.run_streamed("mise install", "mise", &["install"], Some(&wt_path)).is_err() {
         warn("mise install failed or mise not found, skipping");
     }
-    run_command_in("bun", &["install"], Some(&wt_path))
+    repo.runner
+        .run_streamed("bun install", "bun", &["install"], Some(&wt_path))
         .context("Failed to run bun install")?;
 
     // Copy .env if it exists
     let env_src = root.join(".env");
     let env_dst = wt_path.join(".env");
-    if env_src.exists() {
-        fs::copy(&env_src, &env_dst).context("Failed to copy .env")?;
+    if repo.runner.exists(&env_src) {
+        repo.runner.copy_file(&env_src, &env_dst).context("Failed to copy .env")?;
         info("Copied .env from root");
     }
 
     // Patch supabase/config.toml for isolated Supabase instance
     let config_path = wt_path.join("packages/platform/supabase/config.toml");
-    if config_path.exists() {
+    if repo.runner.exists(&config_path) {
         info("Patching supabase/config.toml for isolated instance...");
         let ports = supabase_ports(issue);
         let project_id = supabase_project_id(issue);
 
-        let config = fs::read_to_string(&config_path)
+        let config = repo
+            .runner
+            .read_file(&config_path)
             .context("Failed to read supabase/config.toml")?;
 
         let config = config
@@ -99,35 +120,42 @@ pub fn ensure(prefix: &str, issue: u32, summary: &str) -> Result<()> {
             .replace("port = 54329", &format!("port = {}", ports.pooler))
             .replace("inspector_port = 8083", &format!("inspector_port = {}", ports.inspector));
 
-        fs::write(&config_path, config)
+        repo.runner
+            .write_file(&config_path, &config)
             .context("Failed to write patched supabase/config.toml")?;
 
         // Start Supabase
         info("Starting Supabase...");
-        run_command_inherit(
-            "supabase",
-            &["--workdir", "packages/platform/supabase", "start"],
-            Some(&wt_path),
-        )
-        .context("Failed to start Supabase")?;
+        repo.runner
+            .run_streamed(
+                "supabase start",
+                "supabase",
+                &["--workdir", "packages/platform/supabase", "start"],
+                Some(&wt_path),
+            )
+            .context("Failed to start Supabase")?;
 
         // Reset DB (runs migrations + seeds)
         info("Resetting Supabase database (migrations + seed)...");
-        run_command_inherit(
-            "supabase",
-            &["--workdir", "packages/platform/supabase", "db", "reset"],
-            Some(&wt_path),
-        )
-        .context("Failed to reset Supabase database")?;
+        repo.runner
+            .run_streamed(
+                "supabase db reset",
+                "supabase",
+                &["--workdir", "packages/platform/supabase", "db", "reset"],
+                Some(&wt_path),
+            )
+            .context("Failed to reset Supabase database")?;
 
         // Extract anon key from supabase status
         info("Extracting Supabase anon key...");
-        let status_output = run_command_in(
-            "supabase",
-            &["--workdir", "packages/platform/supabase", "status", "-o", "env"],
-            Some(&wt_path),
-        )
-        .unwrap_or_default();
+        let status_output = repo
+            .runner
+            .run(
+                "supabase",
+                &["--workdir", "packages/platform/supabase", "status", "-o", "env"],
+                Some(&wt_path),
+            )
+            .unwrap_or_default();
 
         let anon_key = status_output
             .lines()
@@ -142,14 +170,8 @@ pub fn ensure(prefix: &str, issue: u32, summary: &str) -> Result<()> {
             ports.api, ports.db, anon_key,
         );
 
-        use std::io::Write;
-        let mut env_file = fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&env_dst)
-            .context("Failed to open .env for appending")?;
-        env_file
-            .write_all(env_append.as_bytes())
+        repo.runner
+            .append_file(&env_dst, &env_append)
             .context("Failed to append Supabase config to .env")?;
 
         info("Supabase instance ready");
@@ -160,48 +182,220 @@ pub fn ensure(prefix: &str, issue: u32, summary: &str) -> Result<()> {
     Ok(())
 }
 
+/// Resolve the branch a task's worktree starts from.
+///
+/// Stacked tasks start from their parent's branch until the parent is done
+/// (merged); everything else starts from the base branch.
+fn start_point_for_task(repo: &Repo, issue: u32, task: u32) -> Result<String> {
+    let Some(parent) = read_task(repo, issue, task)?.parent_task else {
+        return Ok(BASE_BRANCH.to_string());
+    };
+
+    let parent_fm = read_task(repo, issue, parent)?;
+    if parent_fm.status == "done" {
+        return Ok(BASE_BRANCH.to_string());
+    }
+
+    let hint = format!("Run 'aidd wt ensure ... --task {parent}' first");
+    let parent_branch = parent_fm.branch_name.with_context(|| {
+        Error::new(ErrorKind::NotFound, format!("Parent task {parent} has no branchName"))
+            .with_hint(&hint)
+    })?;
+    if !repo.local_branch_exists(&parent_branch) {
+        anyhow::bail!(Error::new(
+            ErrorKind::NotFound,
+            format!("Parent branch {parent_branch} (task {parent}) does not exist locally"),
+        )
+        .with_hint(hint));
+    }
+
+    info(&format!("Stacking on parent task {parent} ({parent_branch})"));
+    Ok(parent_branch)
+}
+
+/// Record the branch and worktree of a task in its TASK.md.
+fn record_task_branch(
+    repo: &Repo,
+    issue: u32,
+    task: u32,
+    branch: &str,
+    summary: &str,
+) -> Result<()> {
+    update_task_fields(repo, issue, task, &[
+        ("branchName", branch),
+        ("worktreePath", &format!(".worktrees/{issue}-{summary}")),
+    ])
+}
+
 /// Remove a worktree and clean up its branch.
-pub fn remove(issue: u32) -> Result<()> {
-    let root = repo_root();
-    let wt_path = find_worktree_for_issue(issue);
-
-    if let Some(ref wt_path) = wt_path {
-        // Stop Supabase if config exists
-        let config_path = wt_path.join("packages/platform/supabase/config.toml");
-        if config_path.exists() {
-            info("Stopping Supabase...");
-            if let Err(e) = run_command_in(
-                "supabase",
-                &["--workdir", "packages/platform/supabase", "stop"],
-                Some(wt_path),
-            ) {
-                warn(&format!("Failed to stop Supabase: {e}"));
-            }
+pub fn remove(repo: &Repo, issue: u32) -> Result<()> {
+    match repo.find_worktree_for_issue(issue) {
+        Some(wt_path) => remove_worktree(repo, &wt_path)?,
+        None => info(&format!("No worktree found for issue {issue}")),
+    }
+
+    // Delete all local branches matching */{issue}-*
+    for branch in repo.find_branches_for_issue(issue) {
+        delete_branch(repo, &branch, false);
+    }
+
+    Ok(())
+}
+
+/// Remove the worktree of a merged branch and force-delete the branch.
+///
+/// Only the given branch is touched, so other (stacked) worktrees of the
+/// same issue survive. Force deletion is needed because git cannot tell
+/// that a squash- or rebase-merged branch has been merged.
+pub fn remove_merged_branch(repo: &Repo, branch: &str) -> Result<()> {
+    match repo.find_worktree_for_branch(branch) {
+        Some(wt_path) if wt_path != repo.root => remove_worktree(repo, &wt_path)?,
+        Some(_) => warn(&format!("{branch} is checked out in the main worktree, keeping it")),
+        None => info(&format!("No worktree found for branch {branch}")),
+    }
+    delete_branch(repo, branch, true);
+    Ok(())
+}
+
+/// Stop the worktree's Supabase instance (if any) and remove the worktree.
+fn remove_worktree(repo: &Repo, wt_path: &Path) -> Result<()> {
+    // Stop Supabase if config exists
+    let config_path = wt_path.join("packages/platform/supabase/config.toml");
+    if repo.runner.exists(&config_path) {
+        info("Stopping Supabase...");
+        if let Err(e) = repo.runner.run(
+            "supabase",
+            &["--workdir", "packages/platform/supabase", "stop"],
+            Some(wt_path),
+        ) {
+            warn(&format!("Failed to stop Supabase: {e}"));
         }
+    }
 
-        info(&format!("Removing worktree: {}", wt_path.display()));
-        run_command("git", &[
-            "-C",
-            &root.to_string_lossy(),
-            "worktree",
-            "remove",
-            &wt_path.to_string_lossy(),
-            "--force",
-        ])
+    info(&format!("Removing worktree: {}", wt_path.display()));
+    repo.git(&["worktree", "remove", &wt_path.to_string_lossy(), "--force"])
         .context("Failed to remove worktree")?;
-    } else {
-        info(&format!("No worktree found for issue {issue}"));
-    }
+    Ok(())
+}
 
-    // Delete all local branches matching */{issue}-*
-    for branch in find_branches_for_issue(issue) {
-        info(&format!("Deleting branch: {branch}"));
-        if run_command("git", &["-C", &root.to_string_lossy(), "branch", "-d", &branch]).is_err() {
+/// Delete a local branch, warning instead of failing if git refuses.
+fn delete_branch(repo: &Repo, branch: &str, force: bool) {
+    let flag = if force { "-D" } else { "-d" };
+    info(&format!("Deleting branch: {branch}"));
+    if repo.git(&["branch", flag, branch]).is_err() {
+        if force {
+            warn(&format!("Failed to delete branch {branch}"));
+        } else {
             warn(&format!(
                 "Branch {branch} not fully merged. Use 'git branch -D {branch}' to force delete."
             ));
         }
     }
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::forge::fake::FakeForge;
+    use crate::runner::fake::FakeRunner;
+
+    /// A fresh, empty directory to act as the repository root.
+    fn temp_root(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("aidd-wt-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_ensure_creates_branch_from_base() {
+        let root = temp_root("new-branch");
+        let (runner, forge) = (FakeRunner::new(), FakeForge::new());
+        let repo = Repo::for_test(root.clone(), &runner, &forge);
+
+        ensure(&repo, "feat", 3, "add-search", None).unwrap();
+
+        let wt = root.join(".worktrees/3-add-search");
+        assert_eq!(
+            runner.commands(),
+            vec![
+                "git branch --list feat/3-add-search".to_string(),
+                format!("git worktree add -b feat/3-add-search {} main", wt.display()),
+                "mise install".to_string(),
+                "bun install".to_string(),
+            ]
+        );
+        let calls = runner.calls();
+        assert_eq!(calls[1].dir.as_deref(), Some(root.as_path()));
+        assert_eq!(calls[3].dir.as_deref(), Some(wt.as_path()));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_ensure_reuses_existing_branch() {
+        let root = temp_root("existing-branch");
+        let runner = FakeRunner::new()
+            .with_output("git branch --list fix/4-typo", "  fix/4-typo")
+            .with_failure("mise", "mise: command not found");
+        let forge = FakeForge::new();
+        let repo = Repo::for_test(root.clone(), &runner, &forge);
+
+        ensure(&repo, "fix", 4, "typo", None).unwrap();
+
+        let wt = root.join(".worktrees/4-typo");
+        assert_eq!(runner.commands()[1], format!("git worktree add {} fix/4-typo", wt.display()));
+        assert_eq!(runner.commands().last().unwrap(), "bun install");
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_ensure_fails_when_bun_install_fails() {
+        let root = temp_root("bun-fails");
+        let runner = FakeRunner::new().with_failure("bun install", "lockfile is broken");
+        let forge = FakeForge::new();
+        let repo = Repo::for_test(root.clone(), &runner, &forge);
+
+        let err = ensure(&repo, "feat", 5, "x", None).unwrap_err();
+        assert!(format!("{err:#}").contains("bun install"));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_ensure_existing_worktree_runs_nothing() {
+        let root = temp_root("existing-worktree");
+        fs::create_dir_all(root.join(".worktrees/6-done")).unwrap();
+        let (runner, forge) = (FakeRunner::new(), FakeForge::new());
+        let repo = Repo::for_test(root.clone(), &runner, &forge);
+
+        ensure(&repo, "feat", 6, "done", None).unwrap();
+        assert!(runner.calls().is_empty());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_remove_deletes_worktree_and_branches() {
+        let root = temp_root("remove");
+        fs::create_dir_all(root.join(".worktrees/7-old")).unwrap();
+        let runner =
+            FakeRunner::new().with_output("git branch --list --format", "feat/7-old\nfix/7-old");
+        let forge = FakeForge::new();
+        let repo = Repo::for_test(root.clone(), &runner, &forge);
+
+        remove(&repo, 7).unwrap();
+
+        let wt = root.join(".worktrees/7-old");
+        assert_eq!(
+            runner.commands(),
+            vec![
+                format!("git worktree remove {} --force", wt.display()),
+                "git branch --list --format=%(refname:short) */7-*".to_string(),
+                "git branch -d feat/7-old".to_string(),
+                "git branch -d fix/7-old".to_string(),
+            ]
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
 }
@@ -0,0 +1,145 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::error::{Error, ErrorKind};
+
+/// Location of the per-repository configuration, relative to the repo root.
+pub const CONFIG_PATH: &str = ".aidd/config.toml";
+
+/// Per-repository settings read from `.aidd/config.toml`.
+///
+/// Every section is optional; a missing file means all defaults.
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub forge: ForgeConfig,
+    /// `[commands."<prefix>"]` sections, keyed by command line prefix
+    /// (e.g. `"git push"`, `"supabase"`).
+    pub commands: BTreeMap<String, CommandConfig>,
+    pub display: DisplayConfig,
+}
+
+/// `[display]` section: how output for people is formatted.
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayConfig {
+    /// IANA time zone for times shown by `status`, e.g. `Asia/Tokyo`
+    /// (default: the system's). Generated files always use UTC.
+    pub timezone: Option<String>,
+}
+
+/// `[forge]` section: which code hosting backend to talk to.
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ForgeConfig {
+    pub backend: ForgeBackend,
+    /// REST API base URL for `github-api`, e.g. `https://github.example.com/api/v3`.
+    pub api_url: Option<String>,
+}
+
+/// Timeout and retry overrides for commands matching a prefix.
+///
+/// Unset fields keep the default for the command (see `runner::policy`).
+#[derive(Debug, Default, Clone, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommandConfig {
+    /// Seconds before the command is killed; `0` disables the timeout.
+    pub timeout: Option<u64>,
+    /// How many times a failed or timed-out command is retried.
+    pub retries: Option<u32>,
+    /// Seconds to wait before the first retry; doubles on each further retry.
+    pub backoff: Option<u64>,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ForgeBackend {
+    /// GitHub through the `gh` CLI.
+    #[default]
+    Github,
+    /// GitHub through its REST and GraphQL APIs, authenticated with `GITHUB_TOKEN`.
+    GithubApi,
+    /// GitLab through the `glab` CLI.
+    Gitlab,
+}
+
+impl Config {
+    /// Load the configuration of the repository at `root`.
+    pub fn load(root: &Path) -> Result<Self> {
+        Self::load_from(&root.join(CONFIG_PATH))
+    }
+
+    /// Load the configuration from a file, falling back to defaults if it does not exist.
+    pub fn load_from(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&content).with_context(|| {
+            Error::new(ErrorKind::Validation, format!("Invalid {}", path.display()))
+        })
+    }
+
+    fn parse(content: &str) -> Result<Self> {
+        Ok(toml::from_str(content)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_config_uses_defaults() {
+        let config = Config::parse("").unwrap();
+        assert_eq!(config.forge.backend, ForgeBackend::Github);
+    }
+
+    #[test]
+    fn test_forge_backend() {
+        let config = Config::parse("[forge]\nbackend = \"gitlab\"\n").unwrap();
+        assert_eq!(config.forge.backend, ForgeBackend::Gitlab);
+    }
+
+    #[test]
+    fn test_github_api_backend() {
+        let content = "[forge]\nbackend = \"github-api\"\napi_url = \"http://127.0.0.1:8080\"\n";
+        let config = Config::parse(content).unwrap();
+        assert_eq!(config.forge.backend, ForgeBackend::GithubApi);
+        assert_eq!(config.forge.api_url.as_deref(), Some("http://127.0.0.1:8080"));
+    }
+
+    #[test]
+    fn test_display_timezone() {
+        let config = Config::parse("[display]\ntimezone = \"Asia/Tokyo\"\n").unwrap();
+        assert_eq!(config.display.timezone.as_deref(), Some("Asia/Tokyo"));
+        assert_eq!(Config::parse("").unwrap().display.timezone, None);
+    }
+
+    #[test]
+    fn test_unknown_backend_fails() {
+        assert!(Config::parse("[forge]\nbackend = \"svn\"\n").is_err());
+        assert!(Config::parse("[forge]\nbackedn = \"gitlab\"\n").is_err());
+    }
+
+    #[test]
+    fn test_command_overrides() {
+        let content = "[commands.supabase]\ntimeout = 900\n\n\
+            [commands.\"git push\"]\nretries = 5\nbackoff = 1\n";
+        let config = Config::parse(content).unwrap();
+        assert_eq!(config.commands["supabase"].timeout, Some(900));
+        assert_eq!(config.commands["git push"].retries, Some(5));
+        assert!(Config::parse("[commands.\"git push\"]\nretry = 5\n").is_err());
+    }
+
+    #[test]
+    fn test_missing_file_uses_defaults() {
+        let config = Config::load_from(Path::new("/nonexistent/.aidd/config.toml")).unwrap();
+        assert_eq!(config, Config::default());
+    }
+}
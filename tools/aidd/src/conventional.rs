@@ -0,0 +1,132 @@
+use anyhow::{bail, Result};
+
+/// Commit types allowed by `.agent/rules/commit.md`.
+pub const COMMIT_TYPES: &[&str] = &["feat", "fix", "refactor", "test", "docs", "chore"];
+
+/// Words that mark a commit as work in progress (forbidden by the rules).
+const WIP_MARKERS: &[&str] = &["wip", "tmp", "temp", "todo", "fixup", "squash", "work in progress"];
+
+/// A commit subject split into its Conventional Commits parts.
+#[derive(Debug, PartialEq)]
+pub struct ConventionalCommit<'a> {
+    pub kind: &'a str,
+    pub scope: Option<&'a str>,
+    pub description: &'a str,
+}
+
+/// Parse a `<type>(<scope>): <description>` commit subject.
+pub fn parse(subject: &str) -> Result<ConventionalCommit<'_>> {
+    let Some((head, description)) = subject.split_once(": ") else {
+        bail!("expected '<type>(<scope>): <description>'");
+    };
+
+    let (kind, scope) = match head.split_once('(') {
+        Some((kind, rest)) => match rest.strip_suffix(')') {
+            Some(scope) if !scope.is_empty() => (kind, Some(scope)),
+            _ => bail!("malformed scope in '{head}'"),
+        },
+        None => (head, None),
+    };
+
+    if !COMMIT_TYPES.contains(&kind) {
+        bail!("unknown type '{kind}' (expected one of: {})", COMMIT_TYPES.join(", "));
+    }
+    if description.trim().is_empty() {
+        bail!("description is empty");
+    }
+
+    Ok(ConventionalCommit { kind, scope, description })
+}
+
+/// Parse a commit subject and check it against `.agent/rules/commit.md`.
+///
+/// On top of [`parse`], requires a lowercase kebab-case scope, an English
+/// (ASCII) description and rejects WIP-style messages.
+pub fn validate(subject: &str) -> Result<ConventionalCommit<'_>> {
+    let lower = subject.to_lowercase();
+    if lower.starts_with("fixup!") || lower.starts_with("squash!") || lower.starts_with("wip") {
+        bail!("WIP-style commits are not allowed");
+    }
+
+    let commit = parse(subject)?;
+
+    if let Some(scope) = commit.scope {
+        let valid = scope
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        if !valid {
+            bail!("scope '{scope}' must be lowercase kebab-case (module or app name)");
+        }
+    }
+
+    if !commit.description.is_ascii() {
+        bail!("description must be written in English");
+    }
+
+    // Match markers on word boundaries so "template" does not count as "temp".
+    let words: Vec<&str> = commit
+        .description
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+    let padded = format!(" {} ", words.join(" ").to_lowercase());
+    if let Some(marker) = WIP_MARKERS.iter().find(|m| padded.contains(&format!(" {m} "))) {
+        bail!("WIP-style commits are not allowed ('{marker}')");
+    }
+
+    Ok(commit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_with_scope() {
+        let c = parse("feat(auth): add login endpoint").unwrap();
+        assert_eq!(c.kind, "feat");
+        assert_eq!(c.scope, Some("auth"));
+        assert_eq!(c.description, "add login endpoint");
+    }
+
+    #[test]
+    fn test_parse_without_scope() {
+        let c = parse("docs: update readme").unwrap();
+        assert_eq!(c.kind, "docs");
+        assert_eq!(c.scope, None);
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid() {
+        assert!(parse("add login endpoint").is_err());
+        assert!(parse("feature(auth): add login").is_err());
+        assert!(parse("feat(): add login").is_err());
+        assert!(parse("feat(auth: add login").is_err());
+        assert!(parse("feat:  ").is_err());
+    }
+
+    #[test]
+    fn test_validate_accepts_rule_examples() {
+        assert!(validate("feat(auth): add login endpoint").is_ok());
+        assert!(validate("fix(web): resolve routing error").is_ok());
+        assert!(validate("chore(library-write): bump deps").is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_wip() {
+        assert!(validate("WIP").is_err());
+        assert!(validate("wip: login").is_err());
+        assert!(validate("fixup! feat(auth): add login").is_err());
+        assert!(validate("feat(auth): wip login").is_err());
+        assert!(validate("feat(auth): temp commit").is_err());
+        assert!(validate("feat(auth): work in progress on login").is_err());
+        assert!(validate("feat(auth): add template loader").is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_scope_and_language() {
+        assert!(validate("feat(Auth): add login").is_err());
+        assert!(validate("feat(auth_api): add login").is_err());
+        assert!(validate("feat(auth): ログインを追加").is_err());
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use clap::ValueEnum;
+
+use crate::error::{Error, ErrorKind};
+use crate::tasks::TaskEntry;
+
+/// Output format of `plan graph`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GraphFormat {
+    Mermaid,
+    Dot,
+}
+
+/// Whether a task no longer holds up the tasks that depend on it.
+fn finished(status: &str) -> bool {
+    matches!(status, "done" | "dropped")
+}
+
+/// Dependencies of `task` that are not finished yet, in `dependsOn` order.
+///
+/// A dependency on a task that does not exist counts as unfinished.
+pub fn blockers(task: &TaskEntry, tasks: &[TaskEntry]) -> Vec<u32> {
+    task.frontmatter
+        .depends_on
+        .iter()
+        .copied()
+        .filter(|dep| {
+            tasks
+                .iter()
+                .find(|t| t.number == *dep)
+                .is_none_or(|t| !finished(&t.frontmatter.status))
+        })
+        .collect()
+}
+
+/// The state shown for a task: `blocked` while it is not finished and a
+/// dependency is not finished, otherwise its status.
+pub fn state<'a>(task: &'a TaskEntry, tasks: &[TaskEntry]) -> &'a str {
+    if !finished(&task.frontmatter.status) && !blockers(task, tasks).is_empty() {
+        "blocked"
+    } else {
+        &task.frontmatter.status
+    }
+}
+
+/// Check that every dependency names another task of the issue and that
+/// the dependencies form no cycle.
+pub fn check(issue: u32, tasks: &[TaskEntry]) -> Result<()> {
+    for task in tasks {
+        if let Some(dep) =
+            task.frontmatter.depends_on.iter().find(|dep| !tasks.iter().any(|t| t.number == **dep))
+        {
+            anyhow::bail!(Error::new(
+                ErrorKind::Validation,
+                format!("Task {} depends on task {dep}, which does not exist", task.number)
+            )
+            .with_hint(format!("Fix dependsOn in features/{issue}/{}/TASK.md", task.number)));
+        }
+    }
+    if let Some(cycle) = find_cycle(tasks) {
+        let path: Vec<String> = cycle.iter().map(u32::to_string).collect();
+        anyhow::bail!(Error::new(
+            ErrorKind::Validation,
+            format!("Task dependencies form a cycle: {}", path.join(" → "))
+        )
+        .with_hint("Remove one of the dependsOn entries in the cycle"));
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mark {
+    Visiting,
+    Visited,
+}
+
+/// A dependency cycle as the task numbers along it, the first repeated at
+/// the end: `[2, 3, 2]` when 2 depends on 3 and 3 on 2.
+fn find_cycle(tasks: &[TaskEntry]) -> Option<Vec<u32>> {
+    let deps: BTreeMap<u32, &[u32]> =
+        tasks.iter().map(|t| (t.number, t.frontmatter.depends_on.as_slice())).collect();
+    let mut marks = BTreeMap::new();
+    let mut path = Vec::new();
+    deps.keys().find_map(|number| visit(*number, &deps, &mut marks, &mut path))
+}
+
+fn visit(
+    number: u32,
+    deps: &BTreeMap<u32, &[u32]>,
+    marks: &mut BTreeMap<u32, Mark>,
+    path: &mut Vec<u32>,
+) -> Option<Vec<u32>> {
+    match marks.get(&number) {
+        Some(Mark::Visited) => return None,
+        Some(Mark::Visiting) => {
+            let start = path.iter().position(|n| *n == number)?;
+            let mut cycle = path[start..].to_vec();
+            cycle.push(number);
+            return Some(cycle);
+        }
+        None => {}
+    }
+    marks.insert(number, Mark::Visiting);
+    path.push(number);
+    for dep in deps.get(&number).copied().unwrap_or_default() {
+        if let Some(cycle) = visit(*dep, deps, marks, path) {
+            return Some(cycle);
+        }
+    }
+    path.pop();
+    marks.insert(number, Mark::Visited);
+    None
+}
+
+/// The dependency graph of an issue's tasks, with an edge from each
+/// dependency to the task waiting for it.
+///
+/// Nodes are labelled `1. title [state]`; `titles` gives the title of each
+/// task number, and tasks without one show the number alone.
+pub fn render(
+    format: GraphFormat,
+    issue: u32,
+    tasks: &[TaskEntry],
+    titles: &BTreeMap<u32, String>,
+) -> String {
+    let label = |task: &TaskEntry| match titles.get(&task.number) {
+        Some(title) => format!("{}. {title} [{}]", task.number, state(task, tasks)),
+        None => format!("{} [{}]", task.number, state(task, tasks)),
+    };
+    let edges = tasks.iter().flat_map(|task| {
+        task.frontmatter.depends_on.iter().map(move |dep| (*dep, task.number))
+    });
+
+    let mut out = String::new();
+    match format {
+        GraphFormat::Mermaid => {
+            out.push_str("flowchart TD\n");
+            for task in tasks {
+                let label = label(task).replace('"', "#quot;");
+                out.push_str(&format!("    t{}[\"{label}\"]\n", task.number));
+            }
+            for (from, to) in edges {
+                out.push_str(&format!("    t{from} --> t{to}\n"));
+            }
+        }
+        GraphFormat::Dot => {
+            out.push_str(&format!("digraph \"issue-{issue}\" {{\n"));
+            for task in tasks {
+                let label = label(task).replace('\\', "\\\\").replace('"', "\\\"");
+                out.push_str(&format!("    t{} [label=\"{label}\"];\n", task.number));
+            }
+            for (from, to) in edges {
+                out.push_str(&format!("    t{from} -> t{to};\n"));
+            }
+            out.push_str("}\n");
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error;
+    use crate::frontmatter::TaskFrontmatter;
+
+    fn task(number: u32, status: &str, depends_on: &[u32]) -> TaskEntry {
+        let frontmatter = TaskFrontmatter {
+            depends_on: depends_on.to_vec(),
+            ..TaskFrontmatter::test(number, status)
+        };
+        TaskEntry { number, frontmatter }
+    }
+
+    #[test]
+    fn test_blocked_until_dependencies_finish() {
+        let tasks = [
+            task(1, "done", &[]),
+            task(2, "doing", &[1]),
+            task(3, "todo", &[2]),
+            task(4, "todo", &[1, 5]),
+            task(5, "dropped", &[]),
+        ];
+        assert_eq!(blockers(&tasks[2], &tasks), vec![2]);
+        let states: Vec<&str> = tasks.iter().map(|t| state(t, &tasks)).collect();
+        assert_eq!(states, ["done", "doing", "blocked", "todo", "dropped"]);
+        check(11, &tasks).unwrap();
+    }
+
+    #[test]
+    fn test_check_rejects_cycles_and_unknown_tasks() {
+        let tasks = [task(1, "todo", &[]), task(2, "todo", &[3]), task(3, "todo", &[1, 2])];
+        let err = check(11, &tasks).unwrap_err();
+        assert_eq!(error::kind(&err), ErrorKind::Validation);
+        assert_eq!(err.to_string(), "Task dependencies form a cycle: 2 → 3 → 2");
+
+        let err = check(11, &[task(1, "todo", &[1])]).unwrap_err();
+        assert_eq!(err.to_string(), "Task dependencies form a cycle: 1 → 1");
+
+        let err = check(11, &[task(1, "todo", &[9])]).unwrap_err();
+        assert_eq!(err.to_string(), "Task 1 depends on task 9, which does not exist");
+    }
+
+    #[test]
+    fn test_render() {
+        let tasks = [task(1, "done", &[]), task(2, "todo", &[1]), task(3, "todo", &[2])];
+        let titles = BTreeMap::from([
+            (1, "Migrate \"books\"".to_string()),
+            (2, "Deploy".to_string()),
+        ]);
+        assert_eq!(
+            render(GraphFormat::Mermaid, 11, &tasks, &titles),
+            "flowchart TD\n    t1[\"1. Migrate #quot;books#quot; [done]\"]\n    \
+             t2[\"2. Deploy [todo]\"]\n    t3[\"3 [blocked]\"]\n    t1 --> t2\n    t2 --> t3\n"
+        );
+        assert_eq!(
+            render(GraphFormat::Dot, 11, &tasks, &titles),
+            "digraph \"issue-11\" {\n    t1 [label=\"1. Migrate \\\"books\\\" [done]\"];\n    \
+             t2 [label=\"2. Deploy [todo]\"];\n    t3 [label=\"3 [blocked]\"];\n    \
+             t1 -> t2;\n    t2 -> t3;\n}\n"
+        );
+    }
+}
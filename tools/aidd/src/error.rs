@@ -0,0 +1,246 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::interrupt::{self, Interrupted};
+
+/// Category of a failure, each with a stable exit code so scripts and agents
+/// can react without parsing messages.
+///
+/// | Kind | Exit code |
+/// |------|-----------|
+/// | `other` | 1 |
+/// | `missing_tool` | 3 |
+/// | `auth` | 4 |
+/// | `git_conflict` | 5 |
+/// | `validation` | 6 |
+/// | `not_found` | 7 |
+/// | `command` | 8 |
+/// | `aborted` | 130 |
+///
+/// Exit code 2 is left to clap for usage errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// Anything not classified below.
+    Other,
+    /// A required program (git, gh, bun, supabase, ...) is not installed.
+    MissingTool,
+    /// The forge rejected the credentials or none are configured.
+    Auth,
+    /// Git stopped on conflicting changes: a failed rebase or a rejected push.
+    GitConflict,
+    /// Input or repository state failed a check: commit message, review
+    /// checks, CI checks, configuration, TASK.md frontmatter.
+    Validation,
+    /// An issue, task, PR, branch or worktree does not exist.
+    NotFound,
+    /// An external command or forge API request failed or timed out.
+    Command,
+    /// Stopped by SIGINT or SIGTERM.
+    Aborted,
+}
+
+impl ErrorKind {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorKind::Other => 1,
+            ErrorKind::MissingTool => 3,
+            ErrorKind::Auth => 4,
+            ErrorKind::GitConflict => 5,
+            ErrorKind::Validation => 6,
+            ErrorKind::NotFound => 7,
+            ErrorKind::Command => 8,
+            ErrorKind::Aborted => interrupt::EXIT_CODE,
+        }
+    }
+
+    /// What to try next when the error carries no more specific hint.
+    fn default_hint(self) -> Option<&'static str> {
+        match self {
+            ErrorKind::Other => None,
+            ErrorKind::MissingTool => Some("Install the missing tool and make sure it is on PATH"),
+            ErrorKind::Auth => Some(
+                "Log in with `gh auth login` / `glab auth login`, \
+                 or set GITHUB_TOKEN for the github-api backend",
+            ),
+            ErrorKind::GitConflict => {
+                Some("Resolve the conflict in the worktree, then rerun the command")
+            }
+            ErrorKind::Validation => Some("Fix the reported problems and rerun the command"),
+            ErrorKind::NotFound => {
+                Some("Check the issue and task numbers; `aidd status` lists known issues")
+            }
+            ErrorKind::Command => {
+                Some("Rerun with -v for the command's output, or read the log in .aidd/logs/")
+            }
+            ErrorKind::Aborted => Some("Rerun the command; half-finished steps were cleaned up"),
+        }
+    }
+}
+
+/// An error of a known [`ErrorKind`].
+///
+/// Return it directly or attach it with `.context(...)`; the outermost one
+/// in an error chain decides the exit code.
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    message: String,
+    hint: Option<String>,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self { kind, message: message.into(), hint: None }
+    }
+
+    /// Replace the kind's default remediation hint.
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Output fragments of failed commands, checked in order.
+const OUTPUT_MARKERS: &[(ErrorKind, &[&str])] = &[
+    (
+        ErrorKind::Auth,
+        &[
+            "gh auth login",
+            "glab auth login",
+            "not logged in",
+            "authentication failed",
+            "authentication required",
+            "bad credentials",
+            "http 401",
+            "could not read username",
+            "permission denied (publickey)",
+        ],
+    ),
+    (
+        ErrorKind::GitConflict,
+        &[
+            "conflict",
+            "non-fast-forward",
+            "[rejected]",
+            "stale info",
+            "would be overwritten",
+        ],
+    ),
+    (ErrorKind::NotFound, &["could not resolve to", "http 404", "404 not found"]),
+];
+
+/// The kind of a failed external command, judged by its output.
+pub fn classify_output(output: &str) -> ErrorKind {
+    let output = output.to_lowercase();
+    OUTPUT_MARKERS
+        .iter()
+        .find(|(_, markers)| markers.iter().any(|marker| output.contains(marker)))
+        .map_or(ErrorKind::Command, |(kind, _)| *kind)
+}
+
+/// A failed run as reported on stderr.
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub kind: ErrorKind,
+    pub exit_code: i32,
+    pub message: String,
+    pub hint: Option<String>,
+}
+
+/// The kind of an error chain, decided by its outermost typed error.
+pub fn kind(err: &anyhow::Error) -> ErrorKind {
+    if let Some(error) = err.downcast_ref::<Error>() {
+        error.kind
+    } else if err.downcast_ref::<Interrupted>().is_some() {
+        ErrorKind::Aborted
+    } else {
+        ErrorKind::Other
+    }
+}
+
+impl Report {
+    pub fn new(err: &anyhow::Error) -> Self {
+        let kind = kind(err);
+        let hint = err.downcast_ref::<Error>().and_then(|error| error.hint.clone());
+        Self {
+            kind,
+            exit_code: kind.exit_code(),
+            message: format!("{err:#}"),
+            hint: hint.or_else(|| kind.default_hint().map(str::to_string)),
+        }
+    }
+
+    /// `{"error": {...}}` on a single line, fields in declaration order.
+    pub fn to_json(&self) -> String {
+        #[derive(Serialize)]
+        struct Envelope<'a> {
+            error: &'a Report,
+        }
+        serde_json::to_string(&Envelope { error: self }).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::{anyhow, Context};
+
+    use super::*;
+
+    #[test]
+    fn test_outermost_kind_wins() {
+        let err = anyhow::Error::new(Error::new(ErrorKind::Command, "git rebase failed"))
+            .context(
+                Error::new(ErrorKind::GitConflict, "Rebase failed").with_hint("Rebase by hand"),
+            )
+            .context("Restack failed");
+
+        let report = Report::new(&err);
+        assert_eq!(report.kind, ErrorKind::GitConflict);
+        assert_eq!(report.exit_code, 5);
+        assert_eq!(report.message, "Restack failed: Rebase failed: git rebase failed");
+        assert_eq!(report.hint.as_deref(), Some("Rebase by hand"));
+    }
+
+    #[test]
+    fn test_untyped_and_interrupted_errors() {
+        let report = Report::new(&anyhow!("boom"));
+        assert_eq!((report.kind, report.exit_code, report.hint), (ErrorKind::Other, 1, None));
+
+        let err = Err::<(), _>(Interrupted).context("Failed to run bun install").unwrap_err();
+        assert_eq!(Report::new(&err).exit_code, 130);
+    }
+
+    #[test]
+    fn test_classify_output() {
+        let auth = "To get started with GitHub CLI, please run:  gh auth login";
+        assert_eq!(classify_output(auth), ErrorKind::Auth);
+        let conflict = "CONFLICT (content): Merge conflict in src/lib.rs";
+        assert_eq!(classify_output(conflict), ErrorKind::GitConflict);
+        assert_eq!(
+            classify_output("GraphQL: Could not resolve to an Issue with the number of 9."),
+            ErrorKind::NotFound
+        );
+        let lint = "error: script \"lint\" exited with code 1";
+        assert_eq!(classify_output(lint), ErrorKind::Command);
+    }
+
+    #[test]
+    fn test_json_report() {
+        let err = anyhow::Error::new(Error::new(ErrorKind::NotFound, "No PR found for branch x"));
+        let json: serde_json::Value = serde_json::from_str(&Report::new(&err).to_json()).unwrap();
+        assert_eq!(json["error"]["kind"], "not_found");
+        assert_eq!(json["error"]["exit_code"], 7);
+        assert_eq!(json["error"]["message"], "No PR found for branch x");
+        assert!(json["error"]["hint"].as_str().unwrap().contains("aidd status"));
+    }
+}
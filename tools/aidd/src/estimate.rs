@@ -0,0 +1,103 @@
+/// T-shirt sizes a task can be estimated with, smallest first.
+pub const SIZES: [&str; 5] = ["XS", "S", "M", "L", "XL"];
+
+/// Estimate of a task whose size is not known yet.
+pub const UNKNOWN: &str = "?";
+
+/// Prefix of the line after the Task Breakdown table that sums the estimates.
+pub const TOTAL_PREFIX: &str = "見積合計:";
+
+/// The canonical spelling of a size, ignoring case.
+pub fn parse_size(text: &str) -> Option<&'static str> {
+    SIZES.iter().copied().find(|size| size.eq_ignore_ascii_case(text.trim()))
+}
+
+/// Story points a size counts for in the PLAN total.
+pub fn points(size: &str) -> Option<u32> {
+    match parse_size(size)? {
+        "XS" => Some(1),
+        "S" => Some(2),
+        "M" => Some(3),
+        "L" => Some(5),
+        _ => Some(8),
+    }
+}
+
+/// Split a size marker off a task title: `(S)`, `[L]` or `size:M`.
+///
+/// The bracketed forms must be upper case so `(a)` or `[x]` in prose are
+/// not taken for sizes.
+pub fn split_marker(title: &str) -> (String, Option<&'static str>) {
+    let words: Vec<&str> = title.split_whitespace().collect();
+    for (i, word) in words.iter().enumerate() {
+        let bracketed = word
+            .strip_prefix('(')
+            .and_then(|w| w.strip_suffix(')'))
+            .or_else(|| word.strip_prefix('[').and_then(|w| w.strip_suffix(']')));
+        let size = match bracketed {
+            Some(inner) => SIZES.iter().copied().find(|size| *size == inner),
+            None => word
+                .get(..5)
+                .filter(|prefix| prefix.eq_ignore_ascii_case("size:"))
+                .and_then(|_| parse_size(&word[5..])),
+        };
+        if let Some(size) = size {
+            let rest: Vec<&str> = words[..i].iter().chain(&words[i + 1..]).copied().collect();
+            return (rest.join(" "), Some(size));
+        }
+    }
+    (title.to_string(), None)
+}
+
+/// The size from labels such as `size:M`, `size/L` or `size: XS`.
+pub fn from_labels(labels: &[String]) -> Option<&'static str> {
+    labels.iter().find_map(|label| {
+        let (key, value) = label.split_once([':', '/'])?;
+        key.trim().eq_ignore_ascii_case("size").then(|| parse_size(value)).flatten()
+    })
+}
+
+/// The total line for the estimates of a plan's tasks.
+pub fn total_line<'a>(estimates: impl IntoIterator<Item = &'a str>) -> String {
+    let (mut total, mut unknown) = (0, 0);
+    for estimate in estimates {
+        match points(estimate) {
+            Some(points) => total += points,
+            None => unknown += 1,
+        }
+    }
+    if unknown == 0 {
+        format!("{TOTAL_PREFIX} {total} pt")
+    } else {
+        format!("{TOTAL_PREFIX} {total} pt（未見積 {unknown} 件）")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_marker() {
+        assert_eq!(split_marker("Index books (M)"), ("Index books".to_string(), Some("M")));
+        assert_eq!(split_marker("[L] Add search page"), ("Add search page".to_string(), Some("L")));
+        assert_eq!(split_marker("Add UI size:xs"), ("Add UI".to_string(), Some("XS")));
+        assert_eq!(split_marker("Handle (a) and [x]"), ("Handle (a) and [x]".to_string(), None));
+        assert_eq!(split_marker("Set size:huge"), ("Set size:huge".to_string(), None));
+    }
+
+    #[test]
+    fn test_from_labels() {
+        let labels = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        assert_eq!(from_labels(&labels(&["bug", "size:M"])), Some("M"));
+        assert_eq!(from_labels(&labels(&["Size/XL"])), Some("XL"));
+        assert_eq!(from_labels(&labels(&["size: s"])), Some("S"));
+        assert_eq!(from_labels(&labels(&["priority:high", "size:huge"])), None);
+    }
+
+    #[test]
+    fn test_total_line() {
+        assert_eq!(total_line(["S", "M", "L"]), "見積合計: 10 pt");
+        assert_eq!(total_line(["XS", "?", "?"]), "見積合計: 1 pt（未見積 2 件）");
+    }
+}
@@ -0,0 +1,149 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use anyhow::Result;
+
+use super::{
+    Check, Comment, Forge, Issue, MergeMethod, NewIssue, NewPullRequest, PrState, PrUpdate,
+    PullRequest,
+};
+use crate::runner::dry_run::{Plan, Step};
+
+/// Forge for `--dry-run`: queries go to `inner`, mutations are recorded in
+/// the [`Plan`].
+///
+/// Pull requests merged during the run are reported as merged afterwards,
+/// so `pr merge` does not wait for a merge that never happens. An issue
+/// created during the run can be fetched as #0, so it can be planned.
+pub struct DryRunForge {
+    inner: Box<dyn Forge>,
+    plan: Rc<Plan>,
+    merged: RefCell<Vec<u32>>,
+    created: RefCell<Option<Issue>>,
+}
+
+impl DryRunForge {
+    pub fn new(inner: Box<dyn Forge>, plan: Rc<Plan>) -> Self {
+        Self { inner, plan, merged: RefCell::new(Vec::new()), created: RefCell::new(None) }
+    }
+
+    fn record(&self, description: String) {
+        self.plan.record(Step::Forge(description));
+    }
+}
+
+impl Forge for DryRunForge {
+    fn fetch_issue(&self, number: u32) -> Result<Issue> {
+        match self.created.borrow().as_ref() {
+            Some(issue) if issue.number == number => Ok(issue.clone()),
+            _ => self.inner.fetch_issue(number),
+        }
+    }
+
+    fn create_issue(&self, issue: &NewIssue) -> Result<Issue> {
+        self.record(format!("create issue: {}", issue.title));
+        let created = issue.created(0);
+        *self.created.borrow_mut() = Some(created.clone());
+        Ok(created)
+    }
+
+    fn sub_issues(&self, number: u32) -> Result<Vec<Issue>> {
+        if number == 0 {
+            return Ok(Vec::new());
+        }
+        self.inner.sub_issues(number)
+    }
+
+    fn find_pr(&self, head: &str) -> Result<Option<PullRequest>> {
+        Ok(self.inner.find_pr(head)?.map(|mut pr| {
+            if self.merged.borrow().contains(&pr.number) {
+                pr.state = PrState::Merged;
+            }
+            pr
+        }))
+    }
+
+    fn list_prs(&self, base: Option<&str>) -> Result<Vec<PullRequest>> {
+        self.inner.list_prs(base)
+    }
+
+    fn create_pr(&self, pr: &NewPullRequest) -> Result<PullRequest> {
+        self.record(format!("create PR {} -> {}: {}", pr.head, pr.base, pr.title));
+        Ok(PullRequest {
+            number: 0,
+            title: pr.title.to_string(),
+            head: pr.head.to_string(),
+            base: pr.base.to_string(),
+            state: PrState::Open,
+            url: "(dry run)".to_string(),
+        })
+    }
+
+    fn update_pr(&self, number: u32, update: &PrUpdate) -> Result<()> {
+        let mut changes = Vec::new();
+        if let Some(title) = update.title {
+            changes.push(format!("title = {title}"));
+        }
+        if update.body.is_some() {
+            changes.push("body".to_string());
+        }
+        if let Some(base) = update.base {
+            changes.push(format!("base = {base}"));
+        }
+        self.record(format!("update PR #{number}: {}", changes.join(", ")));
+        Ok(())
+    }
+
+    fn merge_pr(&self, number: u32, method: MergeMethod) -> Result<()> {
+        self.record(format!("merge PR #{number} ({})", format!("{method:?}").to_lowercase()));
+        self.merged.borrow_mut().push(number);
+        Ok(())
+    }
+
+    fn checks(&self, number: u32) -> Result<Vec<Check>> {
+        self.inner.checks(number)
+    }
+
+    fn comment(&self, number: u32, _body: &str) -> Result<()> {
+        self.record(format!("comment on PR #{number}"));
+        Ok(())
+    }
+
+    fn comments(&self, number: u32) -> Result<Vec<Comment>> {
+        self.inner.comments(number)
+    }
+
+    fn is_offline(&self) -> bool {
+        self.inner.is_offline()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forge::fake::FakeForge;
+
+    #[test]
+    fn test_mutations_are_recorded_not_applied() {
+        let plan = Rc::new(Plan::default());
+        let forge = DryRunForge::new(
+            Box::new(FakeForge::new().with_pr("feat/1-child", "feat/1-parent")),
+            Rc::clone(&plan),
+        );
+
+        forge.update_pr(1, &PrUpdate { base: Some("main"), ..Default::default() }).unwrap();
+        let draft = NewIssue { title: "Add search", body: "", labels: &[], assignees: &[] };
+        let created = forge.create_issue(&draft).unwrap();
+        assert_eq!(forge.fetch_issue(created.number).unwrap().title, "Add search");
+        forge.merge_pr(1, MergeMethod::Squash).unwrap();
+
+        let pr = forge.find_pr("feat/1-child").unwrap().unwrap();
+        assert_eq!(pr.base, "feat/1-parent");
+        assert_eq!(pr.state, PrState::Merged);
+        assert_eq!(plan.steps(), vec![
+            Step::Forge("update PR #1: base = main".to_string()),
+            Step::Forge("create issue: Add search".to_string()),
+            Step::Forge("merge PR #1 (squash)".to_string()),
+        ]);
+    }
+}
@@ -0,0 +1,185 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+
+use super::{
+    Check, Comment, Forge, Issue, MergeMethod, NewIssue, NewPullRequest, PrState, PrUpdate,
+    PullRequest,
+};
+use crate::error::{Error, ErrorKind};
+
+/// In-memory forge for tests.
+///
+/// Issues, pull requests and checks are seeded up front; mutations are
+/// applied to the in-memory state so tests can assert on it afterwards.
+#[derive(Default)]
+pub struct FakeForge {
+    issues: RefCell<BTreeMap<u32, Issue>>,
+    /// Sub-issue numbers by parent issue.
+    sub_issues: BTreeMap<u32, Vec<u32>>,
+    state: RefCell<FakeState>,
+}
+
+#[derive(Default)]
+struct FakeState {
+    prs: Vec<PullRequest>,
+    checks: BTreeMap<u32, Vec<Check>>,
+    comments: BTreeMap<u32, Vec<Comment>>,
+    merges: Vec<(u32, MergeMethod)>,
+}
+
+impl FakeForge {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_issue(mut self, number: u32, title: &str, body: &str) -> Self {
+        let issue = Issue {
+            number,
+            title: title.to_string(),
+            body: body.to_string(),
+            labels: Vec::new(),
+            closed: false,
+        };
+        self.issues.get_mut().insert(number, issue);
+        self
+    }
+
+    /// Seed a closed issue.
+    pub fn with_closed_issue(mut self, number: u32, title: &str) -> Self {
+        self = self.with_issue(number, title, "");
+        if let Some(issue) = self.issues.get_mut().get_mut(&number) {
+            issue.closed = true;
+        }
+        self
+    }
+
+    /// Make `child` a sub-issue of `parent`; both must be seeded.
+    pub fn with_sub_issue(mut self, parent: u32, child: u32) -> Self {
+        self.sub_issues.entry(parent).or_default().push(child);
+        self
+    }
+
+    pub fn with_pr(self, head: &str, base: &str) -> Self {
+        self.insert_pr(head, base, &format!("PR for {head}"));
+        self
+    }
+
+    pub fn with_checks(self, number: u32, checks: Vec<Check>) -> Self {
+        self.state.borrow_mut().checks.insert(number, checks);
+        self
+    }
+
+    /// All pull requests, in creation order.
+    pub fn prs(&self) -> Vec<PullRequest> {
+        self.state.borrow().prs.clone()
+    }
+
+    /// Merges performed so far.
+    pub fn merges(&self) -> Vec<(u32, MergeMethod)> {
+        self.state.borrow().merges.clone()
+    }
+
+    fn insert_pr(&self, head: &str, base: &str, title: &str) -> PullRequest {
+        let mut state = self.state.borrow_mut();
+        let number = state.prs.len() as u32 + 1;
+        let pr = PullRequest {
+            number,
+            title: title.to_string(),
+            head: head.to_string(),
+            base: base.to_string(),
+            state: PrState::Open,
+            url: format!("https://forge.test/pull/{number}"),
+        };
+        state.prs.push(pr.clone());
+        pr
+    }
+
+    fn with_pr_mut<T>(&self, number: u32, f: impl FnOnce(&mut PullRequest) -> T) -> Result<T> {
+        let mut state = self.state.borrow_mut();
+        let pr = state
+            .prs
+            .iter_mut()
+            .find(|pr| pr.number == number)
+            .with_context(|| format!("PR #{number} not found"))?;
+        Ok(f(pr))
+    }
+}
+
+impl Forge for FakeForge {
+    fn fetch_issue(&self, number: u32) -> Result<Issue> {
+        self.issues.borrow().get(&number).cloned().with_context(|| {
+            Error::new(ErrorKind::NotFound, format!("Issue #{number} not found"))
+        })
+    }
+
+    fn create_issue(&self, issue: &NewIssue) -> Result<Issue> {
+        let mut issues = self.issues.borrow_mut();
+        let number = issues.keys().next_back().map_or(1, |last| last + 1);
+        let created = issue.created(number);
+        issues.insert(number, created.clone());
+        Ok(created)
+    }
+
+    fn sub_issues(&self, number: u32) -> Result<Vec<Issue>> {
+        let children = self.sub_issues.get(&number).map(Vec::as_slice).unwrap_or_default();
+        children.iter().map(|child| self.fetch_issue(*child)).collect()
+    }
+
+    fn find_pr(&self, head: &str) -> Result<Option<PullRequest>> {
+        Ok(self.state.borrow().prs.iter().rev().find(|pr| pr.head == head).cloned())
+    }
+
+    fn list_prs(&self, base: Option<&str>) -> Result<Vec<PullRequest>> {
+        Ok(self
+            .state
+            .borrow()
+            .prs
+            .iter()
+            .filter(|pr| pr.state == PrState::Open)
+            .filter(|pr| base.is_none_or(|b| pr.base == b))
+            .cloned()
+            .collect())
+    }
+
+    fn create_pr(&self, pr: &NewPullRequest) -> Result<PullRequest> {
+        if let Some(existing) = self.find_pr(pr.head)? {
+            if existing.state == PrState::Open {
+                anyhow::bail!("PR for {} already exists", pr.head);
+            }
+        }
+        Ok(self.insert_pr(pr.head, pr.base, pr.title))
+    }
+
+    fn update_pr(&self, number: u32, update: &PrUpdate) -> Result<()> {
+        self.with_pr_mut(number, |pr| {
+            if let Some(title) = update.title {
+                pr.title = title.to_string();
+            }
+            if let Some(base) = update.base {
+                pr.base = base.to_string();
+            }
+        })
+    }
+
+    fn merge_pr(&self, number: u32, method: MergeMethod) -> Result<()> {
+        self.with_pr_mut(number, |pr| pr.state = PrState::Merged)?;
+        self.state.borrow_mut().merges.push((number, method));
+        Ok(())
+    }
+
+    fn checks(&self, number: u32) -> Result<Vec<Check>> {
+        Ok(self.state.borrow().checks.get(&number).cloned().unwrap_or_default())
+    }
+
+    fn comment(&self, number: u32, body: &str) -> Result<()> {
+        let comment = Comment { author: "aidd".to_string(), body: body.to_string() };
+        self.state.borrow_mut().comments.entry(number).or_default().push(comment);
+        Ok(())
+    }
+
+    fn comments(&self, number: u32) -> Result<Vec<Comment>> {
+        Ok(self.state.borrow().comments.get(&number).cloned().unwrap_or_default())
+    }
+}
@@ -0,0 +1,342 @@
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use super::{
+    issue_number_from_url, parse_pages, Check, CheckState, Comment, Forge, Issue, MergeMethod,
+    NewIssue, NewPullRequest, PrState, PrUpdate, PullRequest,
+};
+use crate::runner::CommandRunner;
+
+/// Fields requested from `gh pr view/list --json`.
+const PR_FIELDS: &str = "number,title,headRefName,baseRefName,state,url";
+
+/// GitHub backend that shells out to the `gh` CLI.
+pub struct GhForge {
+    root: PathBuf,
+    runner: Rc<dyn CommandRunner>,
+}
+
+impl GhForge {
+    pub fn new(root: PathBuf, runner: Rc<dyn CommandRunner>) -> Self {
+        Self { root, runner }
+    }
+
+    fn gh(&self, args: &[&str]) -> Result<String> {
+        self.runner.run("gh", args, Some(&self.root))
+    }
+}
+
+#[derive(Deserialize)]
+struct GhIssue {
+    number: u32,
+    title: String,
+    body: Option<String>,
+    #[serde(default)]
+    labels: Vec<GhLabel>,
+    #[serde(default)]
+    state: String,
+}
+
+#[derive(Deserialize)]
+struct GhLabel {
+    name: String,
+}
+
+impl From<GhIssue> for Issue {
+    fn from(issue: GhIssue) -> Self {
+        Issue {
+            number: issue.number,
+            title: issue.title,
+            body: issue.body.unwrap_or_default(),
+            labels: issue.labels.into_iter().map(|l| l.name).collect(),
+            // `gh issue view` says CLOSED, the REST API (`gh api`) says closed.
+            closed: issue.state.eq_ignore_ascii_case("closed"),
+        }
+    }
+}
+
+/// An issue from `gh issue view --json number,title,body,labels,state`.
+pub(super) fn parse_issue(json: &str) -> Result<Issue> {
+    let issue: GhIssue = serde_json::from_str(json).context("Failed to parse issue JSON")?;
+    Ok(issue.into())
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GhPullRequest {
+    number: u32,
+    title: String,
+    head_ref_name: String,
+    base_ref_name: String,
+    state: String,
+    url: String,
+}
+
+impl From<GhPullRequest> for PullRequest {
+    fn from(pr: GhPullRequest) -> Self {
+        let state = match pr.state.as_str() {
+            "MERGED" => PrState::Merged,
+            "CLOSED" => PrState::Closed,
+            _ => PrState::Open,
+        };
+        PullRequest {
+            number: pr.number,
+            title: pr.title,
+            head: pr.head_ref_name,
+            base: pr.base_ref_name,
+            state,
+            url: pr.url,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GhStatusRollup {
+    #[serde(default)]
+    status_check_rollup: Vec<GhCheck>,
+}
+
+/// Either a check run (`name`/`status`/`conclusion`) or a commit status
+/// (`context`/`state`).
+#[derive(Deserialize)]
+pub(super) struct GhCheck {
+    name: Option<String>,
+    context: Option<String>,
+    status: Option<String>,
+    conclusion: Option<String>,
+    state: Option<String>,
+}
+
+impl From<GhCheck> for Check {
+    fn from(check: GhCheck) -> Self {
+        let outcome = check.conclusion.or(check.state).unwrap_or_default();
+        let state = match outcome.as_str() {
+            "SUCCESS" | "NEUTRAL" => CheckState::Pass,
+            "SKIPPED" => CheckState::Skipped,
+            "FAILURE" | "ERROR" | "CANCELLED" | "TIMED_OUT" | "ACTION_REQUIRED"
+            | "STARTUP_FAILURE" => CheckState::Fail,
+            _ => CheckState::Pending,
+        };
+        let state = match check.status.as_deref() {
+            Some(status) if status != "COMPLETED" => CheckState::Pending,
+            _ => state,
+        };
+        Check { name: check.name.or(check.context).unwrap_or_default(), state }
+    }
+}
+
+/// An issue comment as returned by the REST API.
+#[derive(Deserialize)]
+pub(super) struct GhComment {
+    user: GhUser,
+    body: String,
+}
+
+impl From<GhComment> for Comment {
+    fn from(comment: GhComment) -> Self {
+        Comment { author: comment.user.login, body: comment.body }
+    }
+}
+
+#[derive(Deserialize)]
+struct GhUser {
+    login: String,
+}
+
+impl Forge for GhForge {
+    fn fetch_issue(&self, number: u32) -> Result<Issue> {
+        let json = self
+            .gh(&["issue", "view", &number.to_string(), "--json", "number,title,body,labels,state"])
+            .context("Failed to fetch issue. Is `gh` authenticated?")?;
+        parse_issue(&json)
+    }
+
+    fn create_issue(&self, issue: &NewIssue) -> Result<Issue> {
+        let mut args = vec!["issue", "create", "--title", issue.title, "--body", issue.body];
+        for label in issue.labels {
+            args.extend(["--label", label]);
+        }
+        for assignee in issue.assignees {
+            args.extend(["--assignee", assignee]);
+        }
+        let output = self.gh(&args).context("Failed to create issue")?;
+        Ok(issue.created(issue_number_from_url(&output)?))
+    }
+
+    fn sub_issues(&self, number: u32) -> Result<Vec<Issue>> {
+        let endpoint = format!("repos/{{owner}}/{{repo}}/issues/{number}/sub_issues");
+        let json = self
+            .gh(&["api", "--paginate", &endpoint])
+            .with_context(|| format!("Failed to list sub-issues of #{number}"))?;
+        let issues: Vec<GhIssue> =
+            parse_pages(&json).context("Failed to parse sub-issues JSON")?;
+        Ok(issues.into_iter().map(Issue::from).collect())
+    }
+
+    fn find_pr(&self, head: &str) -> Result<Option<PullRequest>> {
+        match self.gh(&["pr", "view", head, "--json", PR_FIELDS]) {
+            Ok(json) => {
+                let pr: GhPullRequest =
+                    serde_json::from_str(&json).context("Failed to parse PR JSON")?;
+                Ok(Some(pr.into()))
+            }
+            Err(e) if format!("{e:#}").contains("no pull requests found") => Ok(None),
+            Err(e) => Err(e.context(format!("Failed to look up PR for {head}"))),
+        }
+    }
+
+    fn list_prs(&self, base: Option<&str>) -> Result<Vec<PullRequest>> {
+        let mut args = vec!["pr", "list", "--state", "open", "--json", PR_FIELDS];
+        if let Some(base) = base {
+            args.extend(["--base", base]);
+        }
+        let json = self.gh(&args).context("Failed to list PRs")?;
+        let prs: Vec<GhPullRequest> =
+            serde_json::from_str(&json).context("Failed to parse PR list JSON")?;
+        Ok(prs.into_iter().map(PullRequest::from).collect())
+    }
+
+    fn create_pr(&self, pr: &NewPullRequest) -> Result<PullRequest> {
+        self.gh(&[
+            "pr", "create", "--title", pr.title, "--body", pr.body, "--head", pr.head, "--base",
+            pr.base,
+        ])
+        .context("Failed to create PR")?;
+        self.find_pr(pr.head)?
+            .with_context(|| format!("PR for {} not found after creating it", pr.head))
+    }
+
+    fn update_pr(&self, number: u32, update: &PrUpdate) -> Result<()> {
+        let number = number.to_string();
+        let mut args = vec!["pr", "edit", &number];
+        if let Some(title) = update.title {
+            args.extend(["--title", title]);
+        }
+        if let Some(body) = update.body {
+            args.extend(["--body", body]);
+        }
+        if let Some(base) = update.base {
+            args.extend(["--base", base]);
+        }
+        self.gh(&args).with_context(|| format!("Failed to update PR #{number}"))?;
+        Ok(())
+    }
+
+    fn merge_pr(&self, number: u32, method: MergeMethod) -> Result<()> {
+        let flag = match method {
+            MergeMethod::Squash => "--squash",
+            MergeMethod::Merge => "--merge",
+            MergeMethod::Rebase => "--rebase",
+        };
+        self.gh(&["pr", "merge", &number.to_string(), flag])
+            .with_context(|| format!("Failed to merge PR #{number}"))?;
+        Ok(())
+    }
+
+    fn checks(&self, number: u32) -> Result<Vec<Check>> {
+        let json = self
+            .gh(&["pr", "view", &number.to_string(), "--json", "statusCheckRollup"])
+            .with_context(|| format!("Failed to read checks of PR #{number}"))?;
+        let rollup: GhStatusRollup =
+            serde_json::from_str(&json).context("Failed to parse checks JSON")?;
+        Ok(rollup.status_check_rollup.into_iter().map(Check::from).collect())
+    }
+
+    fn comment(&self, number: u32, body: &str) -> Result<()> {
+        let endpoint = format!("repos/{{owner}}/{{repo}}/issues/{number}/comments");
+        self.gh(&["api", &endpoint, "-f", &format!("body={body}")])
+            .with_context(|| format!("Failed to comment on PR #{number}"))?;
+        Ok(())
+    }
+
+    fn comments(&self, number: u32) -> Result<Vec<Comment>> {
+        let endpoint = format!("repos/{{owner}}/{{repo}}/issues/{number}/comments");
+        let json = self
+            .gh(&["api", "--paginate", &endpoint])
+            .with_context(|| format!("Failed to list comments of PR #{number}"))?;
+        let comments: Vec<GhComment> =
+            parse_pages(&json).context("Failed to parse comments JSON")?;
+        Ok(comments.into_iter().map(Comment::from).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::runner::fake::FakeRunner;
+
+    #[test]
+    fn test_gh_invocations() {
+        let runner = Rc::new(
+            FakeRunner::new().with_failure("gh pr view feat/9-x", "no pull requests found for branch"),
+        );
+        let forge = GhForge::new(PathBuf::from("/repo"), runner.clone());
+
+        assert!(forge.find_pr("feat/9-x").unwrap().is_none());
+        forge.merge_pr(4, MergeMethod::Rebase).unwrap();
+        assert_eq!(runner.commands()[1], "gh pr merge 4 --rebase");
+        assert!(runner.calls().iter().all(|c| c.dir.as_deref() == Some(Path::new("/repo"))));
+    }
+
+    #[test]
+    fn test_pr_state_mapping() {
+        let json = r#"{"number":3,"title":"t","headRefName":"feat/1-x","baseRefName":"main","state":"MERGED","url":"u"}"#;
+        let pr: PullRequest = serde_json::from_str::<GhPullRequest>(json).unwrap().into();
+        assert_eq!(pr.state, PrState::Merged);
+        assert_eq!(pr.head, "feat/1-x");
+        assert_eq!(pr.base, "main");
+    }
+
+    #[test]
+    fn test_sub_issues() {
+        let endpoint = "gh api --paginate repos/{owner}/{repo}/issues/7/sub_issues";
+        let json = r#"[{"number":8,"title":"Index","body":null,"labels":[{"name":"size:S"}],"state":"closed"}]"#;
+        let runner = Rc::new(FakeRunner::new().with_output(endpoint, json));
+        let forge = GhForge::new(PathBuf::from("/repo"), runner.clone());
+
+        let issues = forge.sub_issues(7).unwrap();
+        assert_eq!((issues[0].number, issues[0].closed), (8, true));
+        assert_eq!(issues[0].labels, vec!["size:S"]);
+    }
+
+    #[test]
+    fn test_comments_span_pages() {
+        let endpoint = "gh api --paginate repos/{owner}/{repo}/issues/4/comments";
+        let pages = "[{\"user\":{\"login\":\"alice\"},\"body\":\"first\"}]\
+                     [{\"user\":{\"login\":\"bob\"},\"body\":\"second\"}]";
+        let runner = Rc::new(FakeRunner::new().with_output(endpoint, pages));
+        let forge = GhForge::new(PathBuf::from("/repo"), runner);
+
+        let authors: Vec<String> =
+            forge.comments(4).unwrap().into_iter().map(|c| c.author).collect();
+        assert_eq!(authors, vec!["alice", "bob"]);
+    }
+
+    #[test]
+    fn test_check_mapping() {
+        let json = r#"{"statusCheckRollup":[
+            {"__typename":"CheckRun","name":"lint","status":"COMPLETED","conclusion":"SUCCESS"},
+            {"__typename":"CheckRun","name":"test","status":"IN_PROGRESS","conclusion":""},
+            {"__typename":"CheckRun","name":"e2e","status":"COMPLETED","conclusion":"FAILURE"},
+            {"__typename":"StatusContext","context":"deploy","state":"PENDING"}
+        ]}"#;
+        let rollup: GhStatusRollup = serde_json::from_str(json).unwrap();
+        let checks: Vec<Check> = rollup.status_check_rollup.into_iter().map(Check::from).collect();
+        let states: Vec<_> = checks.iter().map(|c| (c.name.as_str(), c.state)).collect();
+        assert_eq!(
+            states,
+            vec![
+                ("lint", CheckState::Pass),
+                ("test", CheckState::Pending),
+                ("e2e", CheckState::Fail),
+                ("deploy", CheckState::Pending),
+            ]
+        );
+    }
+}
@@ -0,0 +1,836 @@
+use std::path::Path;
+use std::thread;
+use std::rc::Rc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use jiff::Timestamp;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use ureq::http::{Method, Request};
+use ureq::Agent;
+
+use super::gh::{GhCheck, GhComment};
+use super::{
+    Check, Comment, Forge, Issue, MergeMethod, NewIssue, NewPullRequest, PrState, PrUpdate,
+    PullRequest,
+};
+use crate::clock::Clock;
+use crate::error::{Error, ErrorKind};
+use crate::helpers::warn;
+use crate::runner::CommandRunner;
+
+/// API base URL of github.com.
+pub const DEFAULT_API_URL: &str = "https://api.github.com";
+
+/// Environment variable holding the API token.
+const TOKEN_ENV: &str = "GITHUB_TOKEN";
+
+/// How many times a rate-limited request is retried.
+const RATE_LIMIT_RETRIES: u32 = 3;
+
+/// Longest we are willing to sleep for a rate limit to reset.
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+
+/// Request timeout for a single API call.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Status check contexts of the latest commit of a pull request.
+const CHECKS_QUERY: &str = "query($owner: String!, $name: String!, $number: Int!) {
+  repository(owner: $owner, name: $name) {
+    pullRequest(number: $number) {
+      commits(last: 1) {
+        nodes {
+          commit {
+            statusCheckRollup {
+              contexts(first: 100) {
+                nodes {
+                  ... on CheckRun { name status conclusion }
+                  ... on StatusContext { context state }
+                }
+              }
+            }
+          }
+        }
+      }
+    }
+  }
+}";
+
+/// GitHub backend that calls the REST and GraphQL APIs with `GITHUB_TOKEN`.
+/// The base URL is configurable for GitHub Enterprise.
+pub struct GitHubApiForge {
+    agent: Agent,
+    api_url: String,
+    token: Option<String>,
+    repo: Option<String>,
+    clock: Rc<dyn Clock>,
+}
+
+/// A raw API response.
+struct ApiResponse {
+    link: Option<String>,
+    body: String,
+}
+
+#[derive(Deserialize)]
+struct ApiIssue {
+    number: u32,
+    title: String,
+    body: Option<String>,
+    #[serde(default)]
+    labels: Vec<ApiLabel>,
+    state: String,
+}
+
+#[derive(Deserialize)]
+struct ApiLabel {
+    name: String,
+}
+
+impl From<ApiIssue> for Issue {
+    fn from(issue: ApiIssue) -> Self {
+        Issue {
+            number: issue.number,
+            title: issue.title,
+            body: issue.body.unwrap_or_default(),
+            labels: issue.labels.into_iter().map(|l| l.name).collect(),
+            closed: issue.state == "closed",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ApiPullRequest {
+    number: u32,
+    title: String,
+    head: ApiRef,
+    base: ApiRef,
+    state: String,
+    merged_at: Option<String>,
+    html_url: String,
+}
+
+#[derive(Deserialize)]
+struct ApiRef {
+    #[serde(rename = "ref")]
+    name: String,
+}
+
+impl From<ApiPullRequest> for PullRequest {
+    fn from(pr: ApiPullRequest) -> Self {
+        let state = match (pr.state.as_str(), &pr.merged_at) {
+            (_, Some(_)) => PrState::Merged,
+            ("closed", None) => PrState::Closed,
+            _ => PrState::Open,
+        };
+        PullRequest {
+            number: pr.number,
+            title: pr.title,
+            head: pr.head.name,
+            base: pr.base.name,
+            state,
+            url: pr.html_url,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct GraphQlResponse<T> {
+    data: Option<T>,
+    #[serde(default)]
+    errors: Vec<GraphQlError>,
+}
+
+#[derive(Deserialize)]
+struct GraphQlError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct ChecksData {
+    repository: ChecksRepository,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChecksRepository {
+    pull_request: Option<ChecksPullRequest>,
+}
+
+#[derive(Deserialize)]
+struct ChecksPullRequest {
+    commits: Nodes<CommitNode>,
+}
+
+#[derive(Deserialize)]
+struct Nodes<T> {
+    nodes: Vec<T>,
+}
+
+#[derive(Deserialize)]
+struct CommitNode {
+    commit: RollupCommit,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RollupCommit {
+    status_check_rollup: Option<Rollup>,
+}
+
+#[derive(Deserialize)]
+struct Rollup {
+    contexts: Nodes<GhCheck>,
+}
+
+impl GitHubApiForge {
+    pub fn new(
+        api_url: &str,
+        token: Option<String>,
+        repo: Option<String>,
+        clock: Rc<dyn Clock>,
+    ) -> Self {
+        let agent = Agent::config_builder()
+            .http_status_as_error(false)
+            .timeout_global(Some(REQUEST_TIMEOUT))
+            .build()
+            .into();
+        Self {
+            agent,
+            api_url: api_url.trim_end_matches('/').to_string(),
+            token,
+            repo,
+            clock,
+        }
+    }
+
+    /// Create a client for the repository at `root`, reading the token from
+    /// the environment and `owner/name` from the `origin` remote.
+    pub fn from_env(
+        api_url: Option<&str>,
+        root: &Path,
+        runner: &dyn CommandRunner,
+        clock: Rc<dyn Clock>,
+    ) -> Self {
+        let token = std::env::var(TOKEN_ENV).ok().filter(|t| !t.is_empty());
+        let repo = runner
+            .run("git", &["remote", "get-url", "origin"], Some(root))
+            .ok()
+            .and_then(|remote| repo_slug(&remote));
+        Self::new(api_url.unwrap_or(DEFAULT_API_URL), token, repo, clock)
+    }
+
+    fn repo(&self) -> Result<&str> {
+        self.repo
+            .as_deref()
+            .context("Could not determine the GitHub repository from the `origin` remote")
+    }
+
+    /// Send a request, waiting out rate limits, and fail on non-2xx responses.
+    fn send(&self, method: Method, url: &str, body: Option<&Value>) -> Result<ApiResponse> {
+        let token = self.token.as_deref().with_context(|| {
+            Error::new(ErrorKind::Auth, format!("{TOKEN_ENV} is not set"))
+                .with_hint("Set GITHUB_TOKEN to a token with access to this repository")
+        })?;
+        let url = if url.starts_with("http://") || url.starts_with("https://") {
+            url.to_string()
+        } else {
+            format!("{}{url}", self.api_url)
+        };
+
+        let mut attempt = 0;
+        loop {
+            let request = Request::builder()
+                .method(method.clone())
+                .uri(&url)
+                .header("Authorization", format!("Bearer {token}"))
+                .header("Accept", "application/vnd.github+json")
+                .header("X-GitHub-Api-Version", "2022-11-28")
+                .header("User-Agent", "aidd");
+            let result = match body {
+                Some(body) => self.agent.run(
+                    request.header("Content-Type", "application/json").body(body.to_string())?,
+                ),
+                None => self.agent.run(request.body(())?),
+            };
+            let mut response = result.with_context(|| format!("{method} {url} failed"))?;
+
+            let status = response.status().as_u16();
+            let header = |name: &str| {
+                response.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string)
+            };
+            let link = header("link");
+            let wait = rate_limit_wait(
+                self.clock.now(),
+                status,
+                header("retry-after").as_deref(),
+                header("x-ratelimit-remaining").as_deref(),
+                header("x-ratelimit-reset").as_deref(),
+            );
+            let body = response
+                .body_mut()
+                .read_to_string()
+                .with_context(|| format!("Failed to read response of {method} {url}"))?;
+
+            if let Some(wait) = wait {
+                attempt += 1;
+                if attempt > RATE_LIMIT_RETRIES || wait > MAX_RATE_LIMIT_WAIT {
+                    anyhow::bail!(Error::new(
+                        ErrorKind::Command,
+                        format!("GitHub API rate limit exceeded (resets in {}s)", wait.as_secs())
+                    ));
+                }
+                warn(&format!("GitHub API rate limit hit, retrying in {}s", wait.as_secs()));
+                thread::sleep(wait);
+                continue;
+            }
+
+            if !(200..300).contains(&status) {
+                let message = serde_json::from_str::<Value>(&body)
+                    .ok()
+                    .and_then(|v| v.get("message")?.as_str().map(str::to_string))
+                    .unwrap_or(body);
+                let message = format!("{method} {url} failed ({status}): {message}");
+                anyhow::bail!(match status {
+                    401 | 403 => Error::new(ErrorKind::Auth, message)
+                        .with_hint("Set GITHUB_TOKEN to a token with access to this repository"),
+                    404 => Error::new(ErrorKind::NotFound, message),
+                    _ => Error::new(ErrorKind::Command, message),
+                });
+            }
+            return Ok(ApiResponse { link, body });
+        }
+    }
+
+    fn request<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&Value>,
+    ) -> Result<T> {
+        let response = self.send(method, path, body)?;
+        serde_json::from_str(&response.body)
+            .with_context(|| format!("Failed to parse response of {path}"))
+    }
+
+    /// GET every page of a list endpoint by following `Link: rel="next"`.
+    fn get_all<T: DeserializeOwned>(&self, path: &str) -> Result<Vec<T>> {
+        let mut items = Vec::new();
+        let mut next = Some(path.to_string());
+        while let Some(url) = next {
+            let response = self.send(Method::GET, &url, None)?;
+            let page: Vec<T> = serde_json::from_str(&response.body)
+                .with_context(|| format!("Failed to parse response of {url}"))?;
+            items.extend(page);
+            next = response.link.as_deref().and_then(next_link);
+        }
+        Ok(items)
+    }
+
+    fn graphql<T: DeserializeOwned>(&self, query: &str, variables: Value) -> Result<T> {
+        let body = json!({ "query": query, "variables": variables });
+        let response: GraphQlResponse<T> =
+            self.request(Method::POST, &graphql_url(&self.api_url), Some(&body))?;
+        if let Some(error) = response.errors.first() {
+            anyhow::bail!(Error::new(
+                ErrorKind::Command,
+                format!("GraphQL query failed: {}", error.message)
+            ));
+        }
+        response.data.context("GraphQL response has no data")
+    }
+}
+
+impl Forge for GitHubApiForge {
+    fn fetch_issue(&self, number: u32) -> Result<Issue> {
+        let path = format!("/repos/{}/issues/{number}", self.repo()?);
+        let issue: ApiIssue = self
+            .request(Method::GET, &path, None)
+            .with_context(|| format!("Failed to fetch issue #{number}"))?;
+        Ok(issue.into())
+    }
+
+    fn create_issue(&self, issue: &NewIssue) -> Result<Issue> {
+        let path = format!("/repos/{}/issues", self.repo()?);
+        let body = json!({
+            "title": issue.title,
+            "body": issue.body,
+            "labels": issue.labels,
+            "assignees": issue.assignees,
+        });
+        let created: ApiIssue = self
+            .request(Method::POST, &path, Some(&body))
+            .context("Failed to create issue")?;
+        Ok(created.into())
+    }
+
+    fn sub_issues(&self, number: u32) -> Result<Vec<Issue>> {
+        let path = format!("/repos/{}/issues/{number}/sub_issues?per_page=100", self.repo()?);
+        let issues: Vec<ApiIssue> = self
+            .get_all(&path)
+            .with_context(|| format!("Failed to list sub-issues of #{number}"))?;
+        Ok(issues.into_iter().map(Issue::from).collect())
+    }
+
+    fn find_pr(&self, head: &str) -> Result<Option<PullRequest>> {
+        let repo = self.repo()?;
+        let owner = repo.split('/').next().unwrap_or_default();
+        let path = format!(
+            "/repos/{repo}/pulls?state=all&head={}",
+            encode_query(&format!("{owner}:{head}"))
+        );
+        let prs: Vec<ApiPullRequest> = self
+            .request(Method::GET, &path, None)
+            .with_context(|| format!("Failed to look up PR for {head}"))?;
+        // The API lists newest first.
+        Ok(prs.into_iter().next().map(PullRequest::from))
+    }
+
+    fn list_prs(&self, base: Option<&str>) -> Result<Vec<PullRequest>> {
+        let mut path = format!("/repos/{}/pulls?state=open&per_page=100", self.repo()?);
+        if let Some(base) = base {
+            path.push_str(&format!("&base={}", encode_query(base)));
+        }
+        let prs: Vec<ApiPullRequest> = self.get_all(&path).context("Failed to list PRs")?;
+        Ok(prs.into_iter().map(PullRequest::from).collect())
+    }
+
+    fn create_pr(&self, pr: &NewPullRequest) -> Result<PullRequest> {
+        let path = format!("/repos/{}/pulls", self.repo()?);
+        let body = json!({ "title": pr.title, "body": pr.body, "head": pr.head, "base": pr.base });
+        let created: ApiPullRequest = self
+            .request(Method::POST, &path, Some(&body))
+            .context("Failed to create PR")?;
+        Ok(created.into())
+    }
+
+    fn update_pr(&self, number: u32, update: &PrUpdate) -> Result<()> {
+        let path = format!("/repos/{}/pulls/{number}", self.repo()?);
+        let mut body = serde_json::Map::new();
+        if let Some(title) = update.title {
+            body.insert("title".to_string(), title.into());
+        }
+        if let Some(text) = update.body {
+            body.insert("body".to_string(), text.into());
+        }
+        if let Some(base) = update.base {
+            body.insert("base".to_string(), base.into());
+        }
+        self.send(Method::PATCH, &path, Some(&Value::Object(body)))
+            .with_context(|| format!("Failed to update PR #{number}"))?;
+        Ok(())
+    }
+
+    fn merge_pr(&self, number: u32, method: MergeMethod) -> Result<()> {
+        let path = format!("/repos/{}/pulls/{number}/merge", self.repo()?);
+        let merge_method = match method {
+            MergeMethod::Squash => "squash",
+            MergeMethod::Merge => "merge",
+            MergeMethod::Rebase => "rebase",
+        };
+        self.send(Method::PUT, &path, Some(&json!({ "merge_method": merge_method })))
+            .with_context(|| format!("Failed to merge PR #{number}"))?;
+        Ok(())
+    }
+
+    fn checks(&self, number: u32) -> Result<Vec<Check>> {
+        let repo = self.repo()?;
+        let (owner, name) = repo.split_once('/').unwrap_or((repo, ""));
+        let data: ChecksData = self
+            .graphql(CHECKS_QUERY, json!({ "owner": owner, "name": name, "number": number }))
+            .with_context(|| format!("Failed to read checks of PR #{number}"))?;
+        let pr = data
+            .repository
+            .pull_request
+            .with_context(|| format!("PR #{number} not found"))?;
+        Ok(pr
+            .commits
+            .nodes
+            .into_iter()
+            .next_back()
+            .and_then(|node| node.commit.status_check_rollup)
+            .map(|rollup| rollup.contexts.nodes.into_iter().map(Check::from).collect())
+            .unwrap_or_default())
+    }
+
+    fn comment(&self, number: u32, body: &str) -> Result<()> {
+        let path = format!("/repos/{}/issues/{number}/comments", self.repo()?);
+        self.send(Method::POST, &path, Some(&json!({ "body": body })))
+            .with_context(|| format!("Failed to comment on PR #{number}"))?;
+        Ok(())
+    }
+
+    fn comments(&self, number: u32) -> Result<Vec<Comment>> {
+        let path = format!("/repos/{}/issues/{number}/comments?per_page=100", self.repo()?);
+        let comments: Vec<GhComment> = self
+            .get_all(&path)
+            .with_context(|| format!("Failed to list comments of PR #{number}"))?;
+        Ok(comments.into_iter().map(Comment::from).collect())
+    }
+}
+
+/// How long to wait before retrying a rate-limited response, or `None` if
+/// the response is not rate limited.
+fn rate_limit_wait(
+    now: Timestamp,
+    status: u16,
+    retry_after: Option<&str>,
+    remaining: Option<&str>,
+    reset: Option<&str>,
+) -> Option<Duration> {
+    if status != 403 && status != 429 {
+        return None;
+    }
+    if let Some(secs) = retry_after.and_then(|v| v.trim().parse().ok()) {
+        return Some(Duration::from_secs(secs));
+    }
+    if remaining.map(str::trim) != Some("0") {
+        // A plain 403 is a permission error, not a rate limit.
+        return (status == 429).then_some(Duration::from_secs(1));
+    }
+    let reset: u64 = reset.and_then(|v| v.trim().parse().ok())?;
+    let now = u64::try_from(now.as_second()).unwrap_or_default();
+    Some(Duration::from_secs(reset.saturating_sub(now) + 1))
+}
+
+/// Extract the `rel="next"` URL from a `Link` header.
+fn next_link(link: &str) -> Option<String> {
+    link.split(',').find_map(|part| {
+        let (url, params) = part.split_once(';')?;
+        params
+            .split(';')
+            .any(|p| p.trim() == r#"rel="next""#)
+            .then(|| url.trim().trim_start_matches('<').trim_end_matches('>').to_string())
+    })
+}
+
+/// GraphQL endpoint for a REST base URL; Enterprise's `/api/v3` maps to
+/// `/api/graphql`.
+fn graphql_url(api_url: &str) -> String {
+    match api_url.strip_suffix("/api/v3") {
+        Some(host) => format!("{host}/api/graphql"),
+        None => format!("{api_url}/graphql"),
+    }
+}
+
+/// `owner/name` of a GitHub remote URL (SSH or HTTPS).
+fn repo_slug(remote: &str) -> Option<String> {
+    let remote = remote.trim().trim_end_matches('/').trim_end_matches(".git");
+    let path = match remote.split_once("://") {
+        Some((_, rest)) => rest.split_once('/')?.1,
+        None => remote.split_once(':')?.1,
+    };
+    let mut parts = path.rsplit('/');
+    let name = parts.next().filter(|s| !s.is_empty())?;
+    let owner = parts.next().filter(|s| !s.is_empty())?;
+    Some(format!("{owner}/{name}"))
+}
+
+/// Percent-encode a query parameter value.
+fn encode_query(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' | b':' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::clock::FixedClock;
+    use crate::error;
+    use crate::forge::CheckState;
+
+    const NOW: &str = "2026-01-01T00:00:00Z";
+
+    /// A request received by [`StubServer`].
+    #[derive(Debug, Clone)]
+    struct Received {
+        method: String,
+        path: String,
+        authorization: Option<String>,
+        body: String,
+    }
+
+    /// A canned response: status, extra headers and body.
+    type Response<'a> = (u16, Vec<(&'a str, String)>, &'a str);
+
+    /// Minimal HTTP server that answers with canned responses in order.
+    struct StubServer {
+        url: String,
+        received: Arc<Mutex<Vec<Received>>>,
+    }
+
+    impl StubServer {
+        /// Start a server answering one connection per response.
+        /// `{url}` in headers and bodies is replaced with the server URL.
+        fn start(responses: Vec<Response>) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            let received = Arc::new(Mutex::new(Vec::new()));
+            let responses: Vec<_> = responses
+                .into_iter()
+                .map(|(status, headers, body)| {
+                    let headers = headers
+                        .into_iter()
+                        .map(|(k, v)| (k.to_string(), v.replace("{url}", &url)))
+                        .collect::<Vec<_>>();
+                    (status, headers, body.replace("{url}", &url))
+                })
+                .collect();
+
+            let log = Arc::clone(&received);
+            thread::spawn(move || {
+                for (status, headers, body) in responses {
+                    let Ok((stream, _)) = listener.accept() else { return };
+                    let mut reader = BufReader::new(stream);
+                    let mut request_line = String::new();
+                    reader.read_line(&mut request_line).unwrap();
+                    let mut parts = request_line.split_whitespace();
+                    let method = parts.next().unwrap_or_default().to_string();
+                    let path = parts.next().unwrap_or_default().to_string();
+
+                    let mut length = 0;
+                    let mut authorization = None;
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        let line = line.trim_end();
+                        if line.is_empty() {
+                            break;
+                        }
+                        let (name, value) = line.split_once(':').unwrap();
+                        match name.to_ascii_lowercase().as_str() {
+                            "content-length" => length = value.trim().parse().unwrap(),
+                            "authorization" => authorization = Some(value.trim().to_string()),
+                            _ => {}
+                        }
+                    }
+                    let mut request_body = vec![0; length];
+                    reader.read_exact(&mut request_body).unwrap();
+                    log.lock().unwrap().push(Received {
+                        method,
+                        path,
+                        authorization,
+                        body: String::from_utf8(request_body).unwrap(),
+                    });
+
+                    let mut response = format!(
+                        "HTTP/1.1 {status} Stub\r\nContent-Type: application/json\r\n\
+                         Content-Length: {}\r\nConnection: close\r\n",
+                        body.len()
+                    );
+                    for (name, value) in headers {
+                        response.push_str(&format!("{name}: {value}\r\n"));
+                    }
+                    response.push_str(&format!("\r\n{body}"));
+                    let mut stream = reader.into_inner();
+                    stream.write_all(response.as_bytes()).unwrap();
+                }
+            });
+            Self { url, received }
+        }
+
+        fn forge(&self) -> GitHubApiForge {
+            let clock = Rc::new(FixedClock(NOW.parse().unwrap()));
+            GitHubApiForge::new(&self.url, Some("t0ken".into()), Some("acme/shop".into()), clock)
+        }
+
+        fn received(&self) -> Vec<Received> {
+            self.received.lock().unwrap().clone()
+        }
+    }
+
+    const PR_JSON: &str = r#"{"number":4,"title":"[ISSUE-1] Search","head":{"ref":"feat/1-search"},"base":{"ref":"main"},"state":"closed","merged_at":"2026-01-01T00:00:00Z","html_url":"https://github.test/acme/shop/pull/4"}"#;
+
+    #[test]
+    fn test_fetch_issue() {
+        let server = StubServer::start(vec![(
+            200,
+            vec![],
+            r#"{"number":12,"title":"Add search","body":null,"labels":[{"name":"size:M"}],"state":"open"}"#,
+        )]);
+        let issue = server.forge().fetch_issue(12).unwrap();
+        assert_eq!(issue.title, "Add search");
+        assert_eq!(issue.body, "");
+        assert_eq!(issue.labels, vec!["size:M"]);
+        assert!(!issue.closed);
+
+        let received = server.received();
+        assert_eq!(received[0].method, "GET");
+        assert_eq!(received[0].path, "/repos/acme/shop/issues/12");
+        assert_eq!(received[0].authorization.as_deref(), Some("Bearer t0ken"));
+    }
+
+    #[test]
+    fn test_find_pr_maps_merged_state() {
+        let prs = format!("[{PR_JSON}]");
+        let server = StubServer::start(vec![(200, vec![], &prs), (200, vec![], "[]")]);
+        let forge = server.forge();
+        let pr = forge.find_pr("feat/1-search").unwrap().unwrap();
+        assert_eq!(pr.state, PrState::Merged);
+        assert_eq!(pr.head, "feat/1-search");
+        assert!(forge.find_pr("feat/2-none").unwrap().is_none());
+        assert_eq!(
+            server.received()[0].path,
+            "/repos/acme/shop/pulls?state=all&head=acme:feat/1-search"
+        );
+    }
+
+    #[test]
+    fn test_list_prs_follows_pagination() {
+        let server = StubServer::start(vec![
+            (
+                200,
+                vec![("Link", r#"<{url}/page2>; rel="next", <{url}/page2>; rel="last""#.into())],
+                &format!("[{PR_JSON}]"),
+            ),
+            (200, vec![], &format!("[{}]", PR_JSON.replace("\"number\":4", "\"number\":5"))),
+        ]);
+        let prs = server.forge().list_prs(Some("main")).unwrap();
+        let numbers: Vec<_> = prs.iter().map(|pr| pr.number).collect();
+        assert_eq!(numbers, vec![4, 5]);
+        let paths: Vec<_> = server.received().into_iter().map(|r| r.path).collect();
+        assert_eq!(
+            paths,
+            vec!["/repos/acme/shop/pulls?state=open&per_page=100&base=main", "/page2"]
+        );
+    }
+
+    #[test]
+    fn test_retries_after_rate_limit() {
+        let server = StubServer::start(vec![
+            (429, vec![("Retry-After", "0".into())], r#"{"message":"slow down"}"#),
+            (201, vec![], r#"{"id":1}"#),
+        ]);
+        server.forge().comment(4, "hello").unwrap();
+        let received = server.received();
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[1].method, "POST");
+        assert_eq!(received[1].body, r#"{"body":"hello"}"#);
+    }
+
+    #[test]
+    fn test_rate_limit_beyond_max_wait_fails() {
+        let reset = NOW.parse::<Timestamp>().unwrap().as_second() + 3600;
+        let server = StubServer::start(vec![(
+            403,
+            vec![("X-RateLimit-Remaining", "0".into()), ("X-RateLimit-Reset", reset.to_string())],
+            r#"{"message":"API rate limit exceeded"}"#,
+        )]);
+        let err = server.forge().fetch_issue(1).unwrap_err();
+        assert!(format!("{err:#}").contains("rate limit exceeded"));
+        assert_eq!(error::kind(&err), ErrorKind::Command);
+    }
+
+    #[test]
+    fn test_error_message_is_reported() {
+        let server = StubServer::start(vec![(404, vec![], r#"{"message":"Not Found"}"#)]);
+        let err = server.forge().fetch_issue(99).unwrap_err();
+        assert!(format!("{err:#}").contains("(404): Not Found"));
+        assert_eq!(error::kind(&err), ErrorKind::NotFound);
+
+        let server = StubServer::start(vec![(401, vec![], r#"{"message":"Bad credentials"}"#)]);
+        let err = server.forge().fetch_issue(1).unwrap_err();
+        assert_eq!(error::kind(&err), ErrorKind::Auth);
+    }
+
+    #[test]
+    fn test_graphql_errors_fail_as_command_errors() {
+        let server = StubServer::start(vec![(
+            200,
+            vec![],
+            r#"{"data":null,"errors":[{"message":"Could not resolve to a PullRequest"}]}"#,
+        )]);
+        let err = server.forge().checks(4).unwrap_err();
+        assert!(format!("{err:#}").contains("GraphQL query failed"));
+        assert_eq!(error::kind(&err), ErrorKind::Command);
+    }
+
+    #[test]
+    fn test_checks_via_graphql() {
+        let server = StubServer::start(vec![(
+            200,
+            vec![],
+            r#"{"data":{"repository":{"pullRequest":{"commits":{"nodes":[{"commit":{"statusCheckRollup":{"contexts":{"nodes":[
+                {"name":"lint","status":"COMPLETED","conclusion":"SUCCESS"},
+                {"name":"test","status":"IN_PROGRESS","conclusion":null},
+                {"context":"deploy","state":"FAILURE"}
+            ]}}}}]}}}}}"#,
+        )]);
+        let checks = server.forge().checks(4).unwrap();
+        let states: Vec<_> = checks.iter().map(|c| (c.name.as_str(), c.state)).collect();
+        assert_eq!(
+            states,
+            vec![
+                ("lint", CheckState::Pass),
+                ("test", CheckState::Pending),
+                ("deploy", CheckState::Fail),
+            ]
+        );
+        let received = server.received();
+        assert_eq!(received[0].path, "/graphql");
+        assert!(received[0].body.contains(r#""number":4"#));
+    }
+
+    #[test]
+    fn test_missing_token_fails_before_request() {
+        let clock = Rc::new(FixedClock(NOW.parse().unwrap()));
+        let forge =
+            GitHubApiForge::new("http://127.0.0.1:1", None, Some("acme/shop".into()), clock);
+        let err = forge.fetch_issue(1).unwrap_err();
+        assert!(format!("{err:#}").contains("GITHUB_TOKEN is not set"));
+        assert_eq!(error::kind(&err), ErrorKind::Auth);
+    }
+
+    #[test]
+    fn test_next_link() {
+        let link = r#"<https://api.github.com/x?page=2>; rel="next", <https://api.github.com/x?page=5>; rel="last""#;
+        assert_eq!(next_link(link).as_deref(), Some("https://api.github.com/x?page=2"));
+        assert_eq!(next_link(r#"<https://api.github.com/x?page=1>; rel="prev""#), None);
+    }
+
+    #[test]
+    fn test_graphql_url() {
+        assert_eq!(graphql_url("https://api.github.com"), "https://api.github.com/graphql");
+        assert_eq!(
+            graphql_url("https://github.example.com/api/v3"),
+            "https://github.example.com/api/graphql"
+        );
+    }
+
+    #[test]
+    fn test_repo_slug() {
+        assert_eq!(repo_slug("git@github.com:acme/shop.git").as_deref(), Some("acme/shop"));
+        assert_eq!(repo_slug("https://github.com/acme/shop\n").as_deref(), Some("acme/shop"));
+        assert_eq!(
+            repo_slug("ssh://git@github.example.com:22/acme/shop.git").as_deref(),
+            Some("acme/shop")
+        );
+        assert_eq!(repo_slug("shop"), None);
+    }
+
+    #[test]
+    fn test_rate_limit_wait() {
+        let now = Timestamp::from_second(1_000).unwrap();
+        assert_eq!(rate_limit_wait(now, 200, Some("5"), None, None), None);
+        assert_eq!(rate_limit_wait(now, 403, None, Some("12"), None), None);
+        let wait = rate_limit_wait(now, 429, Some("7"), None, None);
+        assert_eq!(wait, Some(Duration::from_secs(7)));
+        let wait = rate_limit_wait(now, 403, None, Some("0"), Some("1030"));
+        assert_eq!(wait, Some(Duration::from_secs(31)));
+        let wait = rate_limit_wait(now, 403, None, Some("0"), Some("0"));
+        assert_eq!(wait, Some(Duration::from_secs(1)));
+    }
+}
@@ -0,0 +1,285 @@
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use super::{
+    issue_number_from_url, parse_pages, Check, CheckState, Comment, Forge, Issue, MergeMethod,
+    NewIssue, NewPullRequest, PrState, PrUpdate, PullRequest,
+};
+use crate::runner::CommandRunner;
+
+/// GitLab backend that shells out to the `glab` CLI.
+///
+/// Pull request numbers are merge request IIDs.
+pub struct GitLabForge {
+    root: PathBuf,
+    runner: Rc<dyn CommandRunner>,
+}
+
+impl GitLabForge {
+    pub fn new(root: PathBuf, runner: Rc<dyn CommandRunner>) -> Self {
+        Self { root, runner }
+    }
+
+    fn glab(&self, args: &[&str]) -> Result<String> {
+        self.runner.run("glab", args, Some(&self.root))
+    }
+}
+
+#[derive(Deserialize)]
+struct GlIssue {
+    iid: u32,
+    title: String,
+    description: Option<String>,
+    #[serde(default)]
+    labels: Vec<String>,
+    #[serde(default)]
+    state: String,
+}
+
+#[derive(Deserialize)]
+struct GlMergeRequest {
+    iid: u32,
+    title: String,
+    source_branch: String,
+    target_branch: String,
+    state: String,
+    web_url: String,
+}
+
+impl From<GlMergeRequest> for PullRequest {
+    fn from(mr: GlMergeRequest) -> Self {
+        let state = match mr.state.as_str() {
+            "merged" => PrState::Merged,
+            "closed" => PrState::Closed,
+            _ => PrState::Open,
+        };
+        PullRequest {
+            number: mr.iid,
+            title: mr.title,
+            head: mr.source_branch,
+            base: mr.target_branch,
+            state,
+            url: mr.web_url,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct GlPipeline {
+    id: u64,
+}
+
+#[derive(Deserialize)]
+struct GlJob {
+    name: String,
+    status: String,
+}
+
+impl From<GlJob> for Check {
+    fn from(job: GlJob) -> Self {
+        let state = match job.status.as_str() {
+            "success" => CheckState::Pass,
+            "failed" | "canceled" => CheckState::Fail,
+            "skipped" | "manual" => CheckState::Skipped,
+            _ => CheckState::Pending,
+        };
+        Check { name: job.name, state }
+    }
+}
+
+#[derive(Deserialize)]
+struct GlNote {
+    author: GlUser,
+    body: String,
+    #[serde(default)]
+    system: bool,
+}
+
+#[derive(Deserialize)]
+struct GlUser {
+    username: String,
+}
+
+impl Forge for GitLabForge {
+    fn fetch_issue(&self, number: u32) -> Result<Issue> {
+        let json = self
+            .glab(&["issue", "view", &number.to_string(), "--output", "json"])
+            .context("Failed to fetch issue. Is `glab` authenticated?")?;
+        let issue: GlIssue = serde_json::from_str(&json).context("Failed to parse issue JSON")?;
+        Ok(Issue {
+            number: issue.iid,
+            title: issue.title,
+            body: issue.description.unwrap_or_default(),
+            labels: issue.labels,
+            closed: issue.state == "closed",
+        })
+    }
+
+    fn create_issue(&self, issue: &NewIssue) -> Result<Issue> {
+        let (labels, assignees) = (issue.labels.join(","), issue.assignees.join(","));
+        let mut args = vec!["issue", "create", "--title", issue.title, "--description", issue.body];
+        if !labels.is_empty() {
+            args.extend(["--label", &labels]);
+        }
+        if !assignees.is_empty() {
+            args.extend(["--assignee", &assignees]);
+        }
+        args.push("--yes");
+        let output = self.glab(&args).context("Failed to create issue")?;
+        Ok(issue.created(issue_number_from_url(&output)?))
+    }
+
+    fn sub_issues(&self, _number: u32) -> Result<Vec<Issue>> {
+        // GitLab's child items are work items, which `glab issue` does not expose.
+        Ok(Vec::new())
+    }
+
+    fn find_pr(&self, head: &str) -> Result<Option<PullRequest>> {
+        let json = self
+            .glab(&["mr", "list", "--source-branch", head, "--all", "--output", "json"])
+            .with_context(|| format!("Failed to look up merge request for {head}"))?;
+        let mrs: Vec<GlMergeRequest> =
+            serde_json::from_str(&json).context("Failed to parse merge request JSON")?;
+        // glab lists newest first.
+        Ok(mrs.into_iter().next().map(PullRequest::from))
+    }
+
+    fn list_prs(&self, base: Option<&str>) -> Result<Vec<PullRequest>> {
+        let mut args = vec!["mr", "list", "--output", "json"];
+        if let Some(base) = base {
+            args.extend(["--target-branch", base]);
+        }
+        let json = self.glab(&args).context("Failed to list merge requests")?;
+        let mrs: Vec<GlMergeRequest> =
+            serde_json::from_str(&json).context("Failed to parse merge request JSON")?;
+        Ok(mrs.into_iter().map(PullRequest::from).collect())
+    }
+
+    fn create_pr(&self, pr: &NewPullRequest) -> Result<PullRequest> {
+        self.glab(&[
+            "mr",
+            "create",
+            "--title",
+            pr.title,
+            "--description",
+            pr.body,
+            "--source-branch",
+            pr.head,
+            "--target-branch",
+            pr.base,
+            "--yes",
+        ])
+        .context("Failed to create merge request")?;
+        self.find_pr(pr.head)?
+            .with_context(|| format!("Merge request for {} not found after creating it", pr.head))
+    }
+
+    fn update_pr(&self, number: u32, update: &PrUpdate) -> Result<()> {
+        let number = number.to_string();
+        let mut args = vec!["mr", "update", &number];
+        if let Some(title) = update.title {
+            args.extend(["--title", title]);
+        }
+        if let Some(body) = update.body {
+            args.extend(["--description", body]);
+        }
+        if let Some(base) = update.base {
+            args.extend(["--target-branch", base]);
+        }
+        self.glab(&args)
+            .with_context(|| format!("Failed to update merge request !{number}"))?;
+        Ok(())
+    }
+
+    fn merge_pr(&self, number: u32, method: MergeMethod) -> Result<()> {
+        let number = number.to_string();
+        let mut args = vec!["mr", "merge", &number, "--yes"];
+        match method {
+            MergeMethod::Squash => args.push("--squash"),
+            MergeMethod::Rebase => args.push("--rebase"),
+            MergeMethod::Merge => {}
+        }
+        self.glab(&args)
+            .with_context(|| format!("Failed to merge merge request !{number}"))?;
+        Ok(())
+    }
+
+    fn checks(&self, number: u32) -> Result<Vec<Check>> {
+        let pipelines = self
+            .glab(&["api", &format!("projects/:id/merge_requests/{number}/pipelines")])
+            .with_context(|| format!("Failed to read pipelines of merge request !{number}"))?;
+        let pipelines: Vec<GlPipeline> =
+            serde_json::from_str(&pipelines).context("Failed to parse pipelines JSON")?;
+        // Pipelines are returned newest first.
+        let Some(latest) = pipelines.first() else {
+            return Ok(Vec::new());
+        };
+
+        let jobs = self
+            .glab(&["api", &format!("projects/:id/pipelines/{}/jobs", latest.id)])
+            .context("Failed to read pipeline jobs")?;
+        let jobs: Vec<GlJob> = serde_json::from_str(&jobs).context("Failed to parse jobs JSON")?;
+        Ok(jobs.into_iter().map(Check::from).collect())
+    }
+
+    fn comment(&self, number: u32, body: &str) -> Result<()> {
+        let endpoint = format!("projects/:id/merge_requests/{number}/notes");
+        self.glab(&["api", "--method", "POST", &endpoint, "-f", &format!("body={body}")])
+            .with_context(|| format!("Failed to comment on merge request !{number}"))?;
+        Ok(())
+    }
+
+    fn comments(&self, number: u32) -> Result<Vec<Comment>> {
+        let endpoint = format!("projects/:id/merge_requests/{number}/notes");
+        let json = self
+            .glab(&["api", "--paginate", &endpoint])
+            .with_context(|| format!("Failed to list comments of merge request !{number}"))?;
+        let notes: Vec<GlNote> = parse_pages(&json).context("Failed to parse notes JSON")?;
+        Ok(notes
+            .into_iter()
+            .filter(|n| !n.system)
+            .map(|n| Comment { author: n.author.username, body: n.body })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::fake::FakeRunner;
+
+    #[test]
+    fn test_merge_request_mapping() {
+        let json = r#"[{"iid":7,"title":"t","source_branch":"feat/1-x","target_branch":"main","state":"opened","web_url":"u"}]"#;
+        let mrs: Vec<GlMergeRequest> = serde_json::from_str(json).unwrap();
+        let pr: PullRequest = mrs.into_iter().next().unwrap().into();
+        assert_eq!(pr.number, 7);
+        assert_eq!(pr.state, PrState::Open);
+        assert_eq!(pr.head, "feat/1-x");
+    }
+
+    #[test]
+    fn test_notes_span_pages() {
+        let endpoint = "glab api --paginate projects/:id/merge_requests/7/notes";
+        let pages = r#"[{"author":{"username":"alice"},"body":"first","system":false}]
+[{"author":{"username":"gitlab"},"body":"changed","system":true},
+ {"author":{"username":"bob"},"body":"second","system":false}]"#;
+        let runner = Rc::new(FakeRunner::new().with_output(endpoint, pages));
+        let forge = GitLabForge::new(PathBuf::from("/repo"), runner);
+
+        let bodies: Vec<String> =
+            forge.comments(7).unwrap().into_iter().map(|c| c.body).collect();
+        assert_eq!(bodies, vec!["first", "second"]);
+    }
+
+    #[test]
+    fn test_job_mapping() {
+        let json = r#"[{"name":"lint","status":"success"},{"name":"test","status":"running"},{"name":"e2e","status":"failed"}]"#;
+        let jobs: Vec<GlJob> = serde_json::from_str(json).unwrap();
+        let states: Vec<_> = jobs.into_iter().map(|j| Check::from(j).state).collect();
+        assert_eq!(states, vec![CheckState::Pass, CheckState::Pending, CheckState::Fail]);
+    }
+}
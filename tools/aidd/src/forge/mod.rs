@@ -0,0 +1,226 @@
+pub mod dry_run;
+#[cfg(test)]
+pub mod fake;
+mod gh;
+mod github_api;
+mod gitlab;
+pub mod offline;
+
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::de::DeserializeOwned;
+
+use crate::clock::Clock;
+use crate::config::{Config, ForgeBackend};
+use crate::runner::CommandRunner;
+
+pub use gh::GhForge;
+pub use github_api::GitHubApiForge;
+pub use gitlab::GitLabForge;
+
+/// An issue as returned by the forge.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Issue {
+    pub number: u32,
+    pub title: String,
+    pub body: String,
+    pub labels: Vec<String>,
+    pub closed: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrState {
+    Open,
+    Closed,
+    Merged,
+}
+
+/// A pull request (merge request on GitLab).
+#[derive(Debug, Clone, PartialEq)]
+pub struct PullRequest {
+    pub number: u32,
+    pub title: String,
+    pub head: String,
+    pub base: String,
+    pub state: PrState,
+    pub url: String,
+}
+
+/// Parameters for opening an issue.
+pub struct NewIssue<'a> {
+    pub title: &'a str,
+    pub body: &'a str,
+    pub labels: &'a [String],
+    pub assignees: &'a [String],
+}
+
+/// Parameters for opening a pull request.
+pub struct NewPullRequest<'a> {
+    pub title: &'a str,
+    pub body: &'a str,
+    pub head: &'a str,
+    pub base: &'a str,
+}
+
+/// Fields to change on an existing pull request; `None` leaves a field as is.
+#[derive(Default)]
+pub struct PrUpdate<'a> {
+    pub title: Option<&'a str>,
+    pub body: Option<&'a str>,
+    pub base: Option<&'a str>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckState {
+    Pass,
+    Fail,
+    Pending,
+    Skipped,
+}
+
+/// A CI check reported on a pull request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub name: String,
+    pub state: CheckState,
+}
+
+/// A comment on a pull request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub author: String,
+    pub body: String,
+}
+
+/// How a pull request is merged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MergeMethod {
+    Squash,
+    Merge,
+    Rebase,
+}
+
+/// Issue and pull request operations of a code hosting service.
+///
+/// Commands reach issues and pull requests only through this trait, so the
+/// backend can be chosen in `.aidd/config.toml` and tests can use a fake.
+pub trait Forge {
+    /// Fetch an issue by number.
+    fn fetch_issue(&self, number: u32) -> Result<Issue>;
+
+    /// Open an issue.
+    fn create_issue(&self, issue: &NewIssue) -> Result<Issue>;
+
+    /// Sub-issues of an issue, in the forge's order; empty where the forge
+    /// has no sub-issues.
+    fn sub_issues(&self, number: u32) -> Result<Vec<Issue>>;
+
+    /// Find the most recent pull request whose head is `head`.
+    fn find_pr(&self, head: &str) -> Result<Option<PullRequest>>;
+
+    /// List open pull requests, optionally only those targeting `base`.
+    fn list_prs(&self, base: Option<&str>) -> Result<Vec<PullRequest>>;
+
+    /// Open a pull request.
+    fn create_pr(&self, pr: &NewPullRequest) -> Result<PullRequest>;
+
+    /// Change the title, body or base of a pull request.
+    fn update_pr(&self, number: u32, update: &PrUpdate) -> Result<()>;
+
+    /// Merge a pull request.
+    fn merge_pr(&self, number: u32, method: MergeMethod) -> Result<()>;
+
+    /// CI checks of a pull request's latest commit.
+    fn checks(&self, number: u32) -> Result<Vec<Check>>;
+
+    /// Post a comment on a pull request.
+    fn comment(&self, number: u32, body: &str) -> Result<()>;
+
+    /// List the comments of a pull request.
+    fn comments(&self, number: u32) -> Result<Vec<Comment>>;
+
+    /// Whether the forge works without the network and knows no issue but
+    /// the one being planned, so linked issues are not looked up.
+    fn is_offline(&self) -> bool {
+        false
+    }
+}
+
+impl NewIssue<'_> {
+    /// The issue as created under `number`.
+    fn created(&self, number: u32) -> Issue {
+        Issue {
+            number,
+            title: self.title.to_string(),
+            body: self.body.to_string(),
+            labels: self.labels.to_vec(),
+            closed: false,
+        }
+    }
+}
+
+/// The issue number at the end of an issue URL printed by `gh` or `glab`,
+/// e.g. `https://github.com/acme/shop/issues/12`.
+fn issue_number_from_url(output: &str) -> Result<u32> {
+    output
+        .lines()
+        .rev()
+        .find_map(|line| line.trim().rsplit_once("/issues/")?.1.parse().ok())
+        .with_context(|| format!("No issue URL in output: {output}"))
+}
+
+/// The items of `gh api --paginate` / `glab api --paginate` output.
+///
+/// Past one page both tools print the pages' arrays back to back
+/// (`[...][...]`), so the output is read as a stream of arrays.
+fn parse_pages<T: DeserializeOwned>(json: &str) -> Result<Vec<T>> {
+    let mut items = Vec::new();
+    for page in serde_json::Deserializer::from_str(json).into_iter::<Vec<T>>() {
+        items.extend(page?);
+    }
+    Ok(items)
+}
+
+/// Create the forge configured for the repository at `root`.
+pub fn from_config(
+    config: &Config,
+    root: PathBuf,
+    runner: Rc<dyn CommandRunner>,
+    clock: Rc<dyn Clock>,
+) -> Box<dyn Forge> {
+    match config.forge.backend {
+        ForgeBackend::Github => Box::new(GhForge::new(root, runner)),
+        ForgeBackend::GithubApi => Box::new(GitHubApiForge::from_env(
+            config.forge.api_url.as_deref(),
+            &root,
+            runner.as_ref(),
+            clock,
+        )),
+        ForgeBackend::Gitlab => Box::new(GitLabForge::new(root, runner)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_issue_number_from_url() {
+        let gh = "Creating issue in acme/shop\n\nhttps://github.com/acme/shop/issues/12\n";
+        assert_eq!(issue_number_from_url(gh).unwrap(), 12);
+        let glab = "https://gitlab.com/acme/shop/-/issues/7";
+        assert_eq!(issue_number_from_url(glab).unwrap(), 7);
+        assert!(issue_number_from_url("error").is_err());
+    }
+
+    #[test]
+    fn test_parse_pages() {
+        let pages: Vec<u32> = parse_pages("[1,2]\n[3]").unwrap();
+        assert_eq!(pages, vec![1, 2, 3]);
+        assert!(parse_pages::<u32>("").unwrap().is_empty());
+        assert!(parse_pages::<u32>("[1,2][").is_err());
+    }
+}
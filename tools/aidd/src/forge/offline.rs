@@ -0,0 +1,195 @@
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use super::{
+    gh, Check, Comment, Forge, Issue, MergeMethod, NewIssue, NewPullRequest, PrUpdate,
+    PullRequest,
+};
+use crate::error::{Error, ErrorKind};
+use crate::frontmatter::{parse_issue_draft, split_frontmatter};
+
+/// Forge for `issue plan --from-file/--stdin`: serves one issue read from
+/// disk and never reaches the network.
+///
+/// Other issues are not found and not looked up, so links to them are
+/// planned from the checkbox text alone; there are no sub-issues.
+pub struct OfflineForge {
+    issue: Issue,
+}
+
+impl OfflineForge {
+    pub fn new(issue: Issue) -> Self {
+        Self { issue }
+    }
+}
+
+/// Read issue `number` from `path`, or from stdin when `path` is `None`.
+pub fn read_issue(number: u32, path: Option<&str>) -> Result<Issue> {
+    let (source, content) = match path {
+        Some(path) => {
+            if !Path::new(path).exists() {
+                anyhow::bail!(Error::new(ErrorKind::NotFound, format!("{path} not found")));
+            }
+            let content =
+                fs::read_to_string(path).with_context(|| format!("Failed to read {path}"))?;
+            (path, content)
+        }
+        None => {
+            let mut content = String::new();
+            std::io::stdin().read_to_string(&mut content).context("Failed to read stdin")?;
+            ("stdin", content)
+        }
+    };
+    parse_issue(number, &content).with_context(|| {
+        Error::new(ErrorKind::Validation, format!("Invalid issue in {source}")).with_hint(
+            "Pass `gh issue view <n> --json number,title,body,labels,state` output, \
+             or markdown whose first line is the title",
+        )
+    })
+}
+
+/// Parse issue `number` from `gh issue view --json` output or markdown.
+///
+/// Markdown starts with the title line (`# Title` or plain text) followed by
+/// the body, or with issue draft frontmatter (`title:`, `labels:`).
+pub fn parse_issue(number: u32, content: &str) -> Result<Issue> {
+    if content.trim_start().starts_with('{') {
+        let issue = gh::parse_issue(content)?;
+        if issue.number != number {
+            anyhow::bail!("The JSON is issue #{}, not #{number}", issue.number);
+        }
+        return Ok(issue);
+    }
+
+    let (title, body, labels) = if split_frontmatter(content).is_some() {
+        let (draft, body) = parse_issue_draft(content)?;
+        (draft.title, body, draft.labels)
+    } else {
+        let content = content.trim_start();
+        let (title, body) = content.split_once('\n').unwrap_or((content, ""));
+        (title.trim_start_matches('#').to_string(), body, Vec::new())
+    };
+    let title = title.trim();
+    if title.is_empty() {
+        anyhow::bail!("The issue has no title");
+    }
+    Ok(Issue {
+        number,
+        title: title.to_string(),
+        body: body.trim().to_string(),
+        labels,
+        closed: false,
+    })
+}
+
+fn unavailable<T>(what: &str) -> Result<T> {
+    anyhow::bail!(Error::new(ErrorKind::Validation, format!("Cannot {what} offline"))
+        .with_hint("Rerun without --from-file or --stdin"))
+}
+
+impl Forge for OfflineForge {
+    fn fetch_issue(&self, number: u32) -> Result<Issue> {
+        if number == self.issue.number {
+            return Ok(self.issue.clone());
+        }
+        anyhow::bail!(Error::new(
+            ErrorKind::NotFound,
+            format!("Issue #{number} is not available offline")
+        ))
+    }
+
+    fn create_issue(&self, _issue: &NewIssue) -> Result<Issue> {
+        unavailable("create an issue")
+    }
+
+    fn sub_issues(&self, _number: u32) -> Result<Vec<Issue>> {
+        Ok(Vec::new())
+    }
+
+    fn find_pr(&self, _head: &str) -> Result<Option<PullRequest>> {
+        unavailable("look up pull requests")
+    }
+
+    fn list_prs(&self, _base: Option<&str>) -> Result<Vec<PullRequest>> {
+        unavailable("list pull requests")
+    }
+
+    fn create_pr(&self, _pr: &NewPullRequest) -> Result<PullRequest> {
+        unavailable("create a pull request")
+    }
+
+    fn update_pr(&self, _number: u32, _update: &PrUpdate) -> Result<()> {
+        unavailable("update a pull request")
+    }
+
+    fn merge_pr(&self, _number: u32, _method: MergeMethod) -> Result<()> {
+        unavailable("merge a pull request")
+    }
+
+    fn checks(&self, _number: u32) -> Result<Vec<Check>> {
+        unavailable("read CI checks")
+    }
+
+    fn comment(&self, _number: u32, _body: &str) -> Result<()> {
+        unavailable("comment on a pull request")
+    }
+
+    fn comments(&self, _number: u32) -> Result<Vec<Comment>> {
+        unavailable("read comments")
+    }
+
+    fn is_offline(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error;
+
+    #[test]
+    fn test_parse_gh_json() {
+        let json = r#"{"number":12,"title":"Add search","body":"- [ ] Index books",
+            "labels":[{"name":"size:M"}],"state":"OPEN"}"#;
+        let issue = parse_issue(12, json).unwrap();
+        assert_eq!(issue.title, "Add search");
+        assert_eq!(issue.body, "- [ ] Index books");
+        assert_eq!(issue.labels, vec!["size:M"]);
+        assert!(!issue.closed);
+
+        let err = parse_issue(13, json).unwrap_err();
+        assert_eq!(err.to_string(), "The JSON is issue #12, not #13");
+    }
+
+    #[test]
+    fn test_parse_markdown() {
+        let issue = parse_issue(12, "\n# Add search\n\n- [ ] Index books\n").unwrap();
+        assert_eq!((issue.number, issue.title.as_str()), (12, "Add search"));
+        assert_eq!(issue.body, "- [ ] Index books");
+
+        let issue = parse_issue(12, "Add search").unwrap();
+        assert_eq!((issue.title.as_str(), issue.body.as_str()), ("Add search", ""));
+
+        let draft = "---\ntitle: \"Fix: login\"\nlabels: size:S\n---\n- [ ] Reproduce\n";
+        let issue = parse_issue(3, draft).unwrap();
+        assert_eq!(issue.title, "Fix: login");
+        assert_eq!(issue.labels, vec!["size:S"]);
+        assert_eq!(issue.body, "- [ ] Reproduce");
+
+        assert!(parse_issue(12, "#\n\nbody").is_err());
+    }
+
+    #[test]
+    fn test_serves_only_the_given_issue() {
+        let forge = OfflineForge::new(parse_issue(12, "# Add search").unwrap());
+        assert_eq!(forge.fetch_issue(12).unwrap().title, "Add search");
+        assert_eq!(error::kind(&forge.fetch_issue(13).unwrap_err()), ErrorKind::NotFound);
+        assert!(forge.sub_issues(12).unwrap().is_empty());
+        assert!(forge.is_offline());
+        assert_eq!(error::kind(&forge.list_prs(None).unwrap_err()), ErrorKind::Validation);
+    }
+}
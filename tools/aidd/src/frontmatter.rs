@@ -14,6 +14,74 @@ pub struct PlanFrontmatter {
     pub created_at: String,
 }
 
+/// Parsed frontmatter from a TASK.md file.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskFrontmatter {
+    pub issue_number: u32,
+    pub task_number: u32,
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub worktree_path: Option<String>,
+    /// Task this task is stacked on; its branch starts from the parent's branch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_task: Option<u32>,
+    /// T-shirt size (`XS`..`XL`), or `?` when not estimated yet.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimate: Option<String>,
+    /// Issue this task stands for, from `- [ ] #42` or a sub-issue.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub linked_issue: Option<u32>,
+    /// Tasks of the same issue that must be done before this one starts.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<u32>,
+}
+
+#[cfg(test)]
+impl TaskFrontmatter {
+    /// Task `number` of issue 1 with `status` and nothing else set, for tests.
+    pub fn test(number: u32, status: &str) -> Self {
+        Self {
+            issue_number: 1,
+            task_number: number,
+            status: status.to_string(),
+            branch_name: None,
+            worktree_path: None,
+            parent_task: None,
+            estimate: None,
+            linked_issue: None,
+            depends_on: Vec::new(),
+        }
+    }
+}
+
+/// Frontmatter of an issue draft for `issue create`.
+///
+/// `labels` and `assignees` take a list or a single name.
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct IssueDraft {
+    pub title: String,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub labels: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub assignees: Vec<String>,
+}
+
+fn one_or_many<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(name) => vec![name],
+        OneOrMany::Many(names) => names,
+    })
+}
+
 /// Split a markdown file into frontmatter (YAML) and body.
 ///
 /// Returns `(frontmatter_yaml, body)`.
@@ -41,6 +109,65 @@ pub fn parse_plan_frontmatter(content: &str) -> Result<PlanFrontmatter> {
     serde_yaml::from_str(yaml).context("Failed to parse PLAN.md frontmatter")
 }
 
+/// Parse TASK.md frontmatter from file content.
+pub fn parse_task_frontmatter(content: &str) -> Result<TaskFrontmatter> {
+    let (yaml, _) =
+        split_frontmatter(content).context("No frontmatter found in TASK.md")?;
+    serde_yaml::from_str(yaml).context("Failed to parse TASK.md frontmatter")
+}
+
+/// Parse an issue draft into its frontmatter and the issue body.
+pub fn parse_issue_draft(content: &str) -> Result<(IssueDraft, &str)> {
+    let (yaml, body) = split_frontmatter(content).context("No frontmatter found in the draft")?;
+    let draft = serde_yaml::from_str(yaml).context("Failed to parse the draft's frontmatter")?;
+    Ok((draft, body))
+}
+
+/// Frontmatter block for `fields`, delimiters and trailing newline included.
+///
+/// Values are quoted as YAML needs, so any title or path reads back as is.
+pub fn render_frontmatter<T: Serialize>(fields: &T) -> Result<String> {
+    let yaml = serde_yaml::to_string(fields).context("Failed to serialize frontmatter")?;
+    Ok(format!("{FRONTMATTER_DELIMITER}\n{yaml}{FRONTMATTER_DELIMITER}\n"))
+}
+
+/// A value as YAML, quoted or in block style where a plain scalar would not
+/// read back the same. For [`set_field`] and [`upsert_field`].
+pub fn yaml_value<T: Serialize + ?Sized>(value: &T) -> Result<String> {
+    let yaml = serde_yaml::to_string(value).context("Failed to serialize frontmatter value")?;
+    Ok(yaml.trim_end_matches('\n').to_string())
+}
+
+/// Set a top-level frontmatter field, appending it to the frontmatter
+/// if it is not present yet.
+pub fn upsert_field(content: &str, key: &str, value: &str) -> Result<String> {
+    if let Ok(updated) = set_field(content, key, value) {
+        return Ok(updated);
+    }
+    let (yaml, _) = split_frontmatter(content).context("No frontmatter found")?;
+    let start = yaml.as_ptr() as usize - content.as_ptr() as usize;
+    let end = start + yaml.len();
+    Ok(format!("{}\n{key}: {value}{}", &content[..end], &content[end..]))
+}
+
+/// Replace the value of a top-level frontmatter field, keeping the rest
+/// of the file byte-for-byte intact.
+///
+/// `value` is written as is; pass strings through [`yaml_value`]. A block
+/// value's indented continuation lines are replaced along with the field.
+/// Fails if the content has no frontmatter or the field is not present.
+pub fn set_field(content: &str, key: &str, value: &str) -> Result<String> {
+    let (yaml, _) = split_frontmatter(content).context("No frontmatter found")?;
+    let prefix = format!("{key}:");
+    let mut lines = yaml.lines().skip_while(|l| !l.starts_with(&prefix));
+    let line = lines.next().with_context(|| format!("Field '{key}' not found in frontmatter"))?;
+    let last = lines.take_while(|l| l.starts_with([' ', '\t'])).last().unwrap_or(line);
+
+    // `yaml` borrows from `content`, so the offsets locate the field lines.
+    let start = line.as_ptr() as usize - content.as_ptr() as usize;
+    let end = last.as_ptr() as usize - content.as_ptr() as usize + last.len();
+    Ok(format!("{}{key}: {value}{}", &content[..start], &content[end..]))
+}
 
 #[cfg(test)]
 mod tests {
@@ -74,6 +201,127 @@ Some goal.
         assert_eq!(fm.status, "draft");
     }
 
+    const SAMPLE_TASK: &str = r#"---
+issueNumber: 11
+taskNumber: 2
+status: todo
+branchName: feat/issue-11-task-2
+worktreePath: .worktrees/issue-11-task-2
+---
+
+# Context
+status: not a field
+"#;
+
+    #[test]
+    fn test_parse_task_frontmatter() {
+        let fm = parse_task_frontmatter(SAMPLE_TASK).unwrap();
+        assert_eq!(fm.issue_number, 11);
+        assert_eq!(fm.task_number, 2);
+        assert_eq!(fm.status, "todo");
+        assert_eq!(fm.branch_name.as_deref(), Some("feat/issue-11-task-2"));
+    }
+
+    #[test]
+    fn test_set_field_only_touches_frontmatter() {
+        let updated = set_field(SAMPLE_TASK, "status", "done").unwrap();
+        assert!(updated.contains("\nstatus: done\n"));
+        assert!(updated.contains("status: not a field"));
+        assert_eq!(parse_task_frontmatter(&updated).unwrap().status, "done");
+    }
+
+    #[test]
+    fn test_upsert_field_appends_missing_key() {
+        let updated = upsert_field(SAMPLE_TASK, "parentTask", "1").unwrap();
+        let fm = parse_task_frontmatter(&updated).unwrap();
+        assert_eq!(fm.parent_task, Some(1));
+        assert_eq!(fm.status, "todo");
+        assert!(updated.contains("# Context"));
+
+        let replaced = upsert_field(&updated, "parentTask", "3").unwrap();
+        assert_eq!(parse_task_frontmatter(&replaced).unwrap().parent_task, Some(3));
+    }
+
+    #[test]
+    fn test_set_field_missing_key() {
+        assert!(set_field(SAMPLE_TASK, "owner", "x").is_err());
+        assert!(set_field("no frontmatter", "status", "done").is_err());
+    }
+
+    /// Titles that break naive `title: "{title}"` interpolation.
+    const HOSTILE_TITLES: &[&str] = &[
+        "Say \"hi\"",
+        "C:\\path\\to",
+        "fix: colon: separated",
+        "- leading dash",
+        "? question",
+        "# not a comment",
+        "'single' quotes",
+        "検索機能を追加 🔍",
+        "two\nlines",
+        "null",
+        "yes",
+        "42",
+        "  padded  ",
+        "",
+    ];
+
+    #[test]
+    fn test_render_frontmatter_round_trip() {
+        for title in HOSTILE_TITLES {
+            let fm = PlanFrontmatter {
+                issue_number: 7,
+                title: title.to_string(),
+                status: "draft".to_string(),
+                owner_agent: "claude".to_string(),
+                created_at: "2026-01-01T00:00:00Z".to_string(),
+            };
+            let content = format!("{}\n# Goal\n", render_frontmatter(&fm).unwrap());
+            assert_eq!(parse_plan_frontmatter(&content).unwrap(), fm, "{content}");
+        }
+    }
+
+    #[test]
+    fn test_set_field_round_trip() {
+        let mut content = SAMPLE_PLAN.to_string();
+        for title in HOSTILE_TITLES {
+            content = set_field(&content, "title", &yaml_value(*title).unwrap()).unwrap();
+            let fm = parse_plan_frontmatter(&content).unwrap();
+            assert_eq!((fm.title.as_str(), fm.status.as_str()), (*title, "draft"), "{content}");
+        }
+        assert!(content.ends_with("---\n\n# Goal\nSome goal.\n"));
+    }
+
+    #[test]
+    fn test_render_task_frontmatter_skips_unset_fields() {
+        let fm = TaskFrontmatter {
+            estimate: Some("?".to_string()),
+            linked_issue: Some(9),
+            depends_on: vec![2],
+            ..TaskFrontmatter::test(1, "todo")
+        };
+        let rendered = render_frontmatter(&fm).unwrap();
+        assert!(!rendered.contains("branchName"));
+        assert!(rendered.contains("dependsOn:\n- 2\n"));
+        assert_eq!(parse_task_frontmatter(&rendered).unwrap(), fm);
+    }
+
+    #[test]
+    fn test_parse_issue_draft() {
+        let draft = "---\ntitle: \"feat: 蔵書検索\"\nlabels: [user-story, size:M]\n\
+                     assignees: alice\n---\n\n## 概要\n";
+        let (fields, body) = parse_issue_draft(draft).unwrap();
+        assert_eq!(fields.title, "feat: 蔵書検索");
+        assert_eq!(fields.labels, vec!["user-story", "size:M"]);
+        assert_eq!(fields.assignees, vec!["alice"]);
+        assert_eq!(body, "\n## 概要\n");
+
+        let (fields, _) = parse_issue_draft("---\ntitle: Docs\n---\n").unwrap();
+        assert!(fields.labels.is_empty() && fields.assignees.is_empty());
+        assert!(parse_issue_draft("---\nlabels: [bug]\n---\n").is_err());
+        assert!(parse_issue_draft("# No frontmatter").is_err());
+    }
+
     #[test]
     fn test_no_frontmatter() {
         let content = "# Just a heading\nNo frontmatter here.";
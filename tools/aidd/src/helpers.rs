@@ -1,151 +1,40 @@
 use std::path::PathBuf;
-use std::process::Command;
 
-use anyhow::{Context, Result};
+use crate::log::{self, Level};
+use crate::runner::CommandRunner;
+
+/// Branch that new worktrees start from and pull requests target by default.
+pub const BASE_BRANCH: &str = "main";
 
 /// Generate the branch name for an issue.
 pub fn branch_name(prefix: &str, issue: u32, summary: &str) -> String {
     format!("{prefix}/{issue}-{summary}")
 }
 
-/// Generate the worktree path for an issue.
-pub fn worktree_path(issue: u32, summary: &str) -> PathBuf {
-    repo_root().join(format!(".worktrees/{issue}-{summary}"))
-}
-
-/// Find the worktree path for an issue by scanning `.worktrees/`.
-///
-/// Returns the first directory whose name starts with `{issue}-`.
-pub fn find_worktree_for_issue(issue: u32) -> Option<PathBuf> {
-    let worktrees_dir = repo_root().join(".worktrees");
-    let prefix = format!("{issue}-");
-
-    std::fs::read_dir(&worktrees_dir)
-        .ok()?
-        .filter_map(|entry| entry.ok())
-        .find(|entry| {
-            entry
-                .file_name()
-                .to_string_lossy()
-                .starts_with(&prefix)
-        })
-        .map(|entry| entry.path())
-}
-
-/// Find all local branches matching `*/{issue}-*`.
-pub fn find_branches_for_issue(issue: u32) -> Vec<String> {
-    let root = repo_root();
-    let pattern = format!("*/{issue}-*");
-    run_command(
-        "git",
-        &["-C", &root.to_string_lossy(), "branch", "--list", &pattern],
-    )
-    .map(|out| {
-        out.lines()
-            .map(|l| l.trim().to_string())
-            .filter(|l| !l.is_empty())
-            .collect()
-    })
-    .unwrap_or_default()
-}
-
-/// Generate the PLAN.md path for an issue.
-pub fn plan_file(issue: u32) -> PathBuf {
-    repo_root().join(format!("features/{issue}/PLAN.md"))
-}
-
-/// Generate the features directory path for an issue.
-pub fn features_dir(issue: u32) -> PathBuf {
-    repo_root().join(format!("features/{issue}"))
-}
-
 /// Get the repository root directory.
 ///
 /// Uses `git rev-parse --show-toplevel` to find the root, falling back
-/// to walking up from the current executable's directory.
-pub fn repo_root() -> PathBuf {
-    let output = Command::new("git")
-        .args(["rev-parse", "--show-toplevel"])
-        .output();
-
-    match output {
-        Ok(out) if out.status.success() => {
-            let root = String::from_utf8_lossy(&out.stdout).trim().to_string();
-            PathBuf::from(root)
-        }
-        _ => {
-            // Fallback: walk up from current dir
-            std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."))
-        }
+/// to the current directory.
+pub fn repo_root(runner: &dyn CommandRunner) -> PathBuf {
+    match runner.run("git", &["rev-parse", "--show-toplevel"], None) {
+        Ok(root) => PathBuf::from(root),
+        Err(_) => std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
     }
 }
 
-/// Run an external command and return its stdout as a String.
-pub fn run_command(program: &str, args: &[&str]) -> Result<String> {
-    run_command_in(program, args, None)
-}
-
-/// Run an external command in a specific directory and return its stdout.
-pub fn run_command_in(program: &str, args: &[&str], dir: Option<&PathBuf>) -> Result<String> {
-    let mut cmd = Command::new(program);
-    cmd.args(args);
-    if let Some(d) = dir {
-        cmd.current_dir(d);
-    }
-
-    let output = cmd
-        .output()
-        .with_context(|| format!("Failed to execute '{program}'. Is it installed?"))?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!(
-            "Command '{} {}' failed (exit {}):\n{}",
-            program,
-            args.join(" "),
-            output.status.code().unwrap_or(-1),
-            stderr.trim()
-        );
-    }
-
-    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
-}
-
-/// Run an external command, inheriting stdin/stdout/stderr for interactive use.
-pub fn run_command_inherit(program: &str, args: &[&str], dir: Option<&PathBuf>) -> Result<()> {
-    let mut cmd = Command::new(program);
-    cmd.args(args);
-    if let Some(d) = dir {
-        cmd.current_dir(d);
-    }
-    cmd.stdin(std::process::Stdio::inherit())
-        .stdout(std::process::Stdio::inherit())
-        .stderr(std::process::Stdio::inherit());
-
-    let status = cmd
-        .status()
-        .with_context(|| format!("Failed to execute '{program}'. Is it installed?"))?;
-
-    if !status.success() {
-        anyhow::bail!(
-            "Command '{} {}' failed (exit {})",
-            program,
-            args.join(" "),
-            status.code().unwrap_or(-1)
-        );
-    }
-
-    Ok(())
-}
-
 /// Print an info message.
 pub fn info(msg: &str) {
-    eprintln!("INFO: {msg}");
+    log::log(Level::Info, msg);
 }
 
 /// Print a warning message.
 pub fn warn(msg: &str) {
-    eprintln!("WARN: {msg}");
+    log::log(Level::Warn, msg);
+}
+
+/// Print a message shown only with `-v`.
+pub fn debug(msg: &str) {
+    log::log(Level::Debug, msg);
 }
 
 /// Port configuration for a per-worktree Supabase instance.
@@ -194,24 +83,6 @@ mod tests {
         assert_eq!(branch_name("chore", 7, "update-ci"), "chore/7-update-ci");
     }
 
-    #[test]
-    fn test_worktree_path_ends_correctly() {
-        let path = worktree_path(3, "add-library");
-        assert!(path.ends_with(".worktrees/3-add-library"));
-    }
-
-    #[test]
-    fn test_plan_file_path() {
-        let path = plan_file(1);
-        assert!(path.ends_with("features/1/PLAN.md"));
-    }
-
-    #[test]
-    fn test_features_dir_path() {
-        let path = features_dir(1);
-        assert!(path.ends_with("features/1"));
-    }
-
     #[test]
     fn test_supabase_ports_issue1() {
         let ports = supabase_ports(1);
@@ -0,0 +1,169 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::io;
+use std::process::Child;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+
+use crate::helpers::{info, warn};
+use crate::repo::Repo;
+
+/// Exit code after an interruption, as shells report for SIGINT.
+pub const EXIT_CODE: i32 = 130;
+
+/// How long an interrupted child gets to exit before it is killed.
+const GRACE_PERIOD: Duration = Duration::from_secs(3);
+
+/// The signal not yet dealt with, or 0. Cleared while cleanups run.
+static SIGNAL: AtomicI32 = AtomicI32::new(0);
+/// Whether any signal arrived during this run.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// An action that undoes a half-finished step, e.g. removing a new worktree.
+type Cleanup = Box<dyn FnOnce(&Repo) -> Result<()>>;
+
+thread_local! {
+    static CLEANUPS: RefCell<Vec<(String, Cleanup)>> = RefCell::new(Vec::new());
+}
+
+/// Error returned by a step that stopped because of SIGINT or SIGTERM.
+#[derive(Debug)]
+pub struct Interrupted;
+
+impl fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Interrupted")
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+extern "C" fn on_signal(signal: libc::c_int) {
+    SIGNAL.store(signal, Ordering::SeqCst);
+    // A second signal means the graceful path is stuck: leave immediately.
+    if INTERRUPTED.swap(true, Ordering::SeqCst) {
+        // SAFETY: `_exit` is async-signal-safe.
+        unsafe { libc::_exit(EXIT_CODE) };
+    }
+}
+
+/// Install the SIGINT and SIGTERM handlers.
+///
+/// The handler only records the signal; the runner forwards it to the
+/// running child and commands stop at their next step.
+pub fn install() {
+    let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    // SAFETY: the handler only touches atomics and calls `_exit`.
+    unsafe {
+        libc::signal(libc::SIGINT, handler);
+        libc::signal(libc::SIGTERM, handler);
+    }
+}
+
+/// The signal received, if any.
+pub fn received() -> Option<i32> {
+    match SIGNAL.load(Ordering::SeqCst) {
+        0 => None,
+        signal => Some(signal),
+    }
+}
+
+/// Whether the run was interrupted, even if cleanups have run since.
+pub fn was_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Fail with [`Interrupted`] once a signal has been received.
+pub fn check() -> Result<()> {
+    match received() {
+        Some(_) => Err(Interrupted.into()),
+        None => Ok(()),
+    }
+}
+
+/// Forward `signal` to a child's process group, then kill the group if it
+/// has not exited within the grace period.
+pub fn terminate(child: &mut Child, signal: i32) -> io::Result<()> {
+    let group = -(child.id() as libc::pid_t);
+    // SAFETY: plain syscall; the child leads its own process group.
+    unsafe { libc::kill(group, signal) };
+
+    let started = Instant::now();
+    while started.elapsed() < GRACE_PERIOD {
+        if child.try_wait()?.is_some() {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    warn(&format!("Child did not exit within {}s, killing it", GRACE_PERIOD.as_secs()));
+    // SAFETY: as above.
+    unsafe { libc::kill(group, libc::SIGKILL) };
+    child.wait().map(|_| ())
+}
+
+/// Kill a child's whole process group immediately.
+pub fn kill(child: &mut Child) -> io::Result<()> {
+    // SAFETY: plain syscall; the child leads its own process group.
+    unsafe { libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL) };
+    child.wait().map(|_| ())
+}
+
+/// Register an action to run if the current command is interrupted.
+///
+/// Actions run in reverse order of registration, after the interrupted
+/// step has stopped.
+pub fn on_abort(description: &str, action: impl FnOnce(&Repo) -> Result<()> + 'static) {
+    CLEANUPS.with(|c| c.borrow_mut().push((description.to_string(), Box::new(action))));
+}
+
+/// Drop all registered actions, e.g. after the command finished.
+pub fn clear_cleanups() {
+    CLEANUPS.with(|c| c.borrow_mut().clear());
+}
+
+/// Run the registered actions, newest first.
+///
+/// The signal is cleared first so the actions can run commands; another
+/// signal exits immediately.
+pub fn run_cleanups(repo: &Repo) {
+    SIGNAL.store(0, Ordering::SeqCst);
+    let cleanups = CLEANUPS.with(|c| std::mem::take(&mut *c.borrow_mut()));
+    for (description, action) in cleanups.into_iter().rev() {
+        info(&format!("Cleaning up: {description}"));
+        if let Err(e) = action(repo) {
+            warn(&format!("Cleanup failed ({description}): {e:#}"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::forge::fake::FakeForge;
+    use crate::runner::fake::FakeRunner;
+
+    #[test]
+    fn test_cleanups_run_newest_first() {
+        let runner = FakeRunner::new();
+        let forge = FakeForge::new();
+        let repo = Repo::for_test("/repo", &runner, &forge);
+        let order = Rc::new(RefCell::new(Vec::new()));
+
+        for name in ["worktree", "supabase"] {
+            let order = Rc::clone(&order);
+            on_abort(name, move |_| {
+                order.borrow_mut().push(name);
+                Ok(())
+            });
+        }
+        run_cleanups(&repo);
+        run_cleanups(&repo);
+
+        assert_eq!(*order.borrow(), vec!["supabase", "worktree"]);
+    }
+}
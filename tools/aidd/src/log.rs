@@ -0,0 +1,172 @@
+use std::fs::{self, File};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use jiff::Timestamp;
+
+use crate::clock::Clock;
+
+/// Directory for per-run log files, relative to the repo root.
+pub const LOG_DIR: &str = ".aidd/logs";
+
+/// Message severity, from most to least important.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    /// External commands with their duration, exit code and stderr.
+    Debug,
+    /// Everything, including the stdout of external commands.
+    Trace,
+}
+
+impl Level {
+    /// The console level for `--quiet` and the number of `-v` flags.
+    pub fn from_flags(quiet: bool, verbose: u8) -> Self {
+        match (quiet, verbose) {
+            (true, _) => Level::Warn,
+            (false, 0) => Level::Info,
+            (false, 1) => Level::Debug,
+            (false, _) => Level::Trace,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        }
+    }
+}
+
+static CONSOLE_LEVEL: AtomicU8 = AtomicU8::new(Level::Info as u8);
+static LOG_FILE: Mutex<Option<File>> = Mutex::new(None);
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
+/// Set the most verbose level printed to stderr.
+pub fn set_level(level: Level) {
+    CONSOLE_LEVEL.store(level as u8, Ordering::Relaxed);
+    STARTED.get_or_init(Instant::now);
+}
+
+/// Whether messages at `level` reach stderr.
+pub fn enabled(level: Level) -> bool {
+    level as u8 <= CONSOLE_LEVEL.load(Ordering::Relaxed)
+}
+
+/// Start writing every message, at every level, to
+/// `.aidd/logs/<timestamp>-<command>.log` under `root`.
+/// Runs started in the same second get a `-2`, `-3`, … suffix.
+pub fn open_file(
+    root: &Path,
+    clock: &dyn Clock,
+    command: &str,
+    args: &[String],
+) -> Result<PathBuf> {
+    let dir = root.join(LOG_DIR);
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+    // The log directory ignores its own contents so logs never get committed.
+    let ignore = dir.join(".gitignore");
+    if !ignore.exists() {
+        fs::write(&ignore, "*\n")
+            .with_context(|| format!("Failed to write {}", ignore.display()))?;
+    }
+
+    let stem = format!("{}-{command}", utc_timestamp(clock.now()));
+    let (path, mut file) = create_unique(&dir, &stem)?;
+    writeln!(file, "aidd {}", args.join(" "))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    *LOG_FILE.lock().unwrap_or_else(|e| e.into_inner()) = Some(file);
+    Ok(path)
+}
+
+/// Create `<stem>.log` in `dir`, or `<stem>-<n>.log` with the first free
+/// `n` from 2 when it is taken.
+fn create_unique(dir: &Path, stem: &str) -> Result<(PathBuf, File)> {
+    for n in 1.. {
+        let name = if n == 1 { format!("{stem}.log") } else { format!("{stem}-{n}.log") };
+        let path = dir.join(name);
+        match File::create_new(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to create {}", path.display()))
+            }
+        }
+    }
+    unreachable!()
+}
+
+/// Print a message to stderr if `level` is enabled, and always to the log file.
+pub fn log(level: Level, msg: &str) {
+    if enabled(level) {
+        eprintln!("{}: {msg}", level.label());
+    }
+    to_file(level, msg);
+}
+
+/// Write a message to the log file only.
+pub fn to_file(level: Level, msg: &str) {
+    if let Some(file) = LOG_FILE.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+        let elapsed = STARTED.get_or_init(Instant::now).elapsed().as_secs_f64();
+        // Logging must never fail the command it describes.
+        let _ = writeln!(file, "[{elapsed:8.3}s] {}: {msg}", level.label());
+    }
+}
+
+/// Format a time as `YYYYMMDDTHHMMSSZ` in UTC.
+fn utc_timestamp(time: Timestamp) -> String {
+    time.strftime("%Y%m%dT%H%M%SZ").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_from_flags() {
+        assert_eq!(Level::from_flags(false, 0), Level::Info);
+        assert_eq!(Level::from_flags(false, 1), Level::Debug);
+        assert_eq!(Level::from_flags(false, 3), Level::Trace);
+        assert_eq!(Level::from_flags(true, 2), Level::Warn);
+    }
+
+    #[test]
+    fn test_utc_timestamp() {
+        assert_eq!(utc_timestamp(Timestamp::UNIX_EPOCH), "19700101T000000Z");
+        let leap_day = Timestamp::from_second(1_709_217_296).unwrap();
+        assert_eq!(utc_timestamp(leap_day), "20240229T143456Z");
+    }
+
+    #[test]
+    fn test_create_unique_adds_a_suffix() {
+        let dir = std::env::temp_dir().join(format!("aidd-log-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let names: Vec<String> = (0..3)
+            .map(|_| {
+                let (path, _) = create_unique(&dir, "20261019T093000Z-wt-ensure").unwrap();
+                path.file_name().unwrap().to_string_lossy().into_owned()
+            })
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "20261019T093000Z-wt-ensure.log",
+                "20261019T093000Z-wt-ensure-2.log",
+                "20261019T093000Z-wt-ensure-3.log"
+            ]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,36 +1,153 @@
 mod cli;
+mod clock;
 mod commands;
+mod config;
+mod conventional;
+mod deps;
+mod error;
+mod estimate;
+mod forge;
 mod frontmatter;
 mod helpers;
+mod interrupt;
+mod log;
+mod repo;
+mod runner;
+mod tasks;
 
 use std::process;
+use std::rc::Rc;
 
 use anyhow::Result;
 use clap::Parser;
 
-use cli::{Cli, Commands, IssueAction, PrAction, WtAction};
+use clock::{Clock, SystemClock};
+use cli::{Cli, Commands, ErrorFormat, IssueAction, PlanAction, PrAction, WtAction};
+use config::Config;
+use error::Report;
+use repo::Repo;
+use forge::dry_run::DryRunForge;
+use forge::offline::OfflineForge;
+use runner::dry_run::{DryRunRunner, Plan};
+use runner::policy::Policies;
+use runner::{CommandRunner, Style, SystemRunner};
 
 fn main() {
     let cli = Cli::parse();
+    log::set_level(log::Level::from_flags(cli.quiet, cli.verbose));
+    interrupt::install();
 
-    if let Err(err) = run(cli) {
-        eprintln!("ERROR: {err:#}");
-        process::exit(1);
+    let error_format = cli.error_format;
+    let result = match run(cli) {
+        _ if interrupt::was_interrupted() => Err(interrupt::Interrupted.into()),
+        result => result,
+    };
+    if let Err(err) = result {
+        let report = Report::new(&err);
+        match error_format {
+            ErrorFormat::Text => {
+                log::log(log::Level::Error, &report.message);
+                if let Some(hint) = &report.hint {
+                    log::log(log::Level::Info, &format!("Hint: {hint}"));
+                }
+            }
+            ErrorFormat::Json => {
+                eprintln!("{}", report.to_json());
+                log::to_file(log::Level::Error, &report.message);
+            }
+        }
+        process::exit(report.exit_code);
     }
 }
 
 fn run(cli: Cli) -> Result<()> {
-    match cli.command {
+    let style = Style::detect(cli.error_format == ErrorFormat::Json);
+    let root = helpers::repo_root(&SystemRunner::new(Policies::default(), style));
+    let clock: Rc<dyn Clock> = Rc::new(SystemClock);
+    // A dry run must not touch the repository, not even with a log file.
+    if !cli.dry_run {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        match log::open_file(&root, clock.as_ref(), cli.command.name(), &args) {
+            Ok(path) => helpers::debug(&format!("Logging to {}", path.display())),
+            Err(e) => helpers::warn(&format!("Failed to open log file: {e:#}")),
+        }
+    }
+    let config = Config::load(&root)?;
+    let system: Rc<dyn CommandRunner> =
+        Rc::new(SystemRunner::new(Policies::new(config.commands.clone()), style));
+    let mut forge =
+        forge::from_config(&config, root.clone(), Rc::clone(&system), Rc::clone(&clock));
+    // Planning from a file or stdin must work without the network.
+    if let Commands::Issue { action: IssueAction::Plan { issue, from_file, stdin, .. } } =
+        &cli.command
+    {
+        if from_file.is_some() || *stdin {
+            let issue = forge::offline::read_issue(*issue, from_file.as_deref())?;
+            forge = Box::new(OfflineForge::new(issue));
+        }
+    }
+
+    // In a dry run, side effects are recorded in the plan; the forge keeps
+    // the real runner so its queries still reach the backend.
+    let plan = cli.dry_run.then(|| Rc::new(Plan::default()));
+    let (runner, forge): (Rc<dyn CommandRunner>, Box<dyn forge::Forge>) = match &plan {
+        Some(plan) => (
+            Rc::new(DryRunRunner::new(system, Rc::clone(plan))),
+            Box::new(DryRunForge::new(forge, Rc::clone(plan))),
+        ),
+        None => (system, forge),
+    };
+    let time_zone = clock::time_zone(config.display.timezone.as_deref())?;
+    let repo = Repo { root, runner: runner.as_ref(), forge: forge.as_ref(), clock: clock.as_ref() };
+    let repo = &repo;
+
+    let result = match cli.command {
         Commands::Wt { action } => match action {
-            WtAction::Ensure { prefix, issue, summary } => commands::wt::ensure(&prefix, issue, &summary),
-            WtAction::Remove { issue } => commands::wt::remove(issue),
+            WtAction::Ensure { prefix, issue, summary, task } => {
+                commands::wt::ensure(repo, &prefix, issue, &summary, task)
+            }
+            WtAction::Remove { issue } => commands::wt::remove(repo, issue),
         },
         Commands::Issue { action } => match action {
-            IssueAction::Plan { issue } => commands::issue::plan(issue),
+            IssueAction::Create { from, plan } => commands::issue::create(repo, &from, plan),
+            IssueAction::Plan { issue, sync, recursive, .. } => {
+                commands::issue::plan_issues(repo, issue, sync, recursive)
+            }
+        },
+        Commands::Plan { action } => match action {
+            PlanAction::Estimate { issue, task, size } => {
+                commands::plan::estimate(repo, issue, task, &size)
+            }
+            PlanAction::Graph { issue, format } => commands::plan::graph(repo, issue, format),
         },
         Commands::Pr { action } => match action {
-            PrAction::Create { issue } => commands::pr::create(issue),
+            PrAction::Create { issue, task, skip_review } => {
+                commands::pr::create(repo, issue, task, skip_review)
+            }
+            PrAction::Merge { issue, task, method } => {
+                commands::pr::merge(repo, issue, task, method)
+            }
+            PrAction::Restack { issue } => commands::pr::restack(repo, issue),
         },
-        Commands::Status => commands::status::show(),
+        Commands::Status => commands::status::show(repo, &time_zone),
+        Commands::Commit { issue, task, message } => {
+            commands::commit::run(repo, issue, task, message)
+        }
+        Commands::Changelog { since, version, output } => {
+            commands::changelog::generate(repo, since, version, output)
+        }
+        Commands::ReviewCheck { issue, task } => commands::review::run(repo, issue, task),
+    };
+
+    // Undo half-finished steps only if the command stopped because of the signal.
+    if result.is_err() && interrupt::received().is_some() {
+        interrupt::run_cleanups(repo);
+        return result;
+    }
+    interrupt::clear_cleanups();
+
+    if let Some(plan) = plan {
+        plan.print();
     }
+    result
 }
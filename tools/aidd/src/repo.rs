@@ -0,0 +1,190 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+
+use crate::clock::Clock;
+use crate::error::{Error, ErrorKind};
+use crate::forge::Forge;
+use crate::runner::CommandRunner;
+use crate::tasks::read_task;
+
+/// The repository a command works on, with the tools to act on it.
+///
+/// Commands take a `Repo` instead of spawning processes, calling the forge
+/// or reading the time directly, so tests can substitute a `FakeRunner`, a
+/// `FakeForge` and a `FixedClock`.
+pub struct Repo<'a> {
+    /// Repository root; worktrees live under `.worktrees/` here.
+    pub root: PathBuf,
+    pub runner: &'a dyn CommandRunner,
+    pub forge: &'a dyn Forge,
+    pub clock: &'a dyn Clock,
+}
+
+#[cfg(test)]
+impl<'a> Repo<'a> {
+    /// A repository at `root` on the system clock, for tests.
+    pub fn for_test(
+        root: impl Into<PathBuf>,
+        runner: &'a dyn CommandRunner,
+        forge: &'a dyn Forge,
+    ) -> Self {
+        Self { root: root.into(), runner, forge, clock: &crate::clock::SystemClock }
+    }
+}
+
+impl Repo<'_> {
+    /// Run git in the repository root.
+    pub fn git(&self, args: &[&str]) -> Result<String> {
+        self.runner.run("git", args, Some(&self.root))
+    }
+
+    /// Generate the worktree path for an issue.
+    pub fn worktree_path(&self, issue: u32, summary: &str) -> PathBuf {
+        self.root.join(format!(".worktrees/{issue}-{summary}"))
+    }
+
+    /// The features directory of an issue: `features/{issue}`.
+    pub fn features_dir(&self, issue: u32) -> PathBuf {
+        self.root.join(format!("features/{issue}"))
+    }
+
+    /// The PLAN.md of an issue.
+    pub fn plan_file(&self, issue: u32) -> PathBuf {
+        self.features_dir(issue).join("PLAN.md")
+    }
+
+    /// The TASK.md of a task of an issue.
+    pub fn task_file(&self, issue: u32, task: u32) -> PathBuf {
+        self.features_dir(issue).join(format!("{task}/TASK.md"))
+    }
+
+    /// Find the worktree path for an issue by scanning `.worktrees/`.
+    ///
+    /// Returns the first directory whose name starts with `{issue}-`.
+    pub fn find_worktree_for_issue(&self, issue: u32) -> Option<PathBuf> {
+        let prefix = format!("{issue}-");
+        let dir = self.root.join(".worktrees");
+        self.runner
+            .list_dir(&dir)
+            .ok()?
+            .into_iter()
+            .find(|name| name.starts_with(&prefix))
+            .map(|name| dir.join(name))
+    }
+
+    /// The worktree to work on for an issue, or for one of its tasks.
+    ///
+    /// A task with a recorded `branchName` resolves to the worktree of that
+    /// branch, so stacked tasks of one issue are not mixed up. Only without
+    /// one does the issue's worktree, then the current directory, serve.
+    pub fn work_dir(&self, issue: u32, task: Option<u32>) -> Result<PathBuf> {
+        let branch = match task {
+            Some(task) => read_task(self, issue, task)?.branch_name,
+            None => None,
+        };
+        if let Some(branch) = branch {
+            return self.find_worktree_for_branch(&branch).with_context(|| {
+                Error::new(ErrorKind::NotFound, format!("No worktree has {branch} checked out"))
+                    .with_hint(format!(
+                        "Run `aidd wt ensure ... --task {}` to create it",
+                        task.unwrap_or_default()
+                    ))
+            });
+        }
+        Ok(self
+            .find_worktree_for_issue(issue)
+            .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| ".".into())))
+    }
+
+    /// Find the worktree that has the given branch checked out.
+    pub fn find_worktree_for_branch(&self, branch: &str) -> Option<PathBuf> {
+        let porcelain = self.git(&["worktree", "list", "--porcelain"]).ok()?;
+        parse_worktree_list(&porcelain)
+            .into_iter()
+            .find(|(_, b)| b == branch)
+            .map(|(path, _)| path)
+    }
+
+    /// Check whether a local branch exists.
+    pub fn local_branch_exists(&self, branch: &str) -> bool {
+        self.git(&["branch", "--list", branch]).map(|out| !out.is_empty()).unwrap_or(false)
+    }
+
+    /// Find all local branches matching `*/{issue}-*`.
+    pub fn find_branches_for_issue(&self, issue: u32) -> Vec<String> {
+        let pattern = format!("*/{issue}-*");
+        self.git(&["branch", "--list", "--format=%(refname:short)", &pattern])
+            .map(|out| {
+                out.lines()
+                    .map(|l| l.trim().to_string())
+                    .filter(|l| !l.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Parse `git worktree list --porcelain` into `(path, branch)` pairs.
+///
+/// Worktrees with a detached HEAD are omitted.
+fn parse_worktree_list(porcelain: &str) -> Vec<(PathBuf, String)> {
+    let mut result = Vec::new();
+    let mut current: Option<PathBuf> = None;
+    for line in porcelain.lines() {
+        if let Some(path) = line.strip_prefix("worktree ") {
+            current = Some(PathBuf::from(path));
+        } else if let Some(branch) = line.strip_prefix("branch refs/heads/") {
+            if let Some(path) = current.take() {
+                result.push((path, branch.to_string()));
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forge::fake::FakeForge;
+    use crate::runner::fake::FakeRunner;
+
+    #[test]
+    fn test_parse_worktree_list() {
+        let porcelain = "worktree /repo\nHEAD abc\nbranch refs/heads/main\n\n\
+            worktree /repo/.worktrees/3-add\nHEAD def\nbranch refs/heads/feat/3-add\n\n\
+            worktree /repo/.worktrees/detached\nHEAD 123\ndetached\n";
+        let list = parse_worktree_list(porcelain);
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0], (PathBuf::from("/repo"), "main".to_string()));
+        assert_eq!(
+            list[1],
+            (PathBuf::from("/repo/.worktrees/3-add"), "feat/3-add".to_string())
+        );
+    }
+
+    #[test]
+    fn test_worktree_path_ends_correctly() {
+        let runner = FakeRunner::new();
+        let forge = FakeForge::new();
+        let repo = Repo::for_test("/repo", &runner, &forge);
+        let path = repo.worktree_path(3, "add-library");
+        assert!(path.ends_with(".worktrees/3-add-library"));
+        assert_eq!(repo.plan_file(1), PathBuf::from("/repo/features/1/PLAN.md"));
+        assert_eq!(repo.task_file(11, 2), PathBuf::from("/repo/features/11/2/TASK.md"));
+    }
+
+    #[test]
+    fn test_git_queries_run_in_root() {
+        let runner = FakeRunner::new()
+            .with_output("git branch --list --format", "feat/3-add\nfix/3-bug\n")
+            .with_output("git branch --list feat/3-add", "  feat/3-add");
+        let forge = FakeForge::new();
+        let repo = Repo::for_test("/repo", &runner, &forge);
+
+        assert_eq!(repo.find_branches_for_issue(3), vec!["feat/3-add", "fix/3-bug"]);
+        assert!(repo.local_branch_exists("feat/3-add"));
+        assert!(!repo.local_branch_exists("feat/4-none"));
+        assert!(runner.calls().iter().all(|c| c.dir.as_deref() == Some(repo.root.as_path())));
+    }
+}
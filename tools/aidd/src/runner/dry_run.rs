@@ -0,0 +1,308 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use anyhow::{Context, Result};
+
+use super::CommandRunner;
+
+/// A side effect that `--dry-run` skipped.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    Command { command: String, dir: Option<PathBuf> },
+    CreateDir(PathBuf),
+    Write(PathBuf),
+    Append(PathBuf),
+    Copy { from: PathBuf, to: PathBuf },
+    /// A mutating forge call, e.g. opening or merging a pull request.
+    Forge(String),
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::Command { command, dir: Some(dir) } => {
+                write!(f, "cd {} && {command}", shell_quote(&dir.to_string_lossy()))
+            }
+            Step::Command { command, dir: None } => f.write_str(command),
+            Step::CreateDir(path) => write!(f, "mkdir -p {}", path.display()),
+            Step::Write(path) => write!(f, "write {}", path.display()),
+            Step::Append(path) => write!(f, "append {}", path.display()),
+            Step::Copy { from, to } => write!(f, "copy {} -> {}", from.display(), to.display()),
+            Step::Forge(description) => write!(f, "forge: {description}"),
+        }
+    }
+}
+
+/// The ordered side effects recorded during a dry run.
+#[derive(Default)]
+pub struct Plan {
+    steps: RefCell<Vec<Step>>,
+}
+
+impl Plan {
+    pub fn record(&self, step: Step) {
+        self.steps.borrow_mut().push(step);
+    }
+
+    /// All recorded steps, in order.
+    pub fn steps(&self) -> Vec<Step> {
+        self.steps.borrow().clone()
+    }
+
+    /// Print the plan to stdout.
+    pub fn print(&self) {
+        let steps = self.steps();
+        println!("Dry run: {} planned side effects", steps.len());
+        for (i, step) in steps.iter().enumerate() {
+            println!("{:>3}. {step}", i + 1);
+        }
+    }
+}
+
+/// Runner for `--dry-run`: records side effects in a [`Plan`] instead of
+/// performing them.
+///
+/// Read-only commands (`git rev-parse`, `git log`, ...) still run through
+/// `inner` so the rest of the plan is computed from the real repository.
+/// Every other command succeeds with empty output.
+///
+/// Worktrees that would be created are not on disk, so paths inside them
+/// are looked up in the checkout the worktree was planned from.
+pub struct DryRunRunner {
+    inner: Rc<dyn CommandRunner>,
+    plan: Rc<Plan>,
+    /// Planned worktrees and the directory `git worktree add` ran in.
+    worktrees: RefCell<Vec<(PathBuf, PathBuf)>>,
+}
+
+impl DryRunRunner {
+    pub fn new(inner: Rc<dyn CommandRunner>, plan: Rc<Plan>) -> Self {
+        Self { inner, plan, worktrees: RefCell::new(Vec::new()) }
+    }
+
+    fn skip(&self, program: &str, args: &[&str], dir: Option<&Path>) {
+        if let (Some(path), Some(dir)) = (planned_worktree(program, args), dir) {
+            self.worktrees.borrow_mut().push((dir.join(path), dir.to_path_buf()));
+        }
+        let command = std::iter::once(program)
+            .chain(args.iter().copied())
+            .map(shell_quote)
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.plan.record(Step::Command { command, dir: dir.map(Path::to_path_buf) });
+    }
+
+    /// Map a path inside a planned worktree to its source checkout.
+    fn resolve(&self, path: &Path) -> PathBuf {
+        self.worktrees
+            .borrow()
+            .iter()
+            .find_map(|(worktree, source)| Some(source.join(path.strip_prefix(worktree).ok()?)))
+            .unwrap_or_else(|| path.to_path_buf())
+    }
+
+    fn planned_write(&self, path: &Path) -> bool {
+        self.plan.steps.borrow().iter().any(|step| match step {
+            Step::CreateDir(p) | Step::Write(p) | Step::Append(p) => p == path,
+            Step::Copy { to, .. } => to == path,
+            _ => false,
+        })
+    }
+}
+
+impl CommandRunner for DryRunRunner {
+    fn run(&self, program: &str, args: &[&str], dir: Option<&Path>) -> Result<String> {
+        if is_read_only(program, args) {
+            return self.inner.run(program, args, dir);
+        }
+        self.skip(program, args, dir);
+        Ok(String::new())
+    }
+
+    fn run_streamed(
+        &self,
+        step: &str,
+        program: &str,
+        args: &[&str],
+        dir: Option<&Path>,
+    ) -> Result<()> {
+        if is_read_only(program, args) {
+            return self.inner.run_streamed(step, program, args, dir);
+        }
+        self.skip(program, args, dir);
+        Ok(())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.planned_write(path) || self.resolve(path).exists()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.resolve(path).is_dir()
+    }
+
+    fn read_file(&self, path: &Path) -> Result<String> {
+        let source = self.resolve(path);
+        std::fs::read_to_string(&source)
+            .with_context(|| format!("Failed to read {}", source.display()))
+    }
+
+    fn list_dir(&self, path: &Path) -> Result<Vec<String>> {
+        super::read_dir_names(&self.resolve(path))
+    }
+
+    fn create_dir_all(&self, path: &Path) -> Result<()> {
+        if !path.is_dir() {
+            self.plan.record(Step::CreateDir(path.to_path_buf()));
+        }
+        Ok(())
+    }
+
+    fn write_file(&self, path: &Path, _contents: &str) -> Result<()> {
+        self.plan.record(Step::Write(path.to_path_buf()));
+        Ok(())
+    }
+
+    fn append_file(&self, path: &Path, _contents: &str) -> Result<()> {
+        self.plan.record(Step::Append(path.to_path_buf()));
+        Ok(())
+    }
+
+    fn copy_file(&self, from: &Path, to: &Path) -> Result<()> {
+        self.plan.record(Step::Copy { from: from.to_path_buf(), to: to.to_path_buf() });
+        Ok(())
+    }
+}
+
+/// Whether a command only inspects state and is safe to run in a dry run.
+fn is_read_only(program: &str, args: &[&str]) -> bool {
+    match (program, args) {
+        ("git", [sub, rest @ ..]) => match *sub {
+            "rev-parse" | "log" | "show" | "diff" | "status" | "merge-base" | "describe"
+            | "ls-files" | "cat-file" | "for-each-ref" => true,
+            "branch" => matches!(rest.first(), Some(&"--list" | &"--show-current")),
+            "worktree" => rest.first() == Some(&"list"),
+            "remote" => rest.first() == Some(&"get-url"),
+            "config" => rest.first() == Some(&"--get"),
+            _ => false,
+        },
+        ("supabase", _) => args.contains(&"status"),
+        _ => false,
+    }
+}
+
+/// The path argument of `git worktree add [-b <branch>] <path> ...`.
+fn planned_worktree<'a>(program: &str, args: &[&'a str]) -> Option<&'a str> {
+    let ["worktree", "add", rest @ ..] = args else {
+        return None;
+    };
+    if program != "git" {
+        return None;
+    }
+    let mut rest = rest.iter();
+    while let Some(arg) = rest.next() {
+        match *arg {
+            "-b" | "-B" => {
+                rest.next();
+            }
+            flag if flag.starts_with('-') => {}
+            path => return Some(path),
+        }
+    }
+    None
+}
+
+/// Quote an argument for display the way a POSIX shell would need it.
+fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::fake::FakeRunner;
+
+    fn dry_run(inner: FakeRunner) -> (Rc<FakeRunner>, Rc<Plan>, DryRunRunner) {
+        let inner = Rc::new(inner);
+        let plan = Rc::new(Plan::default());
+        let runner = DryRunRunner::new(inner.clone(), Rc::clone(&plan));
+        (inner, plan, runner)
+    }
+
+    #[test]
+    fn test_read_only_commands_run() {
+        let (inner, plan, runner) =
+            dry_run(FakeRunner::new().with_output("git rev-parse", "feat/1-x"));
+
+        let branch = runner.run("git", &["rev-parse", "--abbrev-ref", "HEAD"], None).unwrap();
+        assert_eq!(branch, "feat/1-x");
+        runner.run("git", &["branch", "--list", "feat/1-x"], None).unwrap();
+        assert_eq!(inner.commands().len(), 2);
+        assert!(plan.steps().is_empty());
+    }
+
+    #[test]
+    fn test_mutating_commands_are_recorded() {
+        let (inner, plan, runner) = dry_run(FakeRunner::new());
+        let dir = Path::new("/repo");
+
+        runner.run("git", &["push", "-u", "origin", "feat/1-x"], Some(dir)).unwrap();
+        runner.run_streamed("supabase start", "supabase", &["start"], None).unwrap();
+        runner.run("git", &["commit", "-m", "feat: it's done"], None).unwrap();
+
+        assert!(inner.calls().is_empty());
+        let lines: Vec<String> = plan.steps().iter().map(Step::to_string).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "cd /repo && git push -u origin feat/1-x",
+                "supabase start",
+                "git commit -m 'feat: it'\\''s done'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_file_operations_are_recorded() {
+        let dir = std::env::temp_dir().join(format!("aidd-dry-run-{}", std::process::id()));
+        let (_, plan, runner) = dry_run(FakeRunner::new());
+
+        runner.create_dir_all(&dir).unwrap();
+        runner.write_file(&dir.join("PLAN.md"), "# plan").unwrap();
+        runner.append_file(&dir.join(".env"), "A=1").unwrap();
+
+        assert!(!dir.exists());
+        assert!(runner.exists(&dir.join("PLAN.md")));
+        assert_eq!(plan.steps(), vec![
+            Step::CreateDir(dir.clone()),
+            Step::Write(dir.join("PLAN.md")),
+            Step::Append(dir.join(".env")),
+        ]);
+    }
+
+    #[test]
+    fn test_planned_worktree_reads_from_source() {
+        let root = std::env::temp_dir().join(format!("aidd-dry-run-wt-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("config.toml"), "port = 1").unwrap();
+        let (_, _, runner) = dry_run(FakeRunner::new());
+        let wt = root.join(".worktrees/1-x");
+
+        let wt_str = wt.to_string_lossy();
+        let args = ["worktree", "add", "-b", "feat/1-x", &wt_str, "main"];
+        runner.run("git", &args, Some(&root)).unwrap();
+
+        assert!(runner.exists(&wt.join("config.toml")));
+        assert_eq!(runner.read_file(&wt.join("config.toml")).unwrap(), "port = 1");
+        assert!(!runner.exists(&wt.join("missing.toml")));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
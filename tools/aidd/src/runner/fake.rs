@@ -0,0 +1,169 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use super::CommandRunner;
+use crate::error::{self, Error};
+
+/// A command recorded by [`FakeRunner`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Call {
+    /// Program and arguments joined by spaces, e.g. `git worktree add ...`.
+    pub command: String,
+    pub dir: Option<PathBuf>,
+    /// Whether the command was run with [`CommandRunner::run_streamed`].
+    pub streamed: bool,
+}
+
+/// Scripted runner for tests.
+///
+/// Every invocation is recorded. Its outcome comes from the first scripted
+/// reply whose prefix matches the command line; unmatched commands succeed
+/// with empty output.
+///
+/// Files are kept in memory: writes never reach the disk, and reads see
+/// the files given with [`FakeRunner::with_file`] or written since, then
+/// the real filesystem.
+#[derive(Default)]
+pub struct FakeRunner {
+    replies: Vec<(String, Result<String, String>)>,
+    calls: RefCell<Vec<Call>>,
+    files: RefCell<BTreeMap<PathBuf, String>>,
+}
+
+impl FakeRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer commands starting with `prefix` with `stdout`.
+    pub fn with_output(mut self, prefix: &str, stdout: &str) -> Self {
+        self.replies.push((prefix.to_string(), Ok(stdout.to_string())));
+        self
+    }
+
+    /// Fail commands starting with `prefix` with `stderr`.
+    pub fn with_failure(mut self, prefix: &str, stderr: &str) -> Self {
+        self.replies.push((prefix.to_string(), Err(stderr.to_string())));
+        self
+    }
+
+    /// Serve `contents` as the file at `path`.
+    pub fn with_file(self, path: impl Into<PathBuf>, contents: &str) -> Self {
+        self.files.borrow_mut().insert(path.into(), contents.to_string());
+        self
+    }
+
+    /// The in-memory contents of the file at `path`, if written.
+    pub fn file(&self, path: &Path) -> Option<String> {
+        self.files.borrow().get(path).cloned()
+    }
+
+    /// All recorded invocations, in order.
+    pub fn calls(&self) -> Vec<Call> {
+        self.calls.borrow().clone()
+    }
+
+    /// Command lines of all recorded invocations, in order.
+    pub fn commands(&self) -> Vec<String> {
+        self.calls.borrow().iter().map(|c| c.command.clone()).collect()
+    }
+
+    fn record(
+        &self,
+        program: &str,
+        args: &[&str],
+        dir: Option<&Path>,
+        streamed: bool,
+    ) -> Result<String> {
+        let command =
+            std::iter::once(program).chain(args.iter().copied()).collect::<Vec<_>>().join(" ");
+        self.calls.borrow_mut().push(Call {
+            command: command.clone(),
+            dir: dir.map(Path::to_path_buf),
+            streamed,
+        });
+        match self.replies.iter().find(|(prefix, _)| command.starts_with(prefix.as_str())) {
+            Some((_, Ok(stdout))) => Ok(stdout.clone()),
+            Some((_, Err(stderr))) => Err(Error::new(
+                error::classify_output(stderr),
+                format!("Command '{command}' failed (exit 1):\n{stderr}"),
+            )
+            .into()),
+            None => Ok(String::new()),
+        }
+    }
+}
+
+impl CommandRunner for FakeRunner {
+    fn run(&self, program: &str, args: &[&str], dir: Option<&Path>) -> Result<String> {
+        self.record(program, args, dir, false)
+    }
+
+    fn run_streamed(
+        &self,
+        _step: &str,
+        program: &str,
+        args: &[&str],
+        dir: Option<&Path>,
+    ) -> Result<()> {
+        self.record(program, args, dir, true).map(|_| ())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files.borrow().keys().any(|file| file.starts_with(path)) || path.exists()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.files.borrow().keys().any(|file| file != path && file.starts_with(path))
+            || path.is_dir()
+    }
+
+    fn read_file(&self, path: &Path) -> Result<String> {
+        match self.file(path) {
+            Some(contents) => Ok(contents),
+            None => std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    fn list_dir(&self, path: &Path) -> Result<Vec<String>> {
+        let mut names: Vec<String> = self
+            .files
+            .borrow()
+            .keys()
+            .filter_map(|file| file.strip_prefix(path).ok()?.iter().next())
+            .map(|name| name.to_string_lossy().into_owned())
+            .collect();
+        if path.is_dir() {
+            names.extend(super::read_dir_names(path)?);
+        } else if names.is_empty() {
+            anyhow::bail!("Failed to read {}", path.display());
+        }
+        names.sort();
+        names.dedup();
+        Ok(names)
+    }
+
+    fn create_dir_all(&self, _path: &Path) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_file(&self, path: &Path, contents: &str) -> Result<()> {
+        self.files.borrow_mut().insert(path.to_path_buf(), contents.to_string());
+        Ok(())
+    }
+
+    fn append_file(&self, path: &Path, contents: &str) -> Result<()> {
+        let mut existing = self.read_file(path).unwrap_or_default();
+        existing.push_str(contents);
+        self.write_file(path, &existing)
+    }
+
+    fn copy_file(&self, from: &Path, to: &Path) -> Result<()> {
+        let contents = self.read_file(from)?;
+        self.write_file(to, &contents)
+    }
+}
@@ -0,0 +1,389 @@
+pub mod dry_run;
+#[cfg(test)]
+pub mod fake;
+pub mod policy;
+mod stream;
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+
+use crate::error::{self, Error, ErrorKind};
+use crate::interrupt::{self, Interrupted};
+use crate::log::{self, Level};
+use policy::Policies;
+pub use stream::Style;
+use stream::Tail;
+
+/// Runs external programs (git, bun, supabase, ...) and writes files.
+///
+/// Commands never spawn processes or write files themselves; they go through
+/// this trait so tests can script the outcome of each invocation and assert
+/// on the calls, and `--dry-run` can record them instead.
+///
+/// The file operations act on the real filesystem unless overridden.
+pub trait CommandRunner {
+    /// Run a command and return its trimmed stdout. Fails on a non-zero exit.
+    fn run(&self, program: &str, args: &[&str], dir: Option<&Path>) -> Result<String>;
+
+    /// Run a long-running command, forwarding its output live as `step`.
+    ///
+    /// On failure the error includes the last lines of output.
+    fn run_streamed(
+        &self,
+        step: &str,
+        program: &str,
+        args: &[&str],
+        dir: Option<&Path>,
+    ) -> Result<()>;
+
+    /// Check whether a path exists.
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    /// Check whether a path is a directory.
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    /// Read a file to a string.
+    fn read_file(&self, path: &Path) -> Result<String> {
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))
+    }
+
+    /// Names of the entries of a directory, sorted.
+    fn list_dir(&self, path: &Path) -> Result<Vec<String>> {
+        read_dir_names(path)
+    }
+
+    /// Create a directory and all of its parents.
+    fn create_dir_all(&self, path: &Path) -> Result<()> {
+        fs::create_dir_all(path)
+            .with_context(|| format!("Failed to create directory: {}", path.display()))
+    }
+
+    /// Write a file, replacing its contents.
+    fn write_file(&self, path: &Path, contents: &str) -> Result<()> {
+        fs::write(path, contents).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Append to a file, creating it if missing.
+    fn append_file(&self, path: &Path, contents: &str) -> Result<()> {
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(contents.as_bytes()))
+            .with_context(|| format!("Failed to append to {}", path.display()))
+    }
+
+    /// Copy a file.
+    fn copy_file(&self, from: &Path, to: &Path) -> Result<()> {
+        fs::copy(from, to)
+            .map(|_| ())
+            .with_context(|| format!("Failed to copy {} to {}", from.display(), to.display()))
+    }
+}
+
+/// Names of the entries of a directory on disk, sorted.
+fn read_dir_names(path: &Path) -> Result<Vec<String>> {
+    let mut names: Vec<String> = fs::read_dir(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?
+        .filter_map(|entry| Some(entry.ok()?.file_name().to_string_lossy().into_owned()))
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+/// Runner that spawns real processes.
+///
+/// Each command runs under the [`policy::Policy`] resolved for it: it is killed
+/// once it exceeds its timeout, and timeouts and transient failures are
+/// retried with backoff while attempts remain. Commands that are not
+/// idempotent (see [`policy::is_idempotent`]) are retried only when the
+/// failure shows the request never left the machine.
+/// Every attempt is traced at debug level with its working directory,
+/// duration and exit code; captured stderr is logged at debug level and
+/// captured stdout at trace level.
+pub struct SystemRunner {
+    policies: Policies,
+    style: Style,
+}
+
+/// What happens to a command's output.
+#[derive(Clone, Copy)]
+enum Mode<'a> {
+    /// Collect stdout and stderr; stdout is returned.
+    Capture,
+    /// Forward both streams live under the given step name, in `Style`.
+    Stream(&'a str, Style),
+}
+
+/// Why a single attempt of a command did not succeed.
+enum Failure {
+    TimedOut(Duration),
+    /// Non-zero exit with stderr (captured) or the output tail (streamed).
+    Exit { code: i32, output: String },
+}
+
+impl Failure {
+    /// Whether another attempt is safe and may succeed; `idempotent` tells
+    /// whether the command may be repeated after it possibly took effect.
+    fn is_retryable(&self, idempotent: bool) -> bool {
+        match self {
+            Failure::TimedOut(_) => idempotent,
+            Failure::Exit { output, .. } if idempotent => policy::is_transient(output),
+            Failure::Exit { output, .. } => policy::is_unsent(output),
+        }
+    }
+
+    fn reason(&self) -> String {
+        match self {
+            Failure::TimedOut(timeout) => format!("timed out after {}s", timeout.as_secs()),
+            Failure::Exit { code, .. } => format!("failed (exit {code})"),
+        }
+    }
+}
+
+impl SystemRunner {
+    pub fn new(policies: Policies, style: Style) -> Self {
+        Self { policies, style }
+    }
+
+    fn command(program: &str, args: &[&str], dir: Option<&Path>) -> Command {
+        let dir_note = dir.map(|d| format!(" (in {})", d.display())).unwrap_or_default();
+        log::log(Level::Debug, &format!("$ {program} {}{dir_note}", args.join(" ")));
+        let mut cmd = Command::new(program);
+        cmd.args(args);
+        if let Some(d) = dir {
+            cmd.current_dir(d);
+        }
+        cmd
+    }
+
+    /// Run a command under its policy, retrying failed attempts.
+    fn execute(
+        &self,
+        program: &str,
+        args: &[&str],
+        dir: Option<&Path>,
+        mode: Mode,
+    ) -> Result<String> {
+        let policy = self.policies.resolve(program, args);
+        let idempotent = policy::is_idempotent(program, args);
+        let line = format!("{program} {}", args.join(" "));
+        let mut attempt = 1;
+        loop {
+            let failure = match Self::attempt(program, args, dir, mode, policy.timeout)? {
+                Ok(stdout) => return Ok(stdout),
+                Err(failure) => failure,
+            };
+
+            if attempt < policy.attempts && failure.is_retryable(idempotent) {
+                let delay = policy.delay(attempt);
+                log::log(
+                    Level::Warn,
+                    &format!(
+                        "'{line}' {}; retrying in {}s (attempt {}/{})",
+                        failure.reason(),
+                        delay.as_secs(),
+                        attempt + 1,
+                        policy.attempts
+                    ),
+                );
+                thread::sleep(delay);
+                interrupt::check()?;
+                attempt += 1;
+                continue;
+            }
+
+            let error = match (&failure, mode) {
+                (Failure::TimedOut(_), _) => Error::new(
+                    ErrorKind::Command,
+                    format!("Command '{line}' {}", failure.reason()),
+                ),
+                (Failure::Exit { output, .. }, Mode::Capture) => Error::new(
+                    error::classify_output(output),
+                    format!("Command '{line}' {}:\n{output}", failure.reason()),
+                ),
+                (Failure::Exit { output, .. }, Mode::Stream(..)) => Error::new(
+                    error::classify_output(output),
+                    format!("Command '{line}' {}. Last output:\n{output}", failure.reason()),
+                ),
+            };
+            let error = anyhow::Error::new(error);
+            return Err(if policy.attempts > 1 {
+                error.context(format!("Gave up on '{line}' after {attempt} attempts"))
+            } else {
+                error
+            });
+        }
+    }
+
+    /// Run a command once, killing it if it exceeds `timeout`.
+    ///
+    /// The outer error is for commands that could not be run at all or were
+    /// interrupted; those are not retried.
+    fn attempt(
+        program: &str,
+        args: &[&str],
+        dir: Option<&Path>,
+        mode: Mode,
+        timeout: Option<Duration>,
+    ) -> Result<Result<String, Failure>> {
+        interrupt::check()?;
+        let started = Instant::now();
+        // Children get their own process group so a terminal Ctrl-C reaches
+        // only aidd, which forwards it and can kill the whole group.
+        let mut child = Self::command(program, args, dir)
+            .process_group(0)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => anyhow::Error::new(
+                    Error::new(
+                        ErrorKind::MissingTool,
+                        format!("Failed to execute '{program}'. Is it installed?"),
+                    )
+                    .with_hint(format!("Install `{program}` and make sure it is on PATH")),
+                ),
+                _ => anyhow::Error::new(e).context(format!("Failed to execute '{program}'")),
+            })?;
+
+        let tail = Arc::new(Mutex::new(Tail::default()));
+        let stdout = child.stdout.take().map(|r| drain(r, mode, &tail));
+        let stderr = child.stderr.take().map(|r| drain(r, mode, &tail));
+
+        let wait =
+            wait_for(&mut child, timeout).with_context(|| format!("Failed to wait for '{program}'"))?;
+        let status = match wait {
+            Wait::Exited(status) => status,
+            Wait::TimedOut => {
+                let timeout = timeout.unwrap_or_default();
+                log::log(Level::Debug, &format!("{program} killed after {}s", timeout.as_secs()));
+                return Ok(Err(Failure::TimedOut(timeout)));
+            }
+            Wait::Interrupted(signal) => {
+                log::log(Level::Warn, &format!("Stopped '{program}' after signal {signal}"));
+                return Err(Interrupted.into());
+            }
+        };
+        let join = |reader: Option<JoinHandle<String>>| {
+            reader.and_then(|r| r.join().ok()).unwrap_or_default()
+        };
+        let (stdout, stderr) = (join(stdout), join(stderr));
+
+        Self::trace_exit(program, status, started);
+        Self::trace_output(Level::Debug, "stderr", &stderr);
+        Self::trace_output(Level::Trace, "stdout", &stdout);
+
+        if status.success() {
+            return Ok(Ok(stdout.trim().to_string()));
+        }
+        let output = match mode {
+            Mode::Capture => stderr.trim().to_string(),
+            Mode::Stream(..) => tail.lock().unwrap_or_else(|e| e.into_inner()).join(),
+        };
+        Ok(Err(Failure::Exit { code: status.code().unwrap_or(-1), output }))
+    }
+
+    fn trace_exit(program: &str, status: ExitStatus, started: Instant) {
+        log::log(
+            Level::Debug,
+            &format!(
+                "{program} exited with {} after {:.2}s",
+                status.code().unwrap_or(-1),
+                started.elapsed().as_secs_f64()
+            ),
+        );
+    }
+
+    fn trace_output(level: Level, stream: &str, output: &str) {
+        if !output.trim().is_empty() {
+            log::log(level, &format!("{stream}:\n{}", output.trim_end()));
+        }
+    }
+}
+
+impl CommandRunner for SystemRunner {
+    fn run(&self, program: &str, args: &[&str], dir: Option<&Path>) -> Result<String> {
+        self.execute(program, args, dir, Mode::Capture)
+    }
+
+    fn run_streamed(
+        &self,
+        step: &str,
+        program: &str,
+        args: &[&str],
+        dir: Option<&Path>,
+    ) -> Result<()> {
+        self.execute(program, args, dir, Mode::Stream(step, self.style)).map(|_| ())
+    }
+}
+
+/// Read a child's output stream on a background thread.
+///
+/// Captured output is returned; streamed output is forwarded and kept in
+/// `tail` instead.
+fn drain(
+    reader: impl Read + Send + 'static,
+    mode: Mode,
+    tail: &Arc<Mutex<Tail>>,
+) -> JoinHandle<String> {
+    match mode {
+        Mode::Capture => thread::spawn(move || {
+            let mut reader = reader;
+            let mut buf = Vec::new();
+            let _ = reader.read_to_end(&mut buf);
+            String::from_utf8_lossy(&buf).into_owned()
+        }),
+        Mode::Stream(step, style) => {
+            let forwarder = stream::spawn_forward(reader, step, style, tail);
+            thread::spawn(move || {
+                let _ = forwarder.join();
+                String::new()
+            })
+        }
+    }
+}
+
+/// How waiting for a child ended.
+enum Wait {
+    Exited(ExitStatus),
+    /// Killed after exceeding its timeout.
+    TimedOut,
+    /// Stopped after aidd received this signal.
+    Interrupted(i32),
+}
+
+/// Wait for a child to exit, killing it once `timeout` has passed and
+/// forwarding SIGINT/SIGTERM to it.
+fn wait_for(child: &mut Child, timeout: Option<Duration>) -> io::Result<Wait> {
+    let started = Instant::now();
+    let mut interval = Duration::from_millis(1);
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Wait::Exited(status));
+        }
+        if let Some(signal) = interrupt::received() {
+            interrupt::terminate(child, signal)?;
+            return Ok(Wait::Interrupted(signal));
+        }
+        if timeout.is_some_and(|t| started.elapsed() >= t) {
+            interrupt::kill(child)?;
+            return Ok(Wait::TimedOut);
+        }
+        thread::sleep(interval);
+        interval = (interval * 2).min(Duration::from_millis(50));
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::config::CommandConfig;
+
+/// Timeout and retry behaviour of an external command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Policy {
+    /// Kill the command after this long; `None` waits forever.
+    pub timeout: Option<Duration>,
+    /// Total number of attempts, at least 1.
+    pub attempts: u32,
+    /// Delay before the first retry; doubled for every further retry.
+    pub backoff: Duration,
+}
+
+impl Policy {
+    /// Local steps (installs, Supabase, lint): generous timeout, no retries.
+    pub const LOCAL: Policy = Policy {
+        timeout: Some(Duration::from_secs(15 * 60)),
+        attempts: 1,
+        backoff: Duration::ZERO,
+    };
+
+    /// Network steps (push, fetch, forge CLIs): shorter timeout, two retries.
+    pub const NETWORK: Policy = Policy {
+        timeout: Some(Duration::from_secs(2 * 60)),
+        attempts: 3,
+        backoff: Duration::from_secs(2),
+    };
+
+    /// The default policy for a command line.
+    pub fn default_for(program: &str, args: &[&str]) -> Self {
+        if is_network(program, args) {
+            Policy::NETWORK
+        } else {
+            Policy::LOCAL
+        }
+    }
+
+    /// Delay before retry number `retry` (1-based).
+    pub fn delay(&self, retry: u32) -> Duration {
+        self.backoff.saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+    }
+
+    fn apply(mut self, config: &CommandConfig) -> Self {
+        if let Some(secs) = config.timeout {
+            self.timeout = (secs > 0).then(|| Duration::from_secs(secs));
+        }
+        if let Some(retries) = config.retries {
+            self.attempts = retries.saturating_add(1);
+        }
+        if let Some(secs) = config.backoff {
+            self.backoff = Duration::from_secs(secs);
+        }
+        self
+    }
+}
+
+/// Policies for all commands: the defaults plus the `[commands]` overrides
+/// from `.aidd/config.toml`.
+#[derive(Default)]
+pub struct Policies {
+    overrides: BTreeMap<String, CommandConfig>,
+}
+
+impl Policies {
+    pub fn new(overrides: BTreeMap<String, CommandConfig>) -> Self {
+        Self { overrides }
+    }
+
+    /// Resolve the policy for a command line.
+    ///
+    /// The override with the longest prefix matching whole words of the
+    /// command line wins; e.g. `"git push"` beats `"git"` for `git push -u`.
+    pub fn resolve(&self, program: &str, args: &[&str]) -> Policy {
+        let words: Vec<&str> = std::iter::once(program).chain(args.iter().copied()).collect();
+        let best = self
+            .overrides
+            .iter()
+            .filter(|(prefix, _)| {
+                let prefix: Vec<&str> = prefix.split_whitespace().collect();
+                !prefix.is_empty() && words.starts_with(&prefix)
+            })
+            .max_by_key(|(prefix, _)| prefix.split_whitespace().count());
+        let policy = Policy::default_for(program, args);
+        match best {
+            Some((_, config)) => policy.apply(config),
+            None => policy,
+        }
+    }
+}
+
+/// Output fragments of connection failures that happen before a request is
+/// sent, so retrying cannot repeat its effect.
+const UNSENT_MARKERS: &[&str] = &[
+    "could not resolve host",
+    "no such host",
+    "connection refused",
+    "tls handshake",
+];
+
+/// Further output fragments of failures worth retrying: network trouble,
+/// server errors and rate limits. Anything else (rejected pushes, missing
+/// PRs, bad arguments) fails the same way on every attempt.
+const TRANSIENT_MARKERS: &[&str] = &[
+    "connection reset",
+    "connection timed out",
+    "operation timed out",
+    "unable to access",
+    "the remote end hung up",
+    "early eof",
+    "rate limit",
+    "http 429",
+    "http 500",
+    "http 502",
+    "http 503",
+    "http 504",
+    "bad gateway",
+    "service unavailable",
+];
+
+/// Whether a failed command's output points at a transient problem.
+pub fn is_transient(output: &str) -> bool {
+    let output = output.to_lowercase();
+    UNSENT_MARKERS.iter().chain(TRANSIENT_MARKERS).any(|marker| output.contains(marker))
+}
+
+/// Whether a failed command's output shows it never reached the server.
+pub fn is_unsent(output: &str) -> bool {
+    let output = output.to_lowercase();
+    UNSENT_MARKERS.iter().any(|marker| output.contains(marker))
+}
+
+/// Whether a command may run again after an attempt that timed out or broke
+/// off midway and may already have taken effect.
+///
+/// Forge CLI calls qualify only when they read (`view`, `list`, `gh api`
+/// GETs); creating issues, commenting or merging twice is not harmless.
+/// Pushing the same refs again is a no-op, but a repeated `pull` or `clone`
+/// is not.
+pub fn is_idempotent(program: &str, args: &[&str]) -> bool {
+    match program {
+        "gh" | "glab" => match args {
+            ["api", rest @ ..] => is_get(rest),
+            [_, sub, ..] => matches!(*sub, "view" | "list" | "status" | "checks" | "diff"),
+            _ => false,
+        },
+        "git" => !matches!(args.first(), Some(&("pull" | "clone"))),
+        _ => true,
+    }
+}
+
+/// Whether `gh api` / `glab api` arguments make a GET request: the method
+/// given by `-X`/`--method`, else POST as soon as fields or input are passed.
+fn is_get(args: &[&str]) -> bool {
+    let mut args = args.iter();
+    let mut has_body = false;
+    while let Some(arg) = args.next() {
+        let method = match *arg {
+            "-X" | "--method" => args.next().copied(),
+            _ => arg.strip_prefix("--method=").or_else(|| arg.strip_prefix("-X")),
+        };
+        if let Some(method) = method.filter(|m| !m.is_empty()) {
+            return method.eq_ignore_ascii_case("GET");
+        }
+        has_body |= matches!(*arg, "-f" | "-F" | "--field" | "--raw-field" | "--input")
+            || arg.starts_with("--field=")
+            || arg.starts_with("--raw-field=");
+    }
+    !has_body
+}
+
+/// Whether a command talks to a remote and may fail transiently.
+fn is_network(program: &str, args: &[&str]) -> bool {
+    match program {
+        "gh" | "glab" => true,
+        "git" => matches!(args.first(), Some(&("push" | "fetch" | "pull" | "ls-remote" | "clone"))),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(timeout: Option<u64>, retries: Option<u32>) -> CommandConfig {
+        CommandConfig { timeout, retries, backoff: None }
+    }
+
+    #[test]
+    fn test_defaults() {
+        let policies = Policies::default();
+        assert_eq!(policies.resolve("git", &["push", "-u", "origin", "x"]), Policy::NETWORK);
+        assert_eq!(policies.resolve("gh", &["pr", "create"]), Policy::NETWORK);
+        assert_eq!(policies.resolve("git", &["worktree", "add"]), Policy::LOCAL);
+        assert_eq!(policies.resolve("supabase", &["start"]), Policy::LOCAL);
+    }
+
+    #[test]
+    fn test_longest_prefix_override_wins() {
+        let policies = Policies::new(BTreeMap::from([
+            ("git".to_string(), config(Some(30), None)),
+            ("git push".to_string(), config(None, Some(0))),
+            ("supabase --workdir".to_string(), config(Some(0), None)),
+        ]));
+
+        let push = policies.resolve("git", &["push", "origin", "x"]);
+        assert_eq!(push.attempts, 1);
+        assert_eq!(push.timeout, Policy::NETWORK.timeout);
+        assert_eq!(policies.resolve("git", &["fetch"]).timeout, Some(Duration::from_secs(30)));
+        assert_eq!(policies.resolve("gitk", &[]), Policy::LOCAL);
+        assert_eq!(policies.resolve("supabase", &["--workdir", "x", "start"]).timeout, None);
+    }
+
+    #[test]
+    fn test_is_transient() {
+        assert!(is_transient("fatal: unable to access 'https://x/': Could not resolve host"));
+        assert!(is_transient("HTTP 502: Bad Gateway (https://api.github.com/graphql)"));
+        assert!(!is_transient("no pull requests found for branch \"feat/1-x\""));
+        assert!(!is_transient("! [rejected] feat/1-x -> feat/1-x (non-fast-forward)"));
+
+        assert!(is_unsent("dial tcp: lookup api.github.com: no such host"));
+        assert!(!is_unsent("HTTP 502: Bad Gateway (https://api.github.com/graphql)"));
+        assert!(!is_unsent("read tcp 10.0.0.2:5000: connection reset by peer"));
+    }
+
+    #[test]
+    fn test_is_idempotent() {
+        assert!(is_idempotent("gh", &["pr", "view", "12", "--json", "state"]));
+        assert!(is_idempotent("glab", &["mr", "list"]));
+        assert!(is_idempotent("gh", &["api", "--paginate", "repos/o/r/issues/1/comments"]));
+        assert!(is_idempotent("glab", &["api", "--method", "GET", "x", "-f", "a=b"]));
+        assert!(is_idempotent("git", &["push", "-u", "origin", "x"]));
+        assert!(is_idempotent("git", &["fetch"]));
+
+        assert!(!is_idempotent("gh", &["issue", "create", "--title", "x"]));
+        assert!(!is_idempotent("gh", &["pr", "merge", "12", "--squash"]));
+        assert!(!is_idempotent("gh", &["api", "repos/o/r/issues/1/comments", "-f", "body=x"]));
+        assert!(!is_idempotent("glab", &["api", "--method", "POST", "x"]));
+        assert!(!is_idempotent("gh", &["api", "-XPATCH", "x"]));
+        assert!(!is_idempotent("git", &["clone", "url"]));
+    }
+
+    #[test]
+    fn test_backoff_doubles() {
+        assert_eq!(Policy::NETWORK.delay(1), Duration::from_secs(2));
+        assert_eq!(Policy::NETWORK.delay(2), Duration::from_secs(4));
+        assert_eq!(Policy::LOCAL.delay(1), Duration::ZERO);
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, IsTerminal, Read};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::log::{self, Level};
+
+/// Number of output lines kept to explain a failed streamed command.
+pub const TAIL_LINES: usize = 20;
+
+/// How forwarded output lines are decorated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    /// `  bun install | line`, for humans watching a terminal.
+    Indented,
+    /// `[bun install] line`, for CI logs and other non-terminal readers.
+    Plain,
+}
+
+impl Style {
+    /// Indented on a terminal; plain when stderr is redirected, `CI` is set
+    /// or stderr carries JSON errors.
+    pub fn detect(json: bool) -> Self {
+        if !json && std::io::stderr().is_terminal() && std::env::var_os("CI").is_none() {
+            Style::Indented
+        } else {
+            Style::Plain
+        }
+    }
+
+    pub fn format(self, step: &str, line: &str) -> String {
+        match self {
+            Style::Indented => format!("  {step} | {line}"),
+            Style::Plain => format!("[{step}] {line}"),
+        }
+    }
+}
+
+/// The last [`TAIL_LINES`] lines of a command's combined output.
+#[derive(Default)]
+pub struct Tail {
+    lines: VecDeque<String>,
+}
+
+impl Tail {
+    pub fn push(&mut self, line: String) {
+        if self.lines.len() == TAIL_LINES {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+    }
+
+    pub fn join(&self) -> String {
+        self.lines.iter().map(String::as_str).collect::<Vec<_>>().join("\n")
+    }
+}
+
+/// Forward each line of `reader` to stderr as it arrives, prefixed with the
+/// step name, and keep it in `tail`.
+///
+/// Lines are always written to the log file; the console copy is dropped in
+/// `--quiet` mode.
+pub fn forward(reader: impl Read, step: &str, style: Style, tail: &Arc<Mutex<Tail>>) {
+    let mut reader = BufReader::new(reader);
+    let mut buf = Vec::new();
+    while matches!(reader.read_until(b'\n', &mut buf), Ok(n) if n > 0) {
+        let line = String::from_utf8_lossy(&buf).trim_end().to_string();
+        buf.clear();
+        if log::enabled(Level::Info) {
+            eprintln!("{}", style.format(step, &line));
+        }
+        log::to_file(Level::Info, &format!("[{step}] {line}"));
+        tail.lock().unwrap_or_else(|e| e.into_inner()).push(line);
+    }
+}
+
+/// [`forward`] on a background thread.
+pub fn spawn_forward(
+    reader: impl Read + Send + 'static,
+    step: &str,
+    style: Style,
+    tail: &Arc<Mutex<Tail>>,
+) -> JoinHandle<()> {
+    let (step, tail) = (step.to_string(), Arc::clone(tail));
+    thread::spawn(move || forward(reader, &step, style, &tail))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_style_format() {
+        assert_eq!(Style::Indented.format("bun install", "done"), "  bun install | done");
+        assert_eq!(Style::Plain.format("bun install", "done"), "[bun install] done");
+        assert_eq!(Style::detect(true), Style::Plain);
+    }
+
+    #[test]
+    fn test_forward_keeps_last_lines() {
+        let output: String = (1..=30).map(|i| format!("line {i}\n")).collect();
+        let tail = Arc::new(Mutex::new(Tail::default()));
+        forward(output.as_bytes(), "test", Style::Plain, &tail);
+
+        let tail = tail.lock().unwrap().join();
+        assert!(tail.starts_with("line 11\n"));
+        assert!(tail.ends_with("line 30"));
+        assert_eq!(tail.lines().count(), TAIL_LINES);
+    }
+}
@@ -0,0 +1,193 @@
+use anyhow::{Context, Result};
+
+use crate::error::{Error, ErrorKind};
+use crate::frontmatter::{
+    parse_task_frontmatter, set_field, upsert_field, yaml_value, TaskFrontmatter,
+};
+use crate::helpers::info;
+use crate::repo::Repo;
+
+/// A TASK.md file found under `features/{issue}/{task}/`.
+pub struct TaskEntry {
+    pub number: u32,
+    pub frontmatter: TaskFrontmatter,
+}
+
+/// List all tasks of an issue, sorted by task number.
+///
+/// Directories without a readable TASK.md are skipped.
+pub fn list_tasks(repo: &Repo, issue: u32) -> Result<Vec<TaskEntry>> {
+    let dir = repo.features_dir(issue);
+    if !repo.runner.exists(&dir) {
+        return Ok(Vec::new());
+    }
+
+    let mut tasks: Vec<TaskEntry> = repo
+        .runner
+        .list_dir(&dir)?
+        .into_iter()
+        .filter_map(|name| {
+            let number = name.parse::<u32>().ok()?;
+            let content = repo.runner.read_file(&repo.task_file(issue, number)).ok()?;
+            let frontmatter = parse_task_frontmatter(&content).ok()?;
+            Some(TaskEntry { number, frontmatter })
+        })
+        .collect();
+
+    tasks.sort_by_key(|t| t.number);
+    Ok(tasks)
+}
+
+/// Read the frontmatter of a single task.
+pub fn read_task(repo: &Repo, issue: u32, task: u32) -> Result<TaskFrontmatter> {
+    let path = repo.task_file(issue, task);
+    let content = repo.runner.read_file(&path).with_context(|| {
+        Error::new(ErrorKind::NotFound, format!("Failed to read {}", path.display()))
+            .with_hint(format!("Run `aidd issue plan {issue}` to create the tasks"))
+    })?;
+    parse_task_frontmatter(&content)
+        .with_context(|| Error::new(ErrorKind::Validation, format!("Invalid {}", path.display())))
+}
+
+/// List the tasks stacked directly on the given task.
+pub fn child_tasks(repo: &Repo, issue: u32, parent: u32) -> Result<Vec<TaskEntry>> {
+    Ok(list_tasks(repo, issue)?
+        .into_iter()
+        .filter(|t| t.frontmatter.parent_task == Some(parent))
+        .collect())
+}
+
+/// Find the task whose TASK.md `branchName` matches the given branch.
+pub fn find_task_by_branch(repo: &Repo, issue: u32, branch: &str) -> Option<u32> {
+    list_tasks(repo, issue)
+        .ok()?
+        .into_iter()
+        .find(|t| t.frontmatter.branch_name.as_deref() == Some(branch))
+        .map(|t| t.number)
+}
+
+/// Update the `status` field of a TASK.md.
+pub fn set_task_status(repo: &Repo, issue: u32, task: u32, status: &str) -> Result<()> {
+    update_task_fields(repo, issue, task, &[("status", status)])?;
+    info(&format!("Updated task {issue}/{task} status to {status}"));
+    Ok(())
+}
+
+/// Set string fields in the frontmatter of a TASK.md, adding any that are
+/// missing.
+pub fn update_task_fields(
+    repo: &Repo,
+    issue: u32,
+    task: u32,
+    fields: &[(&str, &str)],
+) -> Result<()> {
+    let path = repo.task_file(issue, task);
+    let mut content = repo.runner.read_file(&path)?;
+    for (key, value) in fields {
+        content = upsert_field(&content, key, &yaml_value(*value)?)
+            .with_context(|| format!("Failed to update {}", path.display()))?;
+    }
+    repo.runner.write_file(&path, &content)
+}
+
+/// Recompute PLAN.md status from its tasks.
+///
+/// Returns the new status if PLAN.md was updated.
+pub fn rollup_plan_status(repo: &Repo, issue: u32) -> Result<Option<String>> {
+    let pf = repo.plan_file(issue);
+    if !repo.runner.exists(&pf) {
+        return Ok(None);
+    }
+
+    let statuses: Vec<String> = list_tasks(repo, issue)?
+        .into_iter()
+        .map(|t| t.frontmatter.status)
+        .collect();
+    let Some(status) = rollup_status(&statuses) else {
+        return Ok(None);
+    };
+
+    let content = repo.runner.read_file(&pf)?;
+    let updated = set_field(&content, "status", status)
+        .with_context(|| format!("Failed to update {}", pf.display()))?;
+    if updated != content {
+        repo.runner.write_file(&pf, &updated)?;
+        info(&format!("Updated PLAN.md status to {status}"));
+    }
+    Ok(Some(status.to_string()))
+}
+
+/// Derive a PLAN status from its task statuses.
+///
+/// Dropped tasks are ignored. All tasks done → `done`; any task started
+/// or done → `doing`; otherwise the PLAN status is left alone.
+pub fn rollup_status(statuses: &[String]) -> Option<&'static str> {
+    let statuses: Vec<&str> =
+        statuses.iter().map(String::as_str).filter(|s| *s != "dropped").collect();
+    if statuses.is_empty() {
+        return None;
+    }
+    if statuses.iter().all(|s| *s == "done") {
+        Some("done")
+    } else if statuses.iter().any(|s| *s == "doing" || *s == "done") {
+        Some("doing")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::forge::fake::FakeForge;
+    use crate::runner::fake::FakeRunner;
+
+    fn statuses(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_rollup_all_done() {
+        assert_eq!(rollup_status(&statuses(&["done", "done"])), Some("done"));
+    }
+
+    #[test]
+    fn test_rollup_partially_done() {
+        assert_eq!(rollup_status(&statuses(&["done", "todo"])), Some("doing"));
+        assert_eq!(rollup_status(&statuses(&["todo", "doing"])), Some("doing"));
+    }
+
+    #[test]
+    fn test_rollup_ignores_dropped() {
+        assert_eq!(rollup_status(&statuses(&["done", "dropped"])), Some("done"));
+        assert_eq!(rollup_status(&statuses(&["dropped"])), None);
+    }
+
+    #[test]
+    fn test_rollup_untouched() {
+        assert_eq!(rollup_status(&statuses(&["todo", "todo"])), None);
+        assert_eq!(rollup_status(&[]), None);
+    }
+
+    #[test]
+    fn test_tasks_are_read_through_the_runner() {
+        let task = |n: u32, status: &str| {
+            format!("---\nissueNumber: 3\ntaskNumber: {n}\nstatus: {status}\n---\n# Task {n}\n")
+        };
+        let runner = FakeRunner::new()
+            .with_file("/repo/features/3/PLAN.md", "---\nissueNumber: 3\nstatus: todo\n---\n")
+            .with_file("/repo/features/3/1/TASK.md", &task(1, "done"))
+            .with_file("/repo/features/3/2/TASK.md", &task(2, "todo"));
+        let forge = FakeForge::new();
+        let repo = Repo::for_test("/repo", &runner, &forge);
+
+        let numbers: Vec<u32> = list_tasks(&repo, 3).unwrap().iter().map(|t| t.number).collect();
+        assert_eq!(numbers, [1, 2]);
+        assert_eq!(rollup_plan_status(&repo, 3).unwrap().as_deref(), Some("doing"));
+        let plan = runner.file(Path::new("/repo/features/3/PLAN.md")).unwrap();
+        assert!(plan.contains("status: doing"));
+        assert!(list_tasks(&repo, 4).unwrap().is_empty());
+    }
+}
@@ -14,6 +14,7 @@ fn test_help_output() {
     assert!(stdout.contains("issue"));
     assert!(stdout.contains("pr"));
     assert!(stdout.contains("status"));
+    assert!(stdout.contains("review-check"));
 }
 
 #[test]
@@ -48,6 +49,7 @@ fn test_pr_help() {
     assert!(output.status.success());
     let stdout = String::from_utf8_lossy(&output.stdout);
     assert!(stdout.contains("create"));
+    assert!(stdout.contains("merge"));
 }
 
 #[test]
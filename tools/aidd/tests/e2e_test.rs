@@ -0,0 +1,478 @@
+mod harness;
+
+use std::process::Command;
+
+use harness::Sandbox;
+
+#[test]
+fn test_wt_ensure_creates_worktree() {
+    let sandbox = Sandbox::new("wt-ensure");
+    let output = sandbox.aidd(&["wt", "ensure", "feat", "12", "add-search"]);
+
+    let wt = sandbox.repo.join(".worktrees/12-add-search");
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), wt.display().to_string());
+    assert!(wt.join("README.md").exists());
+    assert_eq!(sandbox.git_in(&wt, &["rev-parse", "--abbrev-ref", "HEAD"]), "feat/12-add-search");
+    assert_eq!(
+        sandbox.git(&["rev-parse", "feat/12-add-search"]),
+        sandbox.git(&["rev-parse", "main"])
+    );
+
+    // Supabase gets its own ports and the worktree .env points at them.
+    let config = sandbox.read(".worktrees/12-add-search/packages/platform/supabase/config.toml");
+    assert!(config.contains("project_id = \"ai-driven-development-sample-i12\""));
+    assert!(config.contains("port = 55521"));
+    assert!(config.contains("shadow_port = 55520"));
+    let env = sandbox.read(".worktrees/12-add-search/.env");
+    assert!(env.starts_with("APP_NAME=shop\n"));
+    assert!(env.contains("SUPABASE_URL=http://127.0.0.1:55521"));
+    assert!(env.contains("SUPABASE_ANON_KEY=anon-key-for-tests"));
+
+    let calls = sandbox.shim_calls();
+    let commands: Vec<&str> = calls.iter().map(|(_, c)| c.as_str()).collect();
+    assert_eq!(
+        commands,
+        vec![
+            "mise install",
+            "bun install",
+            "supabase --workdir packages/platform/supabase start",
+            "supabase --workdir packages/platform/supabase db reset",
+            "supabase --workdir packages/platform/supabase status -o env",
+        ]
+    );
+    assert!(calls.iter().all(|(cwd, _)| cwd.ends_with(".worktrees/12-add-search")));
+
+    // A second run finds the worktree and does nothing.
+    sandbox.aidd(&["wt", "ensure", "feat", "12", "add-search"]);
+    assert_eq!(sandbox.shim_commands().len(), commands.len());
+}
+
+#[test]
+fn test_wt_remove_deletes_worktree_and_branch() {
+    let sandbox = Sandbox::new("wt-remove");
+    sandbox.aidd(&["wt", "ensure", "fix", "7", "typo"]);
+    sandbox.aidd(&["wt", "remove", "7"]);
+
+    assert!(!sandbox.repo.join(".worktrees/7-typo").exists());
+    assert_eq!(sandbox.git(&["branch", "--list", "fix/7-typo"]), "");
+    assert!(!sandbox.git(&["worktree", "list"]).contains("7-typo"));
+    assert_eq!(
+        sandbox.shim_commands().last().map(String::as_str),
+        Some("supabase --workdir packages/platform/supabase stop")
+    );
+}
+
+#[test]
+fn test_issue_plan_writes_plan() {
+    let sandbox = Sandbox::new("issue-plan");
+    sandbox.add_issue(
+        12,
+        "Add search",
+        "Search for books.\n\n- [ ] Index books\n- [x] Add search page\n",
+    );
+    sandbox.aidd(&["issue", "plan", "12"]);
+
+    let plan = sandbox.read("features/12/PLAN.md");
+    assert!(plan.contains("issueNumber: 12"));
+    assert!(plan.contains("Add search"));
+    assert!(plan.contains("| 1 | Index books |"));
+    assert!(plan.contains("| 2 | Add search page |"));
+    let task = sandbox.read("features/12/1/TASK.md");
+    assert!(task.contains("taskNumber: 1"));
+    assert!(task.contains("Issue #12: Index books"));
+    let checked = sandbox.read("features/12/2/TASK.md");
+    assert!(checked.contains("Add search page"));
+    assert!(checked.contains("status: done"));
+    assert!(plan.contains("status: doing"));
+    assert_eq!(
+        sandbox.shim_commands(),
+        vec![
+            "gh issue view 12 --json number,title,body,labels,state",
+            "gh api --paginate repos/{owner}/{repo}/issues/12/sub_issues",
+        ]
+    );
+}
+
+#[test]
+fn test_issue_plan_recursive_plans_linked_issues() {
+    let sandbox = Sandbox::new("issue-recursive");
+    sandbox.add_issue(12, "Add search", "- [ ] #13\n- [ ] Docs\n");
+    // Links back to its parent; still planned only once.
+    sandbox.add_issue(13, "Search API", "- [ ] Index books\n- [ ] #12\n");
+    sandbox.aidd(&["issue", "plan", "12", "--recursive"]);
+
+    let linked = sandbox.read("features/12/1/TASK.md");
+    assert!(linked.contains("linkedIssue: 13"));
+    assert!(linked.contains("Issue #12: Search API"));
+    assert!(sandbox.read("features/12/PLAN.md").contains("| 1 | Search API | todo | ? |"));
+    assert!(sandbox.read("features/13/PLAN.md").contains("| 1 | Index books | todo | ? |"));
+    assert!(sandbox.read("features/13/2/TASK.md").contains("linkedIssue: 12"));
+    let views = sandbox.shim_commands().iter().filter(|c| c.starts_with("gh issue view")).count();
+    // 12 and its link to 13, then 13 and its link back to 12; no second plan of 12.
+    assert_eq!(views, 4);
+}
+
+#[test]
+fn test_issue_create_from_draft_and_plan() {
+    let sandbox = Sandbox::new("issue-create");
+    sandbox.write(
+        "drafts/search.md",
+        "---\ntitle: Add search\nlabels: [user-story, size:M]\nassignees: alice\n---\n\n\
+         ## 概要\n蔵書を検索できるようにする。\n\n- [ ] Index books\n",
+    );
+    // What the forge returns for the issue once it exists.
+    sandbox.add_issue(13, "Add search", "- [ ] Index books\n");
+    let output = sandbox.aidd(&["issue", "create", "--from", "drafts/search.md", "--plan"]);
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "13\n");
+    assert_eq!(
+        sandbox.shim_commands()[0],
+        "gh issue create --title Add search --body ## 概要 蔵書を検索できるようにする。  \
+         - [ ] Index books --label user-story --label size:M --assignee alice"
+    );
+    assert!(sandbox.read("features/13/PLAN.md").contains("| 1 | Index books | todo | ? |"));
+}
+
+#[test]
+fn test_issue_plan_offline() {
+    let sandbox = Sandbox::new("issue-offline");
+    sandbox.write(
+        "issue.json",
+        r#"{"number":12,"title":"Add search","body":"- [ ] Index books (M)\n- [ ] #42",
+            "labels":[],"state":"OPEN"}"#,
+    );
+    let output = sandbox.aidd(&["issue", "plan", "12", "--from-file", "issue.json"]);
+
+    let plan = sandbox.read("features/12/PLAN.md");
+    assert!(plan.contains("title: Add search"));
+    assert!(plan.contains("| 1 | Index books | todo | M |"));
+    // The linked issue is not looked up offline; the task is kept as written.
+    assert!(plan.contains("| 2 | #42 | todo | ? |"));
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("WARN"));
+
+    sandbox.aidd_with_stdin(
+        &["issue", "plan", "12", "--stdin", "--sync"],
+        "# Add book search\n\n- [ ] Index books (M)\n- [ ] Add ranking\n",
+    );
+    let plan = sandbox.read("features/12/PLAN.md");
+    assert!(plan.contains("title: Add book search"));
+    assert!(plan.contains("| 3 | Add ranking | todo | ? |"));
+    assert!(sandbox.shim_commands().is_empty());
+}
+
+#[test]
+fn test_plan_graph_and_blocked_status() {
+    let sandbox = Sandbox::new("plan-graph");
+    sandbox.add_issue(12, "Deploy", "- [x] Migrate\n- [ ] Deploy\n- [ ] Verify\n");
+    sandbox.aidd(&["issue", "plan", "12"]);
+    for (task, deps) in [(2, "[1]"), (3, "[2]")] {
+        let path = format!("features/12/{task}/TASK.md");
+        let content = sandbox.read(&path);
+        sandbox.write(&path, &content.replace("status:", &format!("dependsOn: {deps}\nstatus:")));
+    }
+
+    let output = sandbox.aidd(&["plan", "graph", "12", "--format", "dot"]);
+    let dot = String::from_utf8_lossy(&output.stdout);
+    assert!(dot.contains("    t3 [label=\"3. Verify [blocked]\"];\n"));
+    assert!(dot.contains("    t1 -> t2;\n    t2 -> t3;\n"));
+    let output = sandbox.aidd(&["status"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("  Task 3 [blocked] waits for 2\n"));
+
+    let content = sandbox.read("features/12/1/TASK.md");
+    sandbox.write("features/12/1/TASK.md", &content.replace("status:", "dependsOn: [3]\nstatus:"));
+    let output = sandbox.aidd_fails(&["plan", "graph", "12"]);
+    assert_eq!(output.status.code(), Some(6));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("cycle: 1 → 3 → 2 → 1"));
+}
+
+#[test]
+fn test_issue_plan_sync_keeps_edits() {
+    let sandbox = Sandbox::new("issue-sync");
+    sandbox.add_issue(12, "Add search", "- [ ] Index books (M)\n- [ ] Add ranking [S]\n");
+    sandbox.aidd(&["issue", "plan", "12"]);
+    let plan = sandbox.read("features/12/PLAN.md");
+    sandbox.write("features/12/PLAN.md", &plan.replace("Issue #12 の実装スコープ。", "Only the API."));
+
+    sandbox.add_issue(12, "Add book search", "- [ ] Index books (M)\n- [ ] Add search API\n");
+    let output = sandbox.aidd(&["issue", "plan", "12", "--sync"]);
+
+    let diff = String::from_utf8_lossy(&output.stdout);
+    assert!(diff.contains("\n-| 2 | Add ranking | todo | S |\n"));
+    assert!(diff.contains("\n+| 2 | Add ranking | dropped | S |\n"));
+    assert!(diff.contains("\n+| 3 | Add search API | todo | ? |\n"));
+    assert!(diff.contains("\n+見積合計: 3 pt（未見積 1 件）\n"));
+    let plan = sandbox.read("features/12/PLAN.md");
+    assert!(plan.contains("title: Add book search"));
+    assert!(plan.contains("Only the API."));
+    assert!(sandbox.read("features/12/2/TASK.md").contains("status: dropped"));
+    assert!(sandbox.read("features/12/3/TASK.md").contains("Issue #12: Add search API"));
+
+    sandbox.aidd(&["plan", "estimate", "12", "3", "l"]);
+    assert!(sandbox.read("features/12/3/TASK.md").contains("estimate: L"));
+    let plan = sandbox.read("features/12/PLAN.md");
+    assert!(plan.contains("| 3 | Add search API | todo | L |"));
+    assert!(plan.contains("見積合計: 8 pt\n"));
+}
+
+#[test]
+fn test_pr_create_pushes_and_opens_pr() {
+    let sandbox = Sandbox::new("pr-create");
+    sandbox.aidd(&["wt", "ensure", "feat", "12", "add-search"]);
+    let wt = sandbox.repo.join(".worktrees/12-add-search");
+    sandbox.write(".worktrees/12-add-search/search.ts", "export const search = () => [];\n");
+    sandbox.git_in(&wt, &["add", "-A"]);
+    sandbox.git_in(&wt, &["commit", "-m", "feat(search): add search function"]);
+    sandbox.write(
+        "features/12/1/TASK.md",
+        "---\nissueNumber: 12\ntaskNumber: 1\nstatus: done\nbranchName: feat/12-add-search\n---\n\n\
+         # Verification\n- [x] bun test\n",
+    );
+
+    let output = sandbox.aidd(&["pr", "create", "12"]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).lines().last(),
+        Some("https://github.test/acme/shop/pull/1")
+    );
+
+    // The branch reached the bare origin.
+    assert_eq!(
+        sandbox.git_in(&sandbox.origin, &["rev-parse", "feat/12-add-search"]),
+        sandbox.git_in(&wt, &["rev-parse", "HEAD"])
+    );
+
+    let commands = sandbox.shim_commands();
+    assert!(commands.contains(&"mise run lint".to_string()));
+    assert!(commands.contains(&"bun test".to_string()));
+    let create = commands.iter().find(|c| c.starts_with("gh pr create")).unwrap();
+    assert!(create.contains("--title [TASK-12-1] feat(search): add search function"));
+    assert!(create.contains("Closes #12"));
+    assert!(create.ends_with("--head feat/12-add-search --base main"));
+}
+
+#[test]
+fn test_pr_create_uses_the_task_worktree() {
+    let sandbox = Sandbox::new("pr-create-task");
+    let tasks = [(1, "index"), (2, "search")];
+    for (task, summary) in tasks {
+        sandbox.write(
+            &format!("features/12/{task}/TASK.md"),
+            &format!("---\nissueNumber: 12\ntaskNumber: {task}\nstatus: doing\n---\n"),
+        );
+        sandbox.aidd(&["wt", "ensure", "feat", "12", summary, "--task", &task.to_string()]);
+        let wt = sandbox.repo.join(format!(".worktrees/12-{summary}"));
+        sandbox.write(&format!(".worktrees/12-{summary}/{summary}.ts"), "export {};\n");
+        sandbox.git_in(&wt, &["add", "-A"]);
+        sandbox.git_in(&wt, &["commit", "-m", &format!("feat: add {summary}")]);
+    }
+
+    // Both worktrees match issue 12; each PR must come from its task's own.
+    for (task, summary) in tasks {
+        sandbox.aidd(&["pr", "create", "12", "--task", &task.to_string(), "--skip-review"]);
+        let commands = sandbox.shim_commands();
+        let create = commands.iter().rfind(|c| c.starts_with("gh pr create")).unwrap();
+        assert!(create.contains(&format!("--title [TASK-12-{task}] feat: add {summary}")));
+        assert!(create.ends_with(&format!("--head feat/12-{summary} --base main")));
+    }
+}
+
+#[test]
+fn test_pr_merge_needs_a_task_with_several_branches() {
+    let sandbox = Sandbox::new("pr-merge-task");
+    for (task, summary) in [(1, "index"), (2, "search")] {
+        sandbox.write(
+            &format!("features/12/{task}/TASK.md"),
+            &format!("---\nissueNumber: 12\ntaskNumber: {task}\nstatus: doing\n---\n"),
+        );
+        sandbox.aidd(&["wt", "ensure", "feat", "12", summary, "--task", &task.to_string()]);
+    }
+
+    let output = sandbox.aidd_fails(&["pr", "merge", "12"]);
+    assert_eq!(output.status.code(), Some(6));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Issue 12 has several branches: feat/12-index, feat/12-search"));
+    assert!(!sandbox.shim_commands().iter().any(|c| c.starts_with("gh pr")));
+}
+
+#[test]
+fn test_commit_uses_the_task_worktree() {
+    let sandbox = Sandbox::new("commit-task");
+    let tasks = [(1, "index"), (2, "search")];
+    for (task, summary) in tasks {
+        sandbox.write(
+            &format!("features/12/{task}/TASK.md"),
+            &format!("---\nissueNumber: 12\ntaskNumber: {task}\nstatus: doing\n---\n"),
+        );
+        sandbox.aidd(&["wt", "ensure", "feat", "12", summary, "--task", &task.to_string()]);
+        sandbox.write(&format!(".worktrees/12-{summary}/{summary}.ts"), "export {};\n");
+    }
+
+    for (task, summary) in tasks {
+        let message = format!("feat: add {summary}");
+        sandbox.aidd(&["commit", "12", "--task", &task.to_string(), "-m", &message]);
+        let subject = sandbox.git(&["log", "-1", "--format=%s", &format!("feat/12-{summary}")]);
+        assert_eq!(subject, message);
+    }
+}
+
+#[test]
+fn test_dry_run_commit_plans_the_commit() {
+    let sandbox = Sandbox::new("dry-run-commit");
+    sandbox.aidd(&["wt", "ensure", "feat", "12", "add-search"]);
+    sandbox.write(".worktrees/12-add-search/search.ts", "export {};\n");
+    let head = sandbox.git(&["rev-parse", "feat/12-add-search"]);
+
+    let output = sandbox.aidd(&["--dry-run", "commit", "12", "-m", "feat: add search"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("git commit -m 'feat: add search'"), "{stdout}");
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("No changes to commit"));
+    assert_eq!(sandbox.git(&["rev-parse", "feat/12-add-search"]), head);
+}
+
+#[test]
+fn test_dry_run_wt_ensure_changes_nothing() {
+    let sandbox = Sandbox::new("dry-run");
+    let output = sandbox.aidd(&["--dry-run", "wt", "ensure", "feat", "12", "add-search"]);
+
+    assert!(!sandbox.repo.join(".worktrees").exists());
+    assert_eq!(sandbox.git(&["branch", "--list", "feat/12-add-search"]), "");
+    assert!(sandbox.shim_commands().is_empty());
+
+    let wt = sandbox.repo.join(".worktrees/12-add-search");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let plan: Vec<&str> = stdout
+        .lines()
+        .skip_while(|l| !l.starts_with("Dry run:"))
+        .skip(1)
+        .map(|l| l.split_once(". ").unwrap().1)
+        .collect();
+    let in_repo = format!("cd {} && ", sandbox.repo.display());
+    let in_wt = format!("cd {} && ", wt.display());
+    assert_eq!(
+        plan,
+        vec![
+            format!("mkdir -p {}", sandbox.repo.join(".worktrees").display()),
+            format!("{in_repo}git worktree add -b feat/12-add-search {} main", wt.display()),
+            format!("{in_wt}mise install"),
+            format!("{in_wt}bun install"),
+            format!(
+                "copy {} -> {}",
+                sandbox.repo.join(".env").display(),
+                wt.join(".env").display()
+            ),
+            format!("write {}", wt.join("packages/platform/supabase/config.toml").display()),
+            format!("{in_wt}supabase --workdir packages/platform/supabase start"),
+            format!("{in_wt}supabase --workdir packages/platform/supabase db reset"),
+            format!("append {}", wt.join(".env").display()),
+        ]
+    );
+}
+
+#[test]
+fn test_commands_are_traced_and_logged() {
+    let sandbox = Sandbox::new("logging");
+    let output = sandbox.aidd(&["-v", "wt", "ensure", "fix", "7", "typo"]);
+
+    let wt = sandbox.repo.join(".worktrees/7-typo");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(&format!("DEBUG: $ bun install (in {})", wt.display())));
+    assert!(stderr.contains("DEBUG: bun exited with 0 after "));
+
+    // Without -v nothing is traced, but the log file still has everything.
+    let output = sandbox.aidd(&["wt", "remove", "7"]);
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("DEBUG"));
+    let logs = sandbox.repo.join(".aidd/logs");
+    let log = std::fs::read_dir(&logs)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .find(|p| p.to_string_lossy().ends_with("-wt-remove.log"))
+        .unwrap();
+    let log = std::fs::read_to_string(log).unwrap();
+    assert!(log.starts_with("aidd wt remove 7\n"));
+    assert!(log.contains("DEBUG: $ git worktree remove"));
+    assert!(log.contains("INFO: Deleting branch: fix/7-typo"));
+    assert_eq!(sandbox.git(&["status", "--porcelain"]), "");
+}
+
+#[test]
+fn test_long_steps_stream_prefixed_output() {
+    let sandbox = Sandbox::new("streaming");
+    let output = sandbox.aidd(&["wt", "ensure", "feat", "12", "add-search"]);
+
+    // stderr is not a terminal here, so lines are prefixed without indentation.
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("\n[bun install] bun install v1.2.0\n"));
+    assert!(stderr.contains("\n[bun install] 3 packages installed\n"));
+
+    sandbox.fail_shim("bun");
+    let output = sandbox.aidd_fails(&["wt", "ensure", "feat", "13", "add-filter"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("[bun install] bun: simulated failure"));
+    // stdout and stderr are read concurrently, so their interleaving may vary.
+    let error = stderr.lines().find(|l| l.starts_with("ERROR: ")).unwrap();
+    assert!(error.ends_with("Command 'bun install' failed (exit 1). Last output:"));
+    let tail = stderr.split_once(error).unwrap().1;
+    assert!(tail.contains("\nbun: resolving packages"));
+    assert!(tail.contains("\nbun: simulated failure"));
+}
+
+#[test]
+fn test_failures_map_to_exit_codes() {
+    let sandbox = Sandbox::new("exit-codes");
+    sandbox.write("notes.md", "draft\n");
+    let output = sandbox.aidd_fails(&["commit", "12", "-m", "wip"]);
+    assert_eq!(output.status.code(), Some(6));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("ERROR: Invalid commit message 'wip'"));
+    assert!(stderr.contains("INFO: Hint: Fix the reported problems and rerun the command"));
+
+    sandbox.fail_shim("bun");
+    let args = ["--error-format", "json", "wt", "ensure", "feat", "12", "add-search"];
+    let output = sandbox.aidd_fails(&args);
+    assert_eq!(output.status.code(), Some(8));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let line = stderr.lines().find(|l| l.starts_with('{')).unwrap();
+    let report: serde_json::Value = serde_json::from_str(line).unwrap();
+    assert_eq!(report["error"]["kind"], "command");
+    assert_eq!(report["error"]["exit_code"], 8);
+    assert!(report["error"]["message"].as_str().unwrap().starts_with("Failed to run bun install"));
+    assert!(report["error"]["hint"].as_str().unwrap().contains(".aidd/logs/"));
+}
+
+#[test]
+fn test_hung_step_times_out() {
+    let sandbox = Sandbox::new("timeout");
+    sandbox.write(".aidd/config.toml", "[commands.\"bun install\"]\ntimeout = 1\n");
+    sandbox.hang_shim("bun");
+
+    let started = std::time::Instant::now();
+    let output = sandbox.aidd_fails(&["wt", "ensure", "feat", "12", "add-search"]);
+
+    assert!(started.elapsed() < std::time::Duration::from_secs(10));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr
+        .contains("ERROR: Failed to run bun install: Command 'bun install' timed out after 1s"));
+}
+
+#[test]
+fn test_interrupt_removes_half_built_worktree() {
+    let sandbox = Sandbox::new("interrupt");
+    sandbox.hang_shim("bun");
+
+    let child = sandbox.start_aidd(&["wt", "ensure", "feat", "12", "add-search"]);
+    let started = std::time::Instant::now();
+    while !sandbox.shim_commands().iter().any(|c| c == "bun install") {
+        assert!(started.elapsed() < std::time::Duration::from_secs(10), "bun never started");
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    let status = Command::new("kill").args(["-INT", &child.id().to_string()]).status().unwrap();
+    assert!(status.success());
+    let output = child.wait_with_output().unwrap();
+
+    assert_eq!(output.status.code(), Some(130));
+    assert!(started.elapsed() < std::time::Duration::from_secs(10));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Cleaning up: remove half-built worktree"));
+    assert!(stderr.contains("ERROR: Interrupted"));
+    assert!(!sandbox.repo.join(".worktrees/12-add-search").exists());
+    assert_eq!(sandbox.git(&["branch", "--list", "feat/12-add-search"]), "");
+}
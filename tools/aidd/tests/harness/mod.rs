@@ -0,0 +1,281 @@
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Output, Stdio};
+
+/// Tools replaced by logging shims during end-to-end tests.
+const SHIMS: &[&str] = &["gh", "bun", "mise", "supabase"];
+
+/// Shared by every shim: log the call, then answer a few known commands.
+const SHIM_SCRIPT: &str = r#"#!/bin/sh
+name=$(basename "$0")
+printf '%s\t%s %s\n' "$PWD" "$name" "$(echo "$*" | tr '\n' ' ')" >> "$AIDD_SHIM_LOG"
+if [ -f "$AIDD_SHIM_DATA/hang-$name" ]; then
+  sleep 30
+fi
+if [ -f "$AIDD_SHIM_DATA/fail-$name" ]; then
+  echo "$name: resolving packages"
+  echo "$name: simulated failure" >&2
+  exit 1
+fi
+case "$name $1 $2" in
+  "gh issue view")
+    cat "$AIDD_SHIM_DATA/issue-$3.json"
+    ;;
+  "gh issue create")
+    echo "https://github.test/acme/shop/issues/13"
+    ;;
+  "gh api --paginate")
+    case "$3" in
+      */sub_issues) echo '[]' ;;
+    esac
+    ;;
+  "gh pr view")
+    if [ -f "$AIDD_SHIM_DATA/pr.json" ]; then
+      cat "$AIDD_SHIM_DATA/pr.json"
+    else
+      echo "no pull requests found for branch \"$3\"" >&2
+      exit 1
+    fi
+    ;;
+  "gh pr create")
+    while [ $# -gt 0 ]; do
+      case "$1" in
+        --title) title=$2 ;;
+        --head) head=$2 ;;
+        --base) base=$2 ;;
+      esac
+      shift
+    done
+    printf '{"number":1,"title":"%s","headRefName":"%s","baseRefName":"%s","state":"OPEN","url":"https://github.test/acme/shop/pull/1"}' \
+      "$title" "$head" "$base" > "$AIDD_SHIM_DATA/pr.json"
+    echo "https://github.test/acme/shop/pull/1"
+    ;;
+  "bun install ")
+    echo "bun install v1.2.0"
+    echo "3 packages installed"
+    ;;
+  "supabase --workdir packages/platform/supabase")
+    [ "$3" = "status" ] && echo 'ANON_KEY="anon-key-for-tests"'
+    ;;
+esac
+exit 0
+"#;
+
+/// Supabase config in the sandbox repo, using the default ports.
+const SUPABASE_CONFIG: &str = "project_id = \"ai-driven-development-sample\"\n\n\
+    [api]\nport = 54321\n\n[db]\nport = 54322\nshadow_port = 54320\n\n\
+    [studio]\nport = 54323\n";
+
+/// A throwaway repository with a bare `origin` and shimmed tools on PATH.
+///
+/// Layout under a fresh temp directory:
+/// - `origin.git`: bare remote
+/// - `repo`: clone with one commit on `main`, pushed to `origin`
+/// - `bin`: `gh`, `bun`, `mise` and `supabase` shims
+/// - `data`: canned shim responses (issues, the created PR)
+/// - `shims.log`: one line per shim call, `<cwd>\t<program> <args>` (newlines
+///   in arguments become spaces)
+pub struct Sandbox {
+    pub dir: PathBuf,
+    pub repo: PathBuf,
+    pub origin: PathBuf,
+}
+
+impl Sandbox {
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("aidd-e2e-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let sandbox = Self {
+            repo: dir.join("repo"),
+            origin: dir.join("origin.git"),
+            dir,
+        };
+
+        fs::create_dir_all(sandbox.dir.join("bin")).unwrap();
+        fs::create_dir_all(sandbox.dir.join("data")).unwrap();
+        fs::write(sandbox.dir.join("shims.log"), "").unwrap();
+        for shim in SHIMS {
+            let path = sandbox.dir.join("bin").join(shim);
+            fs::write(&path, SHIM_SCRIPT).unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        sandbox.git_in(&sandbox.dir, &["init", "--bare", "-b", "main", "origin.git"]);
+        sandbox.git_in(&sandbox.dir, &["init", "-b", "main", "repo"]);
+        sandbox.write(".gitignore", ".worktrees/\n.env\n");
+        sandbox.write("README.md", "# shop\n");
+        sandbox.write("packages/platform/supabase/config.toml", SUPABASE_CONFIG);
+        sandbox.write(".env", "APP_NAME=shop\n");
+        sandbox.git(&["add", "-A"]);
+        sandbox.git(&["commit", "-m", "chore: initial commit"]);
+        sandbox.git(&["remote", "add", "origin", &sandbox.origin.to_string_lossy()]);
+        sandbox.git(&["push", "-u", "origin", "main"]);
+        fs::write(sandbox.dir.join("shims.log"), "").unwrap();
+        sandbox
+    }
+
+    /// Run `aidd` in the repository root.
+    pub fn aidd(&self, args: &[&str]) -> Output {
+        self.aidd_in(&self.repo, args)
+    }
+
+    /// Run `aidd` in the repository root, expecting it to fail.
+    pub fn aidd_fails(&self, args: &[&str]) -> Output {
+        let output = self.spawn_aidd(&self.repo, args);
+        assert!(!output.status.success(), "aidd {} unexpectedly succeeded", args.join(" "));
+        output
+    }
+
+    /// Run `aidd` in `dir` with the shims first on PATH.
+    pub fn aidd_in(&self, dir: &Path, args: &[&str]) -> Output {
+        let output = self.spawn_aidd(dir, args);
+        assert!(
+            output.status.success(),
+            "aidd {} failed:\n{}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        output
+    }
+
+    /// Run `aidd` in the repository root with `input` on stdin.
+    pub fn aidd_with_stdin(&self, args: &[&str], input: &str) -> Output {
+        let mut child = self
+            .aidd_command(&self.repo, args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+        let output = child.wait_with_output().unwrap();
+        assert!(
+            output.status.success(),
+            "aidd {} failed:\n{}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        output
+    }
+
+    /// Start `aidd` in the repository root without waiting for it.
+    pub fn start_aidd(&self, args: &[&str]) -> Child {
+        self.aidd_command(&self.repo, args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap()
+    }
+
+    fn spawn_aidd(&self, dir: &Path, args: &[&str]) -> Output {
+        self.aidd_command(dir, args).output().unwrap()
+    }
+
+    fn aidd_command(&self, dir: &Path, args: &[&str]) -> Command {
+        let path = format!("{}:{}", self.dir.join("bin").display(), std::env::var("PATH").unwrap());
+        let mut command = Command::new(env!("CARGO_BIN_EXE_aidd"));
+        command
+            .args(args)
+            .current_dir(dir)
+            .env("PATH", path)
+            .env("AIDD_SHIM_LOG", self.dir.join("shims.log"))
+            .env("AIDD_SHIM_DATA", self.dir.join("data"))
+            .envs(Self::git_identity());
+        command
+    }
+
+    /// Run git in the repository root and return its trimmed stdout.
+    pub fn git(&self, args: &[&str]) -> String {
+        self.git_in(&self.repo, args)
+    }
+
+    /// Run git in `dir` and return its trimmed stdout.
+    pub fn git_in(&self, dir: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .envs(Self::git_identity())
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "git {} failed:\n{}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    /// Write a file relative to the repository root, creating parent directories.
+    pub fn write(&self, path: &str, content: &str) {
+        let path = self.repo.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    /// Read a file relative to the repository root.
+    pub fn read(&self, path: &str) -> String {
+        fs::read_to_string(self.repo.join(path))
+            .unwrap_or_else(|e| panic!("failed to read {path}: {e}"))
+    }
+
+    /// Serve an issue from the `gh` shim.
+    pub fn add_issue(&self, number: u32, title: &str, body: &str) {
+        let issue = format!(
+            r#"{{"number":{number},"title":{},"body":{},"labels":[],"state":"OPEN"}}"#,
+            json_string(title),
+            json_string(body)
+        );
+        fs::write(self.dir.join(format!("data/issue-{number}.json")), issue).unwrap();
+    }
+
+    /// Make every call of the `name` shim print some output and fail.
+    pub fn fail_shim(&self, name: &str) {
+        fs::write(self.dir.join(format!("data/fail-{name}")), "").unwrap();
+    }
+
+    /// Make every call of the `name` shim hang for 30 seconds.
+    pub fn hang_shim(&self, name: &str) {
+        fs::write(self.dir.join(format!("data/hang-{name}")), "").unwrap();
+    }
+
+    /// Shim calls so far as `(cwd, "program args")`.
+    pub fn shim_calls(&self) -> Vec<(PathBuf, String)> {
+        fs::read_to_string(self.dir.join("shims.log"))
+            .unwrap()
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .map(|(cwd, command)| (PathBuf::from(cwd), command.trim_end().to_string()))
+            .collect()
+    }
+
+    /// Shim command lines so far.
+    pub fn shim_commands(&self) -> Vec<String> {
+        self.shim_calls().into_iter().map(|(_, command)| command).collect()
+    }
+
+    fn git_identity() -> [(&'static str, &'static str); 5] {
+        [
+            ("GIT_AUTHOR_NAME", "aidd"),
+            ("GIT_AUTHOR_EMAIL", "aidd@example.com"),
+            ("GIT_COMMITTER_NAME", "aidd"),
+            ("GIT_COMMITTER_EMAIL", "aidd@example.com"),
+            ("GIT_CONFIG_NOSYSTEM", "1"),
+        ]
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        if !std::thread::panicking() {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let escaped = s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+    format!("\"{escaped}\"")
+}